@@ -0,0 +1,80 @@
+use bencode_decode::{decode_slice, decode_strict, from_bytes, from_reader, Event, Parser};
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use std::fs;
+
+fn torrent(c: &mut Criterion) {
+    let raw = fs::read("./test/ubuntu-18.04.4-live-server-amd64.iso.torrent").unwrap();
+    let mut group = c.benchmark_group("torrent");
+    group.throughput(Throughput::Bytes(raw.len() as u64));
+    group.bench_function("from_bytes", |b| {
+        b.iter(|| from_bytes(black_box(&raw)).unwrap())
+    });
+    group.bench_function("decode_slice", |b| {
+        b.iter(|| decode_slice(black_box(&raw)).unwrap())
+    });
+    group.bench_function("events", |b| {
+        b.iter(|| {
+            let mut parser = Parser::new(black_box(raw.as_slice()));
+            while parser.next_event().unwrap() != Event::Eof {}
+        })
+    });
+    group.finish();
+}
+
+/// Many small tokens, where the per-token overhead dominates.
+fn small_tokens(c: &mut Criterion) {
+    let mut input = b"l".to_vec();
+    for i in 0..10_000 {
+        input.extend_from_slice(format!("d4:spami{}e3:egg5:bacone", i).as_bytes());
+    }
+    input.push(b'e');
+    let mut group = c.benchmark_group("small_tokens");
+    group.throughput(Throughput::Bytes(input.len() as u64));
+    group.bench_function("from_bytes", |b| {
+        b.iter(|| from_bytes(black_box(&input)).unwrap())
+    });
+    // A reader that isn't a slice, like a file or socket.
+    group.bench_function("from_reader_buffered", |b| {
+        b.iter(|| {
+            let mut parser = Parser::new(std::io::Cursor::new(black_box(&input)));
+            parser.set_buffer_capacity(8 * 1024);
+            decode_strict(&mut parser).unwrap()
+        })
+    });
+    group.bench_function("from_reader_unbuffered", |b| {
+        b.iter(|| from_reader(std::io::Cursor::new(black_box(&input))).unwrap())
+    });
+    group.finish();
+}
+
+/// Many small messages from separate sources, like DHT queries.
+fn messages(c: &mut Criterion) {
+    let messages: Vec<_> = (0..100_000)
+        .map(|i| format!("d1:ad2:id20:{:020}e1:q4:ping1:t2:aa1:y1:qe", i).into_bytes())
+        .collect();
+    let mut group = c.benchmark_group("messages");
+    group.throughput(Throughput::Elements(messages.len() as u64));
+    group.bench_function("new", |b| {
+        b.iter(|| {
+            for message in &messages {
+                let mut parser = Parser::new(black_box(message.as_slice()));
+                parser.set_buffer_capacity(8 * 1024);
+                decode_strict(&mut parser).unwrap();
+            }
+        })
+    });
+    group.bench_function("reset", |b| {
+        let mut parser = Parser::new(&[][..]);
+        parser.set_buffer_capacity(8 * 1024);
+        b.iter(|| {
+            for message in &messages {
+                parser.reset(black_box(message.as_slice()));
+                decode_strict(&mut parser).unwrap();
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, torrent, small_tokens, messages);
+criterion_main!(benches);
@@ -0,0 +1,75 @@
+use bencode_decode::{decode_interned, from_bytes, Parser};
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Keeps track of the number of bytes currently allocated.
+struct Counting;
+
+static LIVE_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        LIVE_BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        LIVE_BYTES.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        LIVE_BYTES.fetch_add(new_size, Ordering::Relaxed);
+        LIVE_BYTES.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+/// The number of bytes the value returned by `f` holds on to.
+fn retained_bytes<T>(f: impl FnOnce() -> T) -> usize {
+    let before = LIVE_BYTES.load(Ordering::Relaxed);
+    let value = f();
+    let retained = LIVE_BYTES.load(Ordering::Relaxed) - before;
+    drop(value);
+    retained
+}
+
+/// A torrent of 10,000 files, whose keys repeat for every file.
+fn many_files(c: &mut Criterion) {
+    let mut input = b"d4:infod5:filesl".to_vec();
+    for i in 0..10_000 {
+        input.extend_from_slice(
+            format!("d6:lengthi{}e4:pathl4:data12:{:08}.binee", i, i).as_bytes(),
+        );
+    }
+    input.extend_from_slice(b"e4:name3:dir12:piece lengthi16384e6:pieces0:ee");
+
+    // Interning shares the `length`, `path` and `data` strings between all
+    // files, where a plain `Value` holds a copy of each for every file, so
+    // it saves at least their 14 bytes per file.
+    let plain = retained_bytes(|| from_bytes(&input).unwrap());
+    let interned = retained_bytes(|| decode_interned(&mut Parser::new(input.as_slice())).unwrap());
+    assert!(
+        interned + 10_000 * 14 <= plain,
+        "{} bytes interned, {} plain",
+        interned,
+        plain
+    );
+
+    let mut group = c.benchmark_group("many_files");
+    group.throughput(Throughput::Bytes(input.len() as u64));
+    group.bench_function("from_bytes", |b| {
+        b.iter(|| from_bytes(black_box(&input)).unwrap())
+    });
+    group.bench_function("decode_interned", |b| {
+        b.iter(|| decode_interned(&mut Parser::new(black_box(input.as_slice()))).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, many_files);
+criterion_main!(benches);
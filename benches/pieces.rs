@@ -0,0 +1,69 @@
+use bencode_decode::{decode_strict, from_bytes, Parser};
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Counts the allocations and reallocations of at least `LARGE` bytes.
+struct Counting;
+
+const LARGE: usize = 1024 * 1024;
+static LARGE_ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        if layout.size() >= LARGE {
+            LARGE_ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        }
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        if new_size >= LARGE {
+            LARGE_ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        }
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+fn large_allocations<T>(f: impl FnOnce() -> T) -> usize {
+    let before = LARGE_ALLOCATIONS.load(Ordering::Relaxed);
+    drop(f());
+    LARGE_ALLOCATIONS.load(Ordering::Relaxed) - before
+}
+
+/// A torrent of a 40 GiB file in 256 KiB pieces, with over 3 MiB of hashes in
+/// `pieces`.
+fn pieces(c: &mut Criterion) {
+    let pieces = vec![0xab; 160 * 1024 * 20];
+    let mut input =
+        b"d4:infod6:lengthi42949672960e4:name4:spam12:piece lengthi262144e6:pieces".to_vec();
+    input.extend_from_slice(format!("{}:", pieces.len()).as_bytes());
+    input.extend_from_slice(&pieces);
+    input.extend_from_slice(b"ee");
+
+    // Reserving the declared length up front reads `pieces` into a single
+    // allocation, where growing it as data arrives takes several.
+    let decode_growing = || decode_strict(&mut Parser::new(input.as_slice())).unwrap();
+    assert!(large_allocations(decode_growing) > 1);
+    assert_eq!(large_allocations(|| from_bytes(&input).unwrap()), 1);
+
+    let mut group = c.benchmark_group("pieces");
+    group.throughput(Throughput::Bytes(input.len() as u64));
+    group.bench_function("growing", |b| {
+        b.iter(|| decode_strict(&mut Parser::new(black_box(input.as_slice()))).unwrap())
+    });
+    group.bench_function("preallocated", |b| {
+        b.iter(|| from_bytes(black_box(&input)).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, pieces);
+criterion_main!(benches);
@@ -0,0 +1,24 @@
+//! Pretty-prints the bencoded values read from stdin, one after another.
+//!
+//! ```sh
+//! cargo run --example bdecode < test/ubuntu-18.04.4-live-server-amd64.iso.torrent
+//! ```
+
+use bencode_decode::{decode_values, Parser};
+use std::io::{self, BufReader};
+use std::process;
+
+fn main() {
+    let stdin = io::stdin();
+    let mut parser = Parser::new(BufReader::new(stdin.lock()));
+    parser.set_trailing_whitespace(true);
+    for value in decode_values(&mut parser) {
+        match value {
+            Ok(value) => println!("{:#}", value),
+            Err(e) => {
+                eprintln!("bdecode: {}", e);
+                process::exit(1);
+            }
+        }
+    }
+}
@@ -0,0 +1,23 @@
+//! Decoding arbitrary bytes must fail gracefully, never panic, and agree
+//! between the streaming and the borrowing decoder.
+
+#![no_main]
+
+use bencode_decode::{decode_slice, decode_spanned, decode_strict, Parser};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let owned = decode_strict(&mut Parser::new(data));
+    let borrowed = decode_slice(data);
+    assert_eq!(
+        owned.as_ref().ok(),
+        borrowed.as_ref().ok().map(|value| value.to_value()).as_ref()
+    );
+    let _ = decode_spanned(&mut Parser::new(data));
+    let mut parser = Parser::new(data);
+    while let Ok(event) = parser.next_event() {
+        if event == bencode_decode::Event::Eof {
+            break;
+        }
+    }
+});
@@ -0,0 +1,12 @@
+//! Encoding any value and decoding it again yields the same value.
+
+#![no_main]
+
+use bencode_decode::{decode_slice, encode, from_bytes, Value};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|value: Value| {
+    let encoded = encode(&value);
+    assert_eq!(from_bytes(&encoded).unwrap(), value);
+    assert_eq!(decode_slice(&encoded).unwrap().to_value(), value);
+});
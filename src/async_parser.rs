@@ -0,0 +1,274 @@
+use crate::{
+    token, Builder, Error, Event, ParseResult, ParseResult::*, Plain, Tokenizer, Value,
+    DEFAULT_MAX_DEPTH, PREALLOC_LIMIT,
+};
+use alloc::{vec, vec::Vec};
+use std::io::ErrorKind;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// The counterpart of `Parser` for a `tokio::io::AsyncRead`, for decoding
+/// bencoded messages inside an event loop. Bytes are read one at a time
+/// while scanning tokens, so wrap unbuffered sources like a `TcpStream` in
+/// a `tokio::io::BufReader`.
+pub struct AsyncParser<R> {
+    reader: R,
+    max_depth: usize,
+    max_items: usize,
+    strict: bool,
+    pos: usize,
+    token_start: usize,
+    tokenizer: Tokenizer,
+}
+
+impl<R: AsyncRead + Unpin> AsyncParser<R> {
+    pub fn new(reader: R) -> Self {
+        Self::with_max_depth(reader, DEFAULT_MAX_DEPTH)
+    }
+
+    /// See `Parser::with_max_depth`.
+    pub fn with_max_depth(reader: R, max_depth: usize) -> Self {
+        Self {
+            reader,
+            max_depth,
+            max_items: usize::MAX,
+            strict: false,
+            pos: 0,
+            token_start: 0,
+            tokenizer: Tokenizer::new(),
+        }
+    }
+
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        self.max_depth = max_depth;
+    }
+
+    /// See `Parser::set_max_byte_string_len`.
+    pub fn set_max_byte_string_len(&mut self, max_len: usize) {
+        self.tokenizer.set_max_byte_string_len(max_len);
+    }
+
+    /// See `Parser::set_max_items`.
+    pub fn set_max_items(&mut self, max_items: usize) {
+        self.max_items = max_items;
+    }
+
+    /// See `Parser::set_strict`.
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    /// See `Parser::set_big_integers`.
+    pub fn set_big_integers(&mut self, allow: bool) {
+        self.tokenizer.set_big_integers(allow);
+    }
+
+    /// See `Parser::position`.
+    pub fn position(&self) -> u64 {
+        self.pos as u64
+    }
+
+    /// Reads the next token, returning `ParseResult::EOF` once the reader is
+    /// exhausted.
+    pub async fn next_token(&mut self) -> Result<ParseResult, Error> {
+        self.token_start = self.pos;
+        self.tokenizer.discard();
+        loop {
+            let offset = self.pos;
+            let event = match self.read_byte().await? {
+                Some(byte) => self.tokenizer.event(byte, offset)?,
+                None => Some(self.tokenizer.end(offset)?),
+            };
+            match event {
+                Some(Event::ByteString(size)) => {
+                    let str = self.read_payload(size).await?;
+                    return Ok(ValueType(Value::ByteString(str)));
+                }
+                Some(event) => return Ok(token(event)),
+                None => {}
+            }
+        }
+    }
+
+    async fn read_byte(&mut self) -> Result<Option<u8>, Error> {
+        let mut buf = [0; 1];
+        loop {
+            match self.reader.read(&mut buf).await {
+                Ok(0) => return Ok(None),
+                Ok(_) => {
+                    self.pos += 1;
+                    return Ok(Some(buf[0]));
+                }
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(Error::io(e, self.pos)),
+            }
+        }
+    }
+
+    async fn read_payload(&mut self, size: usize) -> Result<Vec<u8>, Error> {
+        let mut str = vec![0; size.min(PREALLOC_LIMIT)];
+        let mut filled = 0;
+        while filled < size {
+            if filled == str.len() {
+                str.resize(size.min(filled * 2), 0);
+            }
+            match self.reader.read(&mut str[filled..]).await {
+                Ok(0) => {
+                    return Err(Error::TruncatedByteString {
+                        declared: size,
+                        available: filled,
+                        offset: self.pos,
+                    })
+                }
+                Ok(read) => {
+                    filled += read;
+                    self.pos += read;
+                }
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(Error::io(e, self.pos)),
+            }
+        }
+        Ok(str)
+    }
+}
+
+/// Decodes the next value from `parser`, like `decode`. The reader is left
+/// right after it, so consecutive messages on a stream can be read by
+/// calling this repeatedly.
+///
+/// ```
+/// use bencode_decode::{decode_async, AsyncParser, Value};
+///
+/// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+/// let mut parser = AsyncParser::new("i1e4:spam".as_bytes());
+/// assert_eq!(decode_async(&mut parser).await.unwrap(), Value::Integer(1));
+/// assert_eq!(
+///     decode_async(&mut parser).await.unwrap(),
+///     Value::ByteString(b"spam".to_vec())
+/// );
+/// # });
+/// ```
+pub async fn decode_async<R: AsyncRead + Unpin>(
+    parser: &mut AsyncParser<R>,
+) -> Result<Value, Error> {
+    let mut builder = Builder::new(Plain, parser.max_depth, parser.strict, parser.max_items);
+    loop {
+        let token = parser.next_token().await?;
+        if let Some(value) = builder.push(token, parser.token_start..parser.pos)? {
+            return Ok(value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{decode, Parser};
+    use std::fs;
+
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    #[test]
+    fn matches_sync_decode() {
+        let raw = fs::read("./test/ubuntu-18.04.4-live-server-amd64.iso.torrent").unwrap();
+        let mut parser = AsyncParser::new(raw.as_slice());
+        let value = block_on(decode_async(&mut parser)).unwrap();
+        assert_eq!(parser.position(), raw.len() as u64);
+        assert_eq!(value, decode(&mut Parser::new(raw.as_slice())).unwrap());
+    }
+
+    #[test]
+    fn limits_and_errors() {
+        let decode = |input: &'static str, configure: fn(&mut AsyncParser<&[u8]>)| {
+            let mut parser = AsyncParser::new(input.as_bytes());
+            configure(&mut parser);
+            block_on(decode_async(&mut parser))
+        };
+        assert!(matches!(
+            decode("lli1eee", |p| p.set_max_depth(1)),
+            Err(Error::DepthLimitExceeded { offset: 1 })
+        ));
+        assert!(matches!(
+            decode("3:abc", |p| p.set_max_byte_string_len(2)),
+            Err(Error::LengthLimitExceeded { offset: 0 })
+        ));
+        assert!(matches!(
+            decode("li1ei2ee", |p| p.set_max_items(2)),
+            Err(Error::ItemLimitExceeded { offset: 4 })
+        ));
+        assert!(matches!(
+            decode("d1:bi1e1:ai2ee", |p| p.set_strict(true)),
+            Err(Error::UnsortedKeys { offset: 7 })
+        ));
+        assert!(matches!(
+            decode("i-9223372036854775809e", |p| p.set_big_integers(false)),
+            Err(Error::IntegerOverflow { offset: 0, .. })
+        ));
+        assert!(matches!(
+            decode("l5:ab", |_| {}),
+            Err(Error::TruncatedByteString {
+                declared: 5,
+                available: 2,
+                offset: 5
+            })
+        ));
+        assert!(matches!(
+            decode("li01ee", |_| {}),
+            Err(Error::InvalidInteger { offset: 1 })
+        ));
+    }
+
+    /// Fails every other read with `ErrorKind::Interrupted`, starting with
+    /// the first.
+    struct Interrupting<'a> {
+        input: &'a [u8],
+        interrupt: bool,
+    }
+
+    impl AsyncRead for Interrupting<'_> {
+        fn poll_read(
+            mut self: std::pin::Pin<&mut Self>,
+            cx: &mut std::task::Context<'_>,
+            buf: &mut tokio::io::ReadBuf<'_>,
+        ) -> std::task::Poll<std::io::Result<()>> {
+            self.interrupt = !self.interrupt;
+            if self.interrupt {
+                return std::task::Poll::Ready(Err(ErrorKind::Interrupted.into()));
+            }
+            std::pin::Pin::new(&mut self.input).poll_read(cx, buf)
+        }
+    }
+
+    #[test]
+    fn endless_digits() {
+        let mut parser = AsyncParser::new(tokio::io::repeat(b'1'));
+        assert!(matches!(
+            block_on(decode_async(&mut parser)),
+            Err(Error::InvalidLength { offset: 0 })
+        ));
+        assert_eq!(parser.position(), 21);
+
+        let mut parser = AsyncParser::new(b"i".chain(tokio::io::repeat(b'1')));
+        parser.set_big_integers(false);
+        assert!(matches!(
+            block_on(decode_async(&mut parser)),
+            Err(Error::IntegerOverflow { digits, offset: 0 }) if digits == "11111111111111111111"
+        ));
+    }
+
+    #[test]
+    fn interrupted() {
+        let mut parser = AsyncParser::new(Interrupting {
+            input: b"d4:spaml1:ai-1eee",
+            interrupt: false,
+        });
+        assert_eq!(
+            block_on(decode_async(&mut parser)).unwrap(),
+            crate::from_bytes(b"d4:spaml1:ai-1eee").unwrap()
+        );
+    }
+}
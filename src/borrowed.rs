@@ -0,0 +1,384 @@
+use crate::{
+    parse_integer, Builder, Error, Event, Key, Limits, ParsedInteger, Scalar, Token, Tokenizer,
+    Tree, Value,
+};
+use alloc::{collections::BTreeMap, string::ToString, vec::Vec};
+use core::ops::Range;
+
+/// A decoded value borrowing its byte strings from the input buffer, as
+/// produced by `decode_slice`.
+#[derive(PartialEq, Ord, PartialOrd, Eq, Hash, Debug, Clone)]
+pub enum ValueRef<'a> {
+    ByteString(&'a [u8]),
+    Integer(i64),
+    /// See `Value::BigInteger`.
+    BigInteger(&'a str),
+    List(Vec<ValueRef<'a>>),
+    Dictionary(BTreeMap<&'a [u8], ValueRef<'a>>),
+}
+
+impl<'a> ValueRef<'a> {
+    /// Returns the bytes if this is a `ByteString`. They borrow from the
+    /// input rather than from this value, so they stay usable after the tree
+    /// is dropped.
+    pub fn as_bytes(&self) -> Option<&'a [u8]> {
+        match self {
+            ValueRef::ByteString(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+
+    /// Looks up `key` if this is a `Dictionary`, like `Value::get`.
+    pub fn get(&self, key: impl AsRef<[u8]>) -> Option<&ValueRef<'a>> {
+        match self {
+            ValueRef::Dictionary(map) => map.get(key.as_ref()),
+            _ => None,
+        }
+    }
+
+    /// Copies this value and all of its children into an owned `Value`.
+    pub fn to_value(&self) -> Value {
+        match self {
+            ValueRef::ByteString(bytes) => Value::ByteString(bytes.to_vec()),
+            ValueRef::Integer(int) => Value::Integer(*int),
+            ValueRef::BigInteger(digits) => Value::BigInteger(digits.to_string()),
+            ValueRef::List(list) => Value::List(list.iter().map(ValueRef::to_value).collect()),
+            ValueRef::Dictionary(map) => Value::Dictionary(
+                map.iter()
+                    .map(|(key, value)| (Key::from(*key), value.to_value()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// Decodes the single bencoded value making up `input` without copying any
+/// byte strings; they are returned as slices into `input` instead. Fails
+/// with `Error::TrailingData` if `input` continues after the value.
+///
+/// The result lives as long as `input` does and nothing else, so `input` can
+/// just as well be a memory-mapped file: large byte strings like `pieces`
+/// then point into the mapping and are never copied, for as long as the
+/// mapping is kept alive.
+///
+/// ```
+/// use bencode_decode::{decode_slice, ValueRef};
+///
+/// let input = b"l4:spami42ee";
+/// let value = decode_slice(input).unwrap();
+/// assert_eq!(
+///     value,
+///     ValueRef::List(vec![ValueRef::ByteString(b"spam"), ValueRef::Integer(42)])
+/// );
+/// ```
+pub fn decode_slice<'a>(input: &'a [u8]) -> Result<ValueRef<'a>, Error> {
+    decode_slice_with(input, &Limits::default(), false)
+}
+
+/// Like `decode_slice`, but enforces `limits`, and with `strict`, requires
+/// dictionary keys to be sorted and unique like `Parser::set_strict` does.
+/// Since the input is already in memory, `max_total_bytes` fails on a value
+/// that extends past it rather than bounding what is read.
+///
+/// ```
+/// use bencode_decode::{decode_slice_with, Error, Limits};
+///
+/// let limits = Limits {
+///     max_string_len: 3,
+///     ..Limits::default()
+/// };
+/// assert!(decode_slice_with(b"l3:abce", &limits, false).is_ok());
+/// assert!(matches!(
+///     decode_slice_with(b"l4:spame", &limits, false),
+///     Err(Error::LengthLimitExceeded { offset: 1 })
+/// ));
+/// assert!(matches!(
+///     decode_slice_with(b"d1:bi1e1:ai2ee", &Limits::default(), true),
+///     Err(Error::UnsortedKeys { offset: 7 })
+/// ));
+/// ```
+pub fn decode_slice_with<'a>(
+    input: &'a [u8],
+    limits: &Limits,
+    strict: bool,
+) -> Result<ValueRef<'a>, Error> {
+    let mut parser = SliceParser::with_limits(input, limits);
+    let mut builder = Builder::new(Borrowed, limits.max_depth, strict, limits.max_items);
+    let value = loop {
+        let offset = parser.pos;
+        let token = parser.next_token()?;
+        if parser.pos > limits.max_total_bytes {
+            return Err(Error::ReadLimitExceeded { offset });
+        }
+        if let Some(value) = builder.push(token, offset..parser.pos)? {
+            break value;
+        }
+    };
+    if parser.pos != input.len() {
+        return Err(Error::TrailingData { offset: parser.pos });
+    }
+    Ok(value)
+}
+
+impl<'a> Scalar for ValueRef<'a> {
+    type Key = &'a [u8];
+
+    fn into_key(self) -> Result<&'a [u8], Self> {
+        match self {
+            ValueRef::ByteString(key) => Ok(key),
+            value => Err(value),
+        }
+    }
+}
+
+/// The `Tree` of `ValueRef`s. Of duplicate keys, the last entry wins.
+struct Borrowed;
+
+impl<'a> Tree<ValueRef<'a>> for Borrowed {
+    type Node = ValueRef<'a>;
+
+    fn scalar(&mut self, value: ValueRef<'a>, _: Range<usize>) -> ValueRef<'a> {
+        value
+    }
+
+    fn list(&mut self, items: Vec<ValueRef<'a>>, _: Range<usize>) -> ValueRef<'a> {
+        ValueRef::List(items)
+    }
+
+    fn dict(
+        &mut self,
+        entries: Vec<(usize, &'a [u8], ValueRef<'a>)>,
+        _: Range<usize>,
+    ) -> ValueRef<'a> {
+        ValueRef::Dictionary(
+            entries
+                .into_iter()
+                .map(|(_, key, value)| (key, value))
+                .collect(),
+        )
+    }
+}
+
+/// Reads tokens straight out of a slice, with byte strings borrowed from it.
+pub(crate) struct SliceParser<'a> {
+    input: &'a [u8],
+    pos: usize,
+    max_total_bytes: usize,
+    tokenizer: Tokenizer,
+}
+
+impl<'a> SliceParser<'a> {
+    pub(crate) fn with_limits(input: &'a [u8], limits: &Limits) -> Self {
+        let mut tokenizer = Tokenizer::new();
+        tokenizer.set_max_byte_string_len(limits.max_string_len);
+        Self {
+            input,
+            pos: 0,
+            max_total_bytes: limits.max_total_bytes,
+            tokenizer,
+        }
+    }
+
+    pub(crate) fn next_token(&mut self) -> Result<Token<ValueRef<'a>>, Error> {
+        let start = self.pos;
+        self.tokenizer.discard();
+        let event = loop {
+            let offset = self.pos;
+            let byte = match self.input.get(offset) {
+                Some(&byte) => byte,
+                None => break self.tokenizer.end(offset)?,
+            };
+            self.pos += 1;
+            if let Some(event) = self.tokenizer.event(byte, offset)? {
+                break event;
+            }
+        };
+        Ok(match event {
+            Event::ByteString(size) => {
+                // Refused before checking that the input actually holds it.
+                if size > self.max_total_bytes.saturating_sub(self.pos) {
+                    return Err(Error::ReadLimitExceeded { offset: start });
+                }
+                let rest = &self.input[self.pos..];
+                if rest.len() < size {
+                    return Err(Error::TruncatedByteString {
+                        declared: size,
+                        available: rest.len(),
+                        offset: self.input.len(),
+                    });
+                }
+                self.pos += size;
+                Token::Scalar(ValueRef::ByteString(&rest[..size]))
+            }
+            Event::Integer(int) => Token::Scalar(ValueRef::Integer(int)),
+            // Borrowed from the input rather than the tokenizer.
+            Event::BigInteger(_) => {
+                match parse_integer(&self.input[start + 1..self.pos - 1], start)? {
+                    ParsedInteger::Big(digits) => Token::Scalar(ValueRef::BigInteger(digits)),
+                    ParsedInteger::Small(_) => unreachable!("the tokenizer found it too large"),
+                }
+            }
+            Event::ListStart => Token::ListStart,
+            Event::DictStart => Token::DictStart,
+            Event::End => Token::End,
+            Event::Eof => Token::Eof,
+            Event::DictKey(_) | Event::RawInteger(_) => {
+                unreachable!("the tokenizer neither tells keys apart nor keeps raw integers")
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{decode, decode_with_limits, Parser};
+    use std::fs;
+
+    #[test]
+    fn matches_owned_decode() {
+        let raw = fs::read("./test/ubuntu-18.04.4-live-server-amd64.iso.torrent").unwrap();
+        let borrowed = decode_slice(&raw).unwrap();
+        let owned = decode(&mut Parser::new(raw.as_slice())).unwrap();
+        assert_eq!(borrowed.to_value(), owned);
+    }
+
+    #[test]
+    fn borrows_from_input() {
+        let input = b"d3:foo3:bare";
+        match decode_slice(input).unwrap() {
+            ValueRef::Dictionary(map) => {
+                let bar = &map[b"foo".as_slice()];
+                assert_eq!(bar, &ValueRef::ByteString(&input[8..11]));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    /// Hands out a byte string of `input` after the tree borrowing it is
+    /// gone, as when holding on to the pieces of a memory-mapped torrent.
+    fn pieces_of(input: &[u8]) -> Option<&[u8]> {
+        let torrent = decode_slice(input).ok()?;
+        torrent.get("info")?.get("pieces")?.as_bytes()
+    }
+
+    #[test]
+    fn outlives_tree() {
+        let raw = fs::read("./test/ubuntu-18.04.4-live-server-amd64.iso.torrent").unwrap();
+        let pieces = pieces_of(&raw).unwrap();
+        assert_eq!(pieces.len() % 20, 0);
+        assert!(raw.as_ptr_range().contains(&pieces.as_ptr()));
+        assert_eq!(pieces_of(b"d4:infod4:name4:spamee"), None);
+        assert_eq!(decode_slice(b"i1e").unwrap().get("info"), None);
+    }
+
+    #[test]
+    fn malformed() {
+        let offset = |input: &[u8]| decode_slice(input).unwrap_err().offset();
+        assert!(matches!(
+            decode_slice(b""),
+            Err(Error::UnexpectedEof { offset: 0 })
+        ));
+        assert!(matches!(
+            decode_slice(b"5:ab"),
+            Err(Error::TruncatedByteString {
+                declared: 5,
+                available: 2,
+                offset: 4
+            })
+        ));
+        assert!(matches!(
+            decode_slice(b"e"),
+            Err(Error::UnexpectedEnd { offset: 0 })
+        ));
+        assert!(matches!(
+            decode_slice(b"l12"),
+            Err(Error::UnexpectedEof { offset: 3 })
+        ));
+        assert!(matches!(
+            decode_slice(b"li12"),
+            Err(Error::UnterminatedInteger {
+                start: 1,
+                offset: 4
+            })
+        ));
+        assert!(matches!(
+            decode_slice(b"l3x:abc"),
+            Err(Error::InvalidLength { offset: 1 })
+        ));
+        assert!(matches!(
+            decode_slice(b"lx"),
+            Err(Error::InvalidToken {
+                byte: b'x',
+                offset: 1
+            })
+        ));
+        assert_eq!(offset(b"li1ei01e"), Some(4));
+        assert!(matches!(
+            decode_slice(b"d1:ai1eli1ee1:be"),
+            Err(Error::NonStringKey { offset: 7 })
+        ));
+        assert!(matches!(
+            decode_slice(b"d1:ai1e1:be"),
+            Err(Error::OddDictLength { offset: 10 })
+        ));
+        assert_eq!(
+            decode_slice(b"i-10000000000000000000e").unwrap(),
+            ValueRef::BigInteger("-10000000000000000000")
+        );
+        assert!(matches!(
+            decode_slice(b"i1ei2e"),
+            Err(Error::TrailingData { offset: 3 })
+        ));
+    }
+
+    #[test]
+    fn limits() {
+        let limits = |configure: fn(&mut Limits)| {
+            let mut limits = Limits::default();
+            configure(&mut limits);
+            limits
+        };
+        // Same errors at the same offsets as `decode_with_limits`.
+        for (input, limits, offset) in [
+            ("lli1eee", limits(|l| l.max_depth = 1), 1),
+            ("l4:spame", limits(|l| l.max_string_len = 3), 1),
+            ("li1ei2ee", limits(|l| l.max_items = 2), 4),
+            ("d1:ai1e1:bi2ee", limits(|l| l.max_items = 3), 7),
+            ("l3:abce", limits(|l| l.max_total_bytes = 6), 6),
+            ("l3:abce", limits(|l| l.max_total_bytes = 5), 1),
+            ("99999999999:", limits(|l| l.max_total_bytes = 1024), 0),
+        ] {
+            let error = decode_slice_with(input.as_bytes(), &limits, false).unwrap_err();
+            let expected = decode_with_limits(input.as_bytes(), &limits).unwrap_err();
+            assert_eq!(error.to_string(), expected.to_string());
+            assert_eq!(error.offset(), Some(offset), "{}", input);
+        }
+        assert!(decode_slice_with(b"l3:abce", &limits(|l| l.max_total_bytes = 7), false).is_ok());
+    }
+
+    #[test]
+    fn strict() {
+        fn strict(input: &[u8]) -> Result<ValueRef<'_>, Error> {
+            decode_slice_with(input, &Limits::default(), true)
+        }
+        assert!(strict(b"d1:ai1e1:bd1:ci1e1:di2eee").is_ok());
+        assert!(matches!(
+            strict(b"d1:bi1e1:ai2ee"),
+            Err(Error::UnsortedKeys { offset: 7 })
+        ));
+        assert!(matches!(
+            strict(b"d1:ai1e1:ai2ee"),
+            Err(Error::DuplicateKey { offset: 7 })
+        ));
+        // Keys are checked as they're read, like `decode` does.
+        assert!(matches!(
+            strict(b"d1:bi1e1:ai2e"),
+            Err(Error::UnsortedKeys { offset: 7 })
+        ));
+        assert_eq!(
+            decode_slice(b"d1:bi1e1:ai2e1:bi3ee").unwrap().get("b"),
+            Some(&ValueRef::Integer(3))
+        );
+    }
+}
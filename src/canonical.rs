@@ -0,0 +1,182 @@
+use crate::{Error, Event, Parser};
+use alloc::{vec, vec::Vec};
+use core::cmp::Ordering;
+
+/// An open container while checking: a list, or a dictionary with whether
+/// its next item is a key, and its last key so far.
+enum Open<'a> {
+    List,
+    Dict {
+        at_key: bool,
+        last: Option<&'a [u8]>,
+    },
+}
+
+/// Whether `input` is a single bencoded value in canonical form. See
+/// `check_canonical` for what that involves, and for where it isn't.
+///
+/// ```
+/// use bencode_decode::is_canonical;
+///
+/// assert!(is_canonical(b"d1:ai1e1:bli2eee"));
+/// assert!(!is_canonical(b"d1:bi1e1:ai2ee"));
+/// assert!(!is_canonical(b"i01e"));
+/// assert!(!is_canonical(b"i1e\n"));
+/// ```
+pub fn is_canonical(input: &[u8]) -> bool {
+    check_canonical(input).is_ok()
+}
+
+/// Checks that `input` is a single bencoded value in canonical form, i.e.
+/// what `decode_strict` with `Parser::set_strict` accepts: integers without
+/// leading zeros or a negative zero, dictionary keys that are byte strings
+/// in sorted order without duplicates, and nothing after the value. Fails
+/// with the same error as that would at the first violation, but without
+/// building the value; keys are compared in place and nothing else is
+/// copied out of `input`.
+///
+/// ```
+/// use bencode_decode::{check_canonical, Error};
+///
+/// assert!(matches!(
+///     check_canonical(b"d1:ai1e1:ai2ee"),
+///     Err(Error::DuplicateKey { offset: 7 })
+/// ));
+/// ```
+pub fn check_canonical(input: &[u8]) -> Result<(), Error> {
+    let mut parser = Parser::from_slice(input);
+    let mut stack: Vec<Open> = vec![];
+    loop {
+        let event = parser.next_event()?;
+        let offset = parser.token_start;
+        let at_key = match stack.last_mut() {
+            Some(Open::Dict { at_key, .. }) if event != Event::End => {
+                *at_key = !*at_key;
+                !*at_key
+            }
+            _ => false,
+        };
+        match event {
+            Event::ByteString(len) if at_key => {
+                let start = parser.pos;
+                let key = match input[start..].get(..len) {
+                    Some(key) => key,
+                    None => {
+                        return Err(Error::TruncatedByteString {
+                            declared: len,
+                            available: input.len() - start,
+                            offset: input.len(),
+                        })
+                    }
+                };
+                if let Some(Open::Dict { last, .. }) = stack.last_mut() {
+                    match last.map(|last| key.cmp(last)) {
+                        Some(Ordering::Less) => return Err(Error::UnsortedKeys { offset }),
+                        Some(Ordering::Equal) => return Err(Error::DuplicateKey { offset }),
+                        _ => *last = Some(key),
+                    }
+                }
+                continue;
+            }
+            Event::End => match stack.pop() {
+                Some(Open::Dict { at_key: false, .. }) => {
+                    return Err(Error::OddDictLength { offset })
+                }
+                Some(_) => {}
+                None => return Err(Error::UnexpectedEnd { offset }),
+            },
+            Event::Eof => return Err(Error::UnexpectedEof { offset }),
+            _ if at_key => return Err(Error::NonStringKey { offset }),
+            Event::ListStart | Event::DictStart => {
+                if stack.len() >= parser.max_depth {
+                    return Err(Error::DepthLimitExceeded { offset });
+                }
+                stack.push(match event {
+                    Event::ListStart => Open::List,
+                    _ => Open::Dict {
+                        at_key: true,
+                        last: None,
+                    },
+                });
+                continue;
+            }
+            _ => {}
+        }
+        if stack.is_empty() {
+            return parser.expect_eof();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::decode_strict;
+    use std::fs;
+
+    /// What `decode_strict` in strict mode makes of `input`.
+    fn strict(input: &[u8]) -> Result<(), Error> {
+        let mut parser = Parser::new(input);
+        parser.set_strict(true);
+        decode_strict(&mut parser).map(|_| ())
+    }
+
+    #[test]
+    fn torrent() {
+        let raw = fs::read("./test/ubuntu-18.04.4-live-server-amd64.iso.torrent").unwrap();
+        assert!(is_canonical(&raw));
+        assert!(!is_canonical(&raw[..raw.len() - 1]));
+    }
+
+    #[test]
+    fn matches_strict_decode() {
+        for input in [
+            "i0e",
+            "i-42e",
+            "0:",
+            "le",
+            "de",
+            "d0:0:e",
+            "d1:ai1e1:bd1:cli1eeee",
+            "i01e",
+            "i-0e",
+            "d1:bi1e1:ai2ee",
+            "d1:ai1e1:ai2ee",
+            "d1:ad1:bi1e1:bi2eee",
+            "di1e1:ae",
+            "dlei1ee",
+            "d1:ai1ed1:bi1eee",
+            "d1:ae",
+            "d1:ai1e1:be",
+            "e",
+            "",
+            "l",
+            "d5:ab",
+            "d1:a5:ab",
+            "li1ee i1e",
+            "i1ei2e",
+            "ld1:ai1e1:bi2eed1:bi1e1:ai2eee",
+            "d2:ab1:x1:b1:xe",
+            "d1:a1:x2:ab1:xe",
+        ] {
+            // Errors display with their offsets.
+            assert_eq!(
+                check_canonical(input.as_bytes()).map_err(|e| e.to_string()),
+                strict(input.as_bytes()).map_err(|e| e.to_string()),
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn depth() {
+        let input = "l".repeat(1000) + &"e".repeat(1000);
+        assert!(is_canonical(input.as_bytes()));
+        let input = "l".repeat(1001) + &"e".repeat(1001);
+        assert!(matches!(
+            check_canonical(input.as_bytes()),
+            Err(Error::DepthLimitExceeded { offset: 1000 })
+        ));
+    }
+}
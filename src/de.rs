@@ -0,0 +1,687 @@
+//! Deserialization of bencoded data into any type implementing
+//! `serde::Deserialize`.
+//!
+//! Byte strings deserialize into `Vec<u8>`, `String`, `&str`-like types or
+//! `serde_bytes::ByteBuf`, dictionaries into structs or maps, lists into
+//! sequences and tuples. Since bencode has no booleans, `bool` is read from
+//! the integers `0` and `1`; fields of type `Option` are `None` when absent.
+//!
+//! ```
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize)]
+//! struct Info {
+//!     name: String,
+//!     length: u64,
+//! }
+//!
+//! #[derive(Deserialize)]
+//! struct Torrent {
+//!     announce: String,
+//!     info: Info,
+//! }
+//!
+//! let data = std::fs::read("./test/ubuntu-18.04.4-live-server-amd64.iso.torrent").unwrap();
+//! let torrent: Torrent = bencode_decode::de::from_bytes(&data).unwrap();
+//! assert_eq!(torrent.info.name, "ubuntu-18.04.4-live-server-amd64.iso");
+//! assert_eq!(torrent.info.length, 912_261_120);
+//! ```
+
+use crate::event::{Open, Shape};
+use crate::{Error, Event, Items, Key, ParseResult, ParseResult::*, Parser, Value};
+use serde::de::{self, DeserializeSeed, Visitor};
+use std::{cmp::Ordering, collections::BTreeMap, convert::TryFrom, fmt, io::Read};
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error::Custom(msg.to_string())
+    }
+}
+
+/// Deserializes the single value contained in `reader`, failing with
+/// `Error::TrailingData` if anything follows it.
+pub fn from_reader<R: Read, T: de::DeserializeOwned>(reader: R) -> Result<T, Error> {
+    let mut de = Deserializer::new(Parser::new(reader));
+    let value = T::deserialize(&mut de)?;
+    de.end()?;
+    Ok(value)
+}
+
+/// Deserializes the single value making up `input`. See `from_reader`.
+pub fn from_bytes<T: de::DeserializeOwned>(input: &[u8]) -> Result<T, Error> {
+    from_reader(input)
+}
+
+/// A `serde::Deserializer` reading from the token stream of a `Parser`. The
+/// parser's limits on depth and items apply, and in strict mode dictionary
+/// keys have to be sorted and unique.
+pub struct Deserializer<R: Read> {
+    parser: Parser<R>,
+    peeked: Option<ParseResult>,
+    depth: usize,
+    items: Items,
+}
+
+impl<R: Read> Deserializer<R> {
+    pub fn new(parser: Parser<R>) -> Self {
+        let items = Items::new(parser.max_items);
+        Self {
+            parser,
+            peeked: None,
+            depth: 0,
+            items,
+        }
+    }
+
+    /// Checks that the input has been fully consumed.
+    pub fn end(&mut self) -> Result<(), Error> {
+        match self.peeked.take() {
+            Some(EOF) | None => self.parser.expect_eof(),
+            Some(_) => Err(de::Error::custom("value was not fully deserialized")),
+        }
+    }
+
+    fn next_token(&mut self) -> Result<ParseResult, Error> {
+        match self.peeked.take() {
+            Some(token) => Ok(token),
+            None => self.read_token(),
+        }
+    }
+
+    fn peek_token(&mut self) -> Result<&ParseResult, Error> {
+        if self.peeked.is_none() {
+            self.peeked = Some(self.read_token()?);
+        }
+        Ok(self.peeked.as_ref().unwrap())
+    }
+
+    /// Reads a token from the parser, counting it towards the item limit.
+    fn read_token(&mut self) -> Result<ParseResult, Error> {
+        let token = self.parser.next_token()?;
+        if !matches!(token, End | EOF) {
+            self.items.count(self.parser.token_start)?;
+        }
+        Ok(token)
+    }
+
+    fn enter(&mut self) -> Result<(), Error> {
+        if self.depth >= self.parser.max_depth {
+            return Err(Error::DepthLimitExceeded {
+                offset: self.parser.token_start,
+            });
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn leave(&mut self) -> Result<(), Error> {
+        self.depth -= 1;
+        match self.next_token()? {
+            End => Ok(()),
+            _ => Err(de::Error::custom("expected end of container")),
+        }
+    }
+}
+
+fn unexpected(token: ParseResult, offset: usize) -> Error {
+    match token {
+        End => Error::UnexpectedEnd { offset },
+        EOF => Error::UnexpectedEof { offset },
+        _ => de::Error::custom("unexpected token"),
+    }
+}
+
+/// Hands a scalar or unexpected token to the visitor. Kept out of
+/// `deserialize_any` so that its stack frame, which is repeated for every
+/// level of nesting, stays small. Integers beyond `i64` are passed as the
+/// narrowest type that fits.
+#[inline(never)]
+fn visit_scalar<'de, V: Visitor<'de>>(
+    token: ParseResult,
+    offset: usize,
+    visitor: V,
+) -> Result<V::Value, Error> {
+    match token {
+        ValueType(Value::ByteString(bytes)) => visitor.visit_byte_buf(bytes),
+        ValueType(Value::Integer(int)) => visitor.visit_i64(int),
+        ValueType(Value::BigInteger(digits)) => {
+            if let Ok(int) = digits.parse() {
+                visitor.visit_u64(int)
+            } else if let Ok(int) = digits.parse() {
+                visitor.visit_i128(int)
+            } else if let Ok(int) = digits.parse() {
+                visitor.visit_u128(int)
+            } else {
+                Err(Error::IntegerOverflow { digits, offset })
+            }
+        }
+        // Goes by value, like the canonical integer.
+        ValueType(Value::RawInteger(digits)) => {
+            let canonical = crate::canonical_integer(digits.as_bytes()).unwrap_or_default();
+            let value = match crate::parse_integer(&canonical, offset)? {
+                crate::ParsedInteger::Small(int) => Value::Integer(int),
+                crate::ParsedInteger::Big(digits) => Value::BigInteger(digits.into()),
+            };
+            visit_scalar(ValueType(value), offset, visitor)
+        }
+        token => Err(unexpected(token, offset)),
+    }
+}
+
+impl<'de, R: Read> de::Deserializer<'de> for &mut Deserializer<R> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        // This recurses for every level of nesting, so it's written to keep
+        // its stack frame small, with few temporaries.
+        let list = match self.next_token()? {
+            ListStart => true,
+            DictStart => false,
+            token => return visit_scalar(token, self.parser.token_start, visitor),
+        };
+        self.enter()?;
+        let value = if list {
+            visitor.visit_seq(Access::new(self))
+        } else {
+            visitor.visit_map(Access::new(self))
+        };
+        match value {
+            Ok(value) => self.leave().map(|()| value),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.next_token()? {
+            ValueType(Value::Integer(0)) => visitor.visit_bool(false),
+            ValueType(Value::Integer(1)) => visitor.visit_bool(true),
+            _ => Err(de::Error::custom("expected integer 0 or 1 for bool")),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_some(self)
+    }
+
+    /// Byte strings can also be read as a sequence of `u8`, which is what
+    /// `Vec<u8>` asks for.
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        if let ValueType(Value::ByteString(_)) = self.peek_token()? {
+            if let Some(ValueType(Value::ByteString(bytes))) = self.peeked.take() {
+                return visitor.visit_seq(de::value::SeqDeserializer::new(bytes.into_iter()));
+            }
+        }
+        self.deserialize_any(visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        match self.peek_token()? {
+            ValueType(Value::ByteString(_)) => visitor.visit_enum(Enum { de: self }),
+            DictStart => {
+                self.next_token()?;
+                self.enter()?;
+                let value = visitor.visit_enum(Enum { de: self })?;
+                self.leave()?;
+                Ok(value)
+            }
+            _ => Err(de::Error::custom(
+                "expected byte string or dictionary for enum",
+            )),
+        }
+    }
+
+    /// Skips the value without building it. Byte strings are dropped as
+    /// they're read, and only whether each open container expects a key is
+    /// kept, so the value still has to be well-formed and within the limits,
+    /// but key order isn't checked in it, even in strict mode.
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let mut open = vec![];
+        loop {
+            let (shape, string) = match self.peeked.take() {
+                Some(ValueType(value)) => (Shape::Scalar, matches!(value, Value::ByteString(_))),
+                Some(ListStart) => (Shape::List, false),
+                Some(DictStart) => (Shape::Dict, false),
+                Some(End) => (Shape::End, false),
+                Some(EOF) => {
+                    return Err(Error::UnexpectedEof {
+                        offset: self.parser.token_start,
+                    })
+                }
+                None => {
+                    let event = self.parser.next_event()?;
+                    if !matches!(event, Event::End | Event::Eof) {
+                        self.items.count(self.parser.token_start)?;
+                    }
+                    match event {
+                        Event::ByteString(_) | Event::DictKey(_) => (Shape::Scalar, true),
+                        Event::ListStart => (Shape::List, false),
+                        Event::DictStart => (Shape::Dict, false),
+                        Event::End => (Shape::End, false),
+                        Event::Eof => {
+                            return Err(Error::UnexpectedEof {
+                                offset: self.parser.token_start,
+                            })
+                        }
+                        _ => (Shape::Scalar, false),
+                    }
+                }
+            };
+            let offset = self.parser.token_start;
+            match (&shape, open.last_mut()) {
+                (Shape::End, None) => return Err(Error::UnexpectedEnd { offset }),
+                (Shape::End, Some(Open::Value)) => return Err(Error::OddDictLength { offset }),
+                (Shape::End, Some(_)) => {
+                    open.pop();
+                }
+                (_, Some(top @ Open::Key)) if string => *top = Open::Value,
+                (_, Some(Open::Key)) => return Err(Error::NonStringKey { offset }),
+                (_, Some(top @ Open::Value)) => *top = Open::Key,
+                _ => {}
+            }
+            if let Shape::List | Shape::Dict = shape {
+                if self.depth + open.len() >= self.parser.max_depth {
+                    return Err(Error::DepthLimitExceeded { offset });
+                }
+                open.push(match shape {
+                    Shape::List => Open::List,
+                    _ => Open::Key,
+                });
+            }
+            if open.is_empty() {
+                return visitor.visit_unit();
+            }
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct newtype_struct tuple
+        tuple_struct map struct identifier
+    }
+}
+
+/// Walks the items of a list or the entries of a dictionary.
+struct Access<'a, R: Read> {
+    de: &'a mut Deserializer<R>,
+    /// The last key of a dictionary, kept in strict mode to check that the
+    /// next one sorts after it.
+    last_key: Option<Vec<u8>>,
+}
+
+impl<'a, R: Read> Access<'a, R> {
+    fn new(de: &'a mut Deserializer<R>) -> Self {
+        Self { de, last_key: None }
+    }
+}
+
+impl<'de, R: Read> de::SeqAccess<'de> for Access<'_, R> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error> {
+        if *self.de.peek_token()? == End {
+            return Ok(None);
+        }
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+}
+
+impl<'de, R: Read> de::MapAccess<'de> for Access<'_, R> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        let strict = self.de.parser.strict;
+        let order = match self.de.peek_token()? {
+            End => return Ok(None),
+            ValueType(Value::ByteString(key)) if strict => {
+                let order = self
+                    .last_key
+                    .as_deref()
+                    .map(|last| key.as_slice().cmp(last));
+                self.last_key = Some(key.clone());
+                order
+            }
+            ValueType(Value::ByteString(_)) => None,
+            _ => {
+                return Err(Error::NonStringKey {
+                    offset: self.de.parser.token_start,
+                })
+            }
+        };
+        let offset = self.de.parser.token_start;
+        match order {
+            Some(Ordering::Less) => return Err(Error::UnsortedKeys { offset }),
+            Some(Ordering::Equal) => return Err(Error::DuplicateKey { offset }),
+            _ => {}
+        }
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        if *self.de.peek_token()? == End {
+            return Err(Error::OddDictLength {
+                offset: self.de.parser.token_start,
+            });
+        }
+        seed.deserialize(&mut *self.de)
+    }
+}
+
+/// Externally tagged enums: unit variants are byte strings, all others are
+/// single-entry dictionaries mapping the variant name to its content.
+struct Enum<'a, R: Read> {
+    de: &'a mut Deserializer<R>,
+}
+
+impl<'de, R: Read> de::EnumAccess<'de> for Enum<'_, R> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self), Error> {
+        let variant = seed.deserialize(&mut *self.de)?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de, R: Read> de::VariantAccess<'de> for Enum<'_, R> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, Error> {
+        seed.deserialize(self.de)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_seq(self.de, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_map(self.de, visitor)
+    }
+}
+
+impl<'de> de::Deserialize<'de> for Value {
+    fn deserialize<D: de::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a bencode value")
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<Value, E> {
+        Ok(Value::Integer(v as i64))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Value, E> {
+        Ok(Value::Integer(v))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Value, E> {
+        Ok(i64::try_from(v).map_or_else(|_| Value::BigInteger(v.to_string()), Value::Integer))
+    }
+
+    fn visit_i128<E: de::Error>(self, v: i128) -> Result<Value, E> {
+        Ok(i64::try_from(v).map_or_else(|_| Value::BigInteger(v.to_string()), Value::Integer))
+    }
+
+    fn visit_u128<E: de::Error>(self, v: u128) -> Result<Value, E> {
+        Ok(i64::try_from(v).map_or_else(|_| Value::BigInteger(v.to_string()), Value::Integer))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Value, E> {
+        Ok(Value::ByteString(v.as_bytes().to_vec()))
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Value, E> {
+        Ok(Value::ByteString(v.to_vec()))
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<Value, E> {
+        Ok(Value::ByteString(v))
+    }
+
+    fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let mut list = vec![];
+        while let Some(item) = seq.next_element()? {
+            list.push(item);
+        }
+        Ok(Value::List(list))
+    }
+
+    fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> Result<Value, A::Error> {
+        let mut dict = BTreeMap::new();
+        while let Some((key, value)) = map.next_entry()? {
+            match key {
+                Value::ByteString(key) => dict.insert(Key::from(key), value),
+                _ => return Err(de::Error::custom("dictionary keys must be byte strings")),
+            };
+        }
+        Ok(Value::Dictionary(dict))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde::Deserialize;
+    use std::collections::HashMap;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    enum Kind {
+        Plain,
+        Sized(u32),
+        Pair(i8, i8),
+        Named { id: u8 },
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Message {
+        name: String,
+        raw: Vec<u8>,
+        buf: serde_bytes::ByteBuf,
+        count: u16,
+        ok: bool,
+        missing: Option<i64>,
+        kinds: Vec<Kind>,
+        extra: HashMap<String, (i64, String)>,
+    }
+
+    #[test]
+    fn message() {
+        let input: &[u8] = b"d3:buf3:abc5:counti7e5:extrad1:xli-1e1:yee5:kindsl5:Plaind5:Sizedi3eed4:Pairli1ei2eeed5:Namedd2:idi4eeee4:name4:spam2:oki1e3:raw2:\x00\xffe";
+        let message: Message = from_bytes(input).unwrap();
+        let mut extra = HashMap::new();
+        extra.insert("x".to_string(), (-1, "y".to_string()));
+        assert_eq!(
+            message,
+            Message {
+                name: "spam".to_string(),
+                raw: vec![0, 255],
+                buf: serde_bytes::ByteBuf::from(b"abc".to_vec()),
+                count: 7,
+                ok: true,
+                missing: None,
+                kinds: vec![
+                    Kind::Plain,
+                    Kind::Sized(3),
+                    Kind::Pair(1, 2),
+                    Kind::Named { id: 4 }
+                ],
+                extra,
+            }
+        );
+    }
+
+    #[test]
+    fn ignores_unknown_fields() {
+        #[derive(Deserialize)]
+        struct Partial {
+            b: i64,
+        }
+        let partial: Partial = from_bytes(b"d1:ad1:xli1eee1:bi2e1:c0:e").unwrap();
+        assert_eq!(partial.b, 2);
+        // Skipped values still have to be well-formed and within the limits.
+        assert!(matches!(
+            from_bytes::<Partial>(b"d1:adi1e0:e1:bi2ee"),
+            Err(Error::NonStringKey { offset: 5 })
+        ));
+        assert!(matches!(
+            from_bytes::<Partial>(b"d1:ad1:xe1:bi2ee"),
+            Err(Error::OddDictLength { offset: 8 })
+        ));
+        assert!(matches!(
+            from_bytes::<Partial>(b"d1:ali1e"),
+            Err(Error::UnexpectedEof { offset: 8 })
+        ));
+        let mut parser = Parser::with_max_depth("d1:alli1eee1:bi2ee".as_bytes(), 2);
+        assert!(matches!(
+            Partial::deserialize(&mut Deserializer::new(parser)),
+            Err(Error::DepthLimitExceeded { offset: 5 })
+        ));
+        parser = Parser::with_max_depth("d1:ali1ee1:bi2ee".as_bytes(), 2);
+        assert_eq!(
+            Partial::deserialize(&mut Deserializer::new(parser))
+                .unwrap()
+                .b,
+            2
+        );
+    }
+
+    #[test]
+    fn strict() {
+        let deserialize = |input: &str| {
+            let mut parser = Parser::new(input.as_bytes());
+            parser.set_strict(true);
+            BTreeMap::<String, Value>::deserialize(&mut Deserializer::new(parser))
+        };
+        assert!(deserialize("d1:ai1e1:bd1:ci1e1:di2eee").is_ok());
+        assert!(matches!(
+            deserialize("d1:bi1e1:ai2ee"),
+            Err(Error::UnsortedKeys { offset: 7 })
+        ));
+        assert!(matches!(
+            deserialize("d1:ai1e1:ai2ee"),
+            Err(Error::DuplicateKey { offset: 7 })
+        ));
+        assert!(matches!(
+            deserialize("d1:ad1:di1e1:ci2eee"),
+            Err(Error::UnsortedKeys { offset: 11 })
+        ));
+        // Each dictionary starts over.
+        assert!(deserialize("d1:ad1:bi1ee1:bd1:ai2eee").is_ok());
+        // Without strict mode, the last entry wins.
+        let map: BTreeMap<String, i64> = from_bytes(b"d1:bi1e1:ai2e1:bi3ee").unwrap();
+        assert_eq!(map["b"], 3);
+    }
+
+    #[test]
+    fn value() {
+        let input = b"d1:ali1e2:xye1:bd0:i0ee1:ci-10000000000000000000ee";
+        let value: Value = from_bytes(input).unwrap();
+        assert_eq!(value, crate::from_bytes(input).unwrap());
+    }
+
+    #[test]
+    fn big_integers() {
+        assert_eq!(
+            from_bytes::<u64>(b"i18446744073709551615e").unwrap(),
+            u64::MAX
+        );
+        assert_eq!(
+            from_bytes::<i128>(b"i-18446744073709551616e").unwrap(),
+            -18446744073709551616
+        );
+        assert!(matches!(
+            from_bytes::<i64>(b"i9223372036854775808e"),
+            Err(Error::Custom(_))
+        ));
+        assert!(matches!(
+            from_bytes::<u128>(b"i340282366920938463463374607431768211456e"),
+            Err(Error::IntegerOverflow { offset: 0, .. })
+        ));
+    }
+
+    #[test]
+    fn errors() {
+        assert!(matches!(from_bytes::<u8>(b"i256e"), Err(Error::Custom(_))));
+        assert!(matches!(
+            from_bytes::<String>(b"2:\xff\xff"),
+            Err(Error::Custom(_))
+        ));
+        assert!(matches!(
+            from_bytes::<i64>(b"i1ei2e"),
+            Err(Error::TrailingData { .. })
+        ));
+        assert!(matches!(
+            from_bytes::<i64>(b"e"),
+            Err(Error::UnexpectedEnd { offset: 0 })
+        ));
+        assert!(matches!(
+            from_bytes::<Vec<i64>>(b"li1e"),
+            Err(Error::UnexpectedEof { .. })
+        ));
+        assert!(matches!(
+            from_bytes::<BTreeMap<String, i64>>(b"di1ei2ee"),
+            Err(Error::NonStringKey { offset: 1 })
+        ));
+        assert!(matches!(
+            from_bytes::<BTreeMap<String, i64>>(b"d1:ai1e1:be"),
+            Err(Error::OddDictLength { offset: 10 })
+        ));
+        let deep = "l".repeat(2000) + &"e".repeat(2000);
+        assert!(matches!(
+            from_bytes::<Value>(deep.as_bytes()),
+            Err(Error::DepthLimitExceeded { .. })
+        ));
+    }
+
+    #[test]
+    fn max_items() {
+        let deserialize = |input: &str| {
+            let mut parser = Parser::new(input.as_bytes());
+            parser.set_max_items(4);
+            BTreeMap::<String, Value>::deserialize(&mut Deserializer::new(parser))
+        };
+        assert!(deserialize("d1:ai1ee").is_ok());
+        assert!(matches!(
+            deserialize("d1:ai1e1:bi2ee"),
+            Err(Error::ItemLimitExceeded { offset: 10 })
+        ));
+        // Values skipped for unknown fields count as well.
+        #[derive(Deserialize)]
+        struct Partial {
+            #[allow(dead_code)]
+            b: i64,
+        }
+        let mut parser = Parser::new("d1:ali1ei2ee1:bi3ee".as_bytes());
+        parser.set_max_items(4);
+        assert!(matches!(
+            Partial::deserialize(&mut Deserializer::new(parser)),
+            Err(Error::ItemLimitExceeded { offset: 8 })
+        ));
+    }
+}
@@ -0,0 +1,141 @@
+use crate::Value::{self, *};
+use alloc::string::String;
+use core::fmt::{self, Write};
+
+/// Binary byte strings up to this length are shown in hex, longer ones only
+/// by their length.
+const MAX_HEX_LEN: usize = 32;
+
+/// Renders the value in a JSON-ish layout. Byte strings are shown as quoted
+/// strings if they are valid UTF-8, as `<hex>` if they are short binary
+/// blobs and as `<N bytes>` otherwise. The alternate flag (`{:#}`) produces
+/// the indented output of `Value::to_pretty_string(2)`.
+///
+/// ```
+/// use bencode_decode::from_bytes;
+///
+/// let value = from_bytes(b"d4:name4:spam6:piecesli1ei2eee").unwrap();
+/// assert_eq!(value.to_string(), r#"{"name": "spam", "pieces": [1, 2]}"#);
+/// ```
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let indent = if f.alternate() { Some(2) } else { None };
+        write_value(f, self, indent, 0)
+    }
+}
+
+impl Value {
+    /// Renders the value like its `Display` impl, but with every list item
+    /// and dictionary entry on its own line, indented by `indent` spaces per
+    /// level of nesting.
+    pub fn to_pretty_string(&self, indent: usize) -> String {
+        let mut out = String::new();
+        write_value(&mut out, self, Some(indent), 0).expect("Writing to a String never fails");
+        out
+    }
+}
+
+fn write_bytes<W: Write>(out: &mut W, bytes: &[u8]) -> fmt::Result {
+    match core::str::from_utf8(bytes) {
+        Ok(str) => write!(out, "{:?}", str),
+        Err(_) if bytes.len() <= MAX_HEX_LEN => {
+            out.write_char('<')?;
+            for byte in bytes {
+                write!(out, "{:02x}", byte)?;
+            }
+            out.write_char('>')
+        }
+        Err(_) => write!(out, "<{} bytes>", bytes.len()),
+    }
+}
+
+fn write_separator<W: Write>(
+    out: &mut W,
+    first: bool,
+    indent: Option<usize>,
+    level: usize,
+) -> fmt::Result {
+    if !first {
+        out.write_char(',')?;
+    }
+    match indent {
+        Some(indent) => write!(out, "\n{:1$}", "", indent * level),
+        None if first => Ok(()),
+        None => out.write_char(' '),
+    }
+}
+
+fn write_value<W: Write>(
+    out: &mut W,
+    value: &Value,
+    indent: Option<usize>,
+    level: usize,
+) -> fmt::Result {
+    match value {
+        ByteString(bytes) => write_bytes(out, bytes),
+        Integer(int) => write!(out, "{}", int),
+        BigInteger(digits) | RawInteger(digits) => out.write_str(digits),
+        List(list) => {
+            out.write_char('[')?;
+            for (i, item) in list.iter().enumerate() {
+                write_separator(out, i == 0, indent, level + 1)?;
+                write_value(out, item, indent, level + 1)?;
+            }
+            if !list.is_empty() {
+                write_separator(out, true, indent, level)?;
+            }
+            out.write_char(']')
+        }
+        Dictionary(map) => {
+            out.write_char('{')?;
+            for (i, (key, value)) in map.iter().enumerate() {
+                write_separator(out, i == 0, indent, level + 1)?;
+                write_bytes(out, key)?;
+                out.write_str(": ")?;
+                write_value(out, value, indent, level + 1)?;
+            }
+            if !map.is_empty() {
+                write_separator(out, true, indent, level)?;
+            }
+            out.write_char('}')
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::from_bytes;
+
+    #[test]
+    fn compact() {
+        let value = from_bytes(b"d1:ai-1e1:bl0:le2:\xff\x00dee4:\"q\"\n0:e").unwrap();
+        assert_eq!(
+            value.to_string(),
+            r#"{"\"q\"\n": "", "a": -1, "b": ["", [], <ff00>, {}]}"#
+        );
+    }
+
+    #[test]
+    fn binary() {
+        let pieces = [0xffu8; 40];
+        let mut input = b"40:".to_vec();
+        input.extend_from_slice(&pieces);
+        assert_eq!(from_bytes(&input).unwrap().to_string(), "<40 bytes>");
+    }
+
+    #[test]
+    fn pretty() {
+        let value = from_bytes(b"d4:infod6:lengthi3ee4:listli1eleee").unwrap();
+        let expected = r#"{
+  "info": {
+    "length": 3
+  },
+  "list": [
+    1,
+    []
+  ]
+}"#;
+        assert_eq!(value.to_pretty_string(2), expected);
+        assert_eq!(format!("{:#}", value), expected);
+    }
+}
@@ -0,0 +1,307 @@
+use crate::io::{self, Write};
+use crate::Error;
+use crate::Value::{self, *};
+use alloc::{vec, vec::Vec};
+
+/// Serializes `value` into bencoded bytes. Since dictionaries are backed by a
+/// `BTreeMap`, keys are always emitted in sorted order. Integers are written
+/// as held, though: a `RawInteger` keeps its leading zeros, and a hand-built
+/// `BigInteger` that isn't a decimal number makes the output invalid. Use
+/// `Value::canonicalize` for output that is guaranteed to be canonical.
+///
+/// ```
+/// use bencode_decode::{decode, encode, Parser, Value};
+///
+/// let value = Value::List(vec![Value::Integer(42), Value::ByteString(b"spam".to_vec())]);
+/// let bytes = encode(&value);
+/// assert_eq!(bytes, b"li42e4:spame");
+///
+/// let mut parser = Parser::from_slice(&bytes);
+/// assert_eq!(decode(&mut parser).unwrap(), value);
+/// ```
+pub fn encode(value: &Value) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(value.encoded_len());
+    value
+        .write_to(&mut buf)
+        .expect("Writing to a Vec never fails");
+    buf
+}
+
+impl Value {
+    /// Writes the bencoded representation of this value to `writer`. See
+    /// `encode`.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        self.pieces().try_for_each(|piece| piece.write_to(writer))
+    }
+
+    /// The exact number of bytes `to_bytes` produces, computed without
+    /// encoding anything, for preallocating buffers or checking size limits
+    /// up front.
+    ///
+    /// ```
+    /// use bencode_decode::from_bytes;
+    ///
+    /// let value = from_bytes(b"d4:spamli-42eee").unwrap();
+    /// assert_eq!(value.encoded_len(), 15);
+    /// ```
+    pub fn encoded_len(&self) -> usize {
+        let mut len = 0;
+        self.walk(&mut |value, _| {
+            len += match value {
+                ByteString(bytes) => decimal_len(bytes.len() as u64) + 1 + bytes.len(),
+                Integer(int) => usize::from(*int < 0) + decimal_len(int.unsigned_abs()) + 2,
+                BigInteger(digits) | RawInteger(digits) => digits.len() + 2,
+                List(_) => 2,
+                Dictionary(map) => {
+                    map.keys()
+                        .map(|key| decimal_len(key.len() as u64) + 1 + key.len())
+                        .sum::<usize>()
+                        + 2
+                }
+            }
+        });
+        len
+    }
+
+    /// Returns the bencoded representation of this value. See `encode`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        encode(self)
+    }
+
+    /// Encodes this value as canonical bencode, whatever it was decoded from
+    /// or built from: dictionary keys sorted bytewise, and integers in
+    /// minimal form without leading zeros or `-0`. Unlike `to_bytes`, this
+    /// also normalizes hand-built `BigInteger`s, and fails with
+    /// `Error::InvalidInteger` pointing into the output if one isn't a
+    /// decimal number.
+    ///
+    /// ```
+    /// use bencode_decode::{from_bytes, Value};
+    ///
+    /// let value = from_bytes(b"d1:bi1e1:ai2ee").unwrap();
+    /// assert_eq!(value.canonicalize().unwrap(), b"d1:ai2e1:bi1ee");
+    /// let big = Value::BigInteger("-00099999999999999999999".to_string());
+    /// assert_eq!(big.canonicalize().unwrap(), b"i-99999999999999999999e");
+    /// ```
+    pub fn canonicalize(&self) -> Result<Vec<u8>, Error> {
+        let mut buf = vec![];
+        self.write_canonical(&mut buf)?;
+        Ok(buf)
+    }
+
+    fn write_canonical(&self, buf: &mut Vec<u8>) -> Result<(), Error> {
+        for piece in self.pieces() {
+            match piece {
+                Piece::Value(BigInteger(digits) | RawInteger(digits)) => {
+                    let (sign, magnitude) = match digits.strip_prefix('-') {
+                        Some(magnitude) => ("-", magnitude),
+                        None => ("", digits.as_str()),
+                    };
+                    if magnitude.is_empty() || !magnitude.bytes().all(|b| b.is_ascii_digit()) {
+                        return Err(Error::InvalidInteger { offset: buf.len() });
+                    }
+                    match magnitude.trim_start_matches('0') {
+                        "" => buf.extend_from_slice(b"i0e"),
+                        magnitude => write!(buf, "i{}{}e", sign, magnitude)
+                            .expect("Writing to a Vec never fails"),
+                    }
+                }
+                piece => piece.write_to(buf).expect("Writing to a Vec never fails"),
+            }
+        }
+        Ok(())
+    }
+
+    /// The pieces this value is written as, in order. Nodes are kept on an
+    /// explicit stack, like in `walk`, so this is safe on arbitrarily deep
+    /// trees.
+    fn pieces(&self) -> impl Iterator<Item = Piece<'_>> {
+        let mut stack = vec![Piece::Value(self)];
+        core::iter::from_fn(move || {
+            let piece = stack.pop()?;
+            // Reverse order, so that popping the stack yields items in order.
+            match piece {
+                Piece::Value(List(list)) => {
+                    stack.push(Piece::End);
+                    stack.extend(list.iter().rev().map(Piece::Value));
+                }
+                Piece::Value(Dictionary(map)) => {
+                    stack.push(Piece::End);
+                    for (key, value) in map.iter().rev() {
+                        stack.push(Piece::Value(value));
+                        stack.push(Piece::Key(key));
+                    }
+                }
+                _ => {}
+            }
+            Some(piece)
+        })
+    }
+}
+
+/// Part of the bencoded representation of a value: a scalar, or the start
+/// of a list or dictionary, a dictionary key, or the end of a container.
+/// Also used for `OrderedValue`.
+pub(crate) enum Piece<'a, V = Value> {
+    Value(&'a V),
+    Key(&'a [u8]),
+    End,
+}
+
+impl Piece<'_> {
+    fn write_to<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        match *self {
+            Piece::Value(ByteString(bytes)) => {
+                write!(writer, "{}:", bytes.len())?;
+                writer.write_all(bytes)
+            }
+            Piece::Value(Integer(int)) => write!(writer, "i{}e", int),
+            Piece::Value(BigInteger(digits) | RawInteger(digits)) => write!(writer, "i{}e", digits),
+            Piece::Value(List(_)) => writer.write_all(b"l"),
+            Piece::Value(Dictionary(_)) => writer.write_all(b"d"),
+            Piece::Key(key) => {
+                write!(writer, "{}:", key.len())?;
+                writer.write_all(key)
+            }
+            Piece::End => writer.write_all(b"e"),
+        }
+    }
+}
+
+/// The number of decimal digits in `n`.
+fn decimal_len(mut n: u64) -> usize {
+    let mut len = 1;
+    while n >= 10 {
+        n /= 10;
+        len += 1;
+    }
+    len
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{decode, Parser};
+    use std::{collections::BTreeMap, fs};
+
+    fn roundtrip(value: &Value) -> Value {
+        let bytes = encode(value);
+        let mut parser = Parser::from_slice(&bytes);
+        decode(&mut parser).unwrap()
+    }
+
+    #[test]
+    fn scalars() {
+        assert_eq!(encode(&Integer(0)), b"i0e");
+        assert_eq!(encode(&Integer(-42)), b"i-42e");
+        assert_eq!(encode(&ByteString(b"spam".to_vec())), b"4:spam");
+        assert_eq!(encode(&ByteString(vec![])), b"0:");
+    }
+
+    #[test]
+    fn encoded_len() {
+        let raw = fs::read("./test/ubuntu-18.04.4-live-server-amd64.iso.torrent").unwrap();
+        let torrent = decode(&mut Parser::from_slice(&raw)).unwrap();
+        let mut values = vec![
+            torrent,
+            Integer(0),
+            Integer(9),
+            Integer(10),
+            Integer(-1),
+            Integer(i64::MIN),
+            Integer(i64::MAX),
+            BigInteger("-18446744073709551616".into()),
+            ByteString(vec![]),
+            ByteString(vec![0; 10]),
+            List(vec![]),
+        ];
+        values.push(List(values.clone()));
+        for value in values {
+            assert_eq!(value.encoded_len(), encode(&value).len(), "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn sorted_keys() {
+        let mut map = BTreeMap::new();
+        map.insert("zoo".into(), Integer(1));
+        map.insert(
+            "bar".into(),
+            List(vec![Integer(2), ByteString(b"x".to_vec())]),
+        );
+        let value = Dictionary(map);
+        assert_eq!(encode(&value), b"d3:barli2e1:xe3:zooi1ee");
+        assert_eq!(roundtrip(&value), value);
+    }
+
+    #[test]
+    fn deep() {
+        let mut deep = Integer(0);
+        for _ in 0..200_000 {
+            deep = List(vec![deep]);
+        }
+        let mut map = BTreeMap::new();
+        map.insert("a".into(), deep);
+        let deep = Dictionary(map);
+        let mut expected = b"d1:a".to_vec();
+        expected.extend(vec![b'l'; 200_000]);
+        expected.extend_from_slice(b"i0e");
+        expected.extend(vec![b'e'; 200_001]);
+        let bytes = encode(&deep);
+        assert!(bytes == expected);
+        assert_eq!(deep.encoded_len(), bytes.len());
+        assert!(deep.canonicalize().unwrap() == bytes);
+        let mut parser = Parser::from_slice(&bytes);
+        parser.set_max_depth(usize::MAX);
+        let decoded = decode(&mut parser).unwrap();
+        // Dropping is recursive, so unwind both trees by hand.
+        for value in [deep, decoded] {
+            let mut value = match value {
+                Dictionary(mut map) => map.remove(&b"a"[..]).unwrap(),
+                _ => unreachable!(),
+            };
+            while let List(mut list) = value {
+                value = list.pop().unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn torrent() {
+        let raw = fs::read("./test/ubuntu-18.04.4-live-server-amd64.iso.torrent").unwrap();
+        let mut parser = Parser::from_slice(&raw);
+        let value = decode(&mut parser).unwrap();
+        assert_eq!(value.to_bytes(), raw);
+        assert_eq!(value.canonicalize().unwrap(), raw);
+    }
+
+    #[test]
+    fn canonicalize() {
+        let value = crate::from_bytes(b"d1:zi1e1:ald1:yi-1e1:x0:eee").unwrap();
+        assert_eq!(
+            value.canonicalize().unwrap(),
+            b"d1:ald1:x0:1:yi-1eee1:zi1ee"
+        );
+        for (digits, expected) in [
+            ("0", &b"i0e"[..]),
+            ("-0", b"i0e"),
+            ("-000", b"i0e"),
+            ("007", b"i7e"),
+            ("-0042", b"i-42e"),
+            ("18446744073709551616", b"i18446744073709551616e"),
+        ] {
+            assert_eq!(
+                BigInteger(digits.into()).canonicalize().unwrap(),
+                expected,
+                "{}",
+                digits
+            );
+        }
+        for digits in ["", "-", "+1", "1e3", " 1"] {
+            assert!(matches!(
+                List(vec![Integer(1), BigInteger(digits.into())]).canonicalize(),
+                Err(Error::InvalidInteger { offset: 4 })
+            ));
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use crate::io;
+use alloc::string::String;
+use core::fmt;
+
+/// Everything that can go wrong while decoding bencoded data. Apart from
+/// `MissingInfo`, `InvalidMetainfo`, `TypeMismatch` and `Custom`, every
+/// variant carries the byte `offset` into the input at which the problem was
+/// detected.
+#[derive(Debug)]
+pub enum Error {
+    /// The input ended in the middle of a value.
+    UnexpectedEof { offset: usize },
+    /// The input ended at `offset`, `available` bytes into a byte string
+    /// that declared a length of `declared`.
+    TruncatedByteString {
+        declared: usize,
+        available: usize,
+        offset: usize,
+    },
+    /// The input ended at `offset`, inside the integer starting at `start`
+    /// before its closing `e`.
+    UnterminatedInteger { start: usize, offset: usize },
+    /// Encountered an `e` where a value was expected, outside of any
+    /// container it could close.
+    UnexpectedEnd { offset: usize },
+    /// Encountered a byte that cannot start a token.
+    InvalidToken { byte: u8, offset: usize },
+    /// The integer starting at `offset` was not a well-formed decimal number.
+    InvalidInteger { offset: usize },
+    /// The length prefix of the byte string starting at `offset` was not a
+    /// well-formed decimal number.
+    InvalidLength { offset: usize },
+    /// The underlying reader failed.
+    Io { error: io::Error, offset: usize },
+    /// There was more input after the top-level value, starting at `offset`.
+    TrailingData { offset: usize },
+    /// The container starting at `offset` was nested deeper than the
+    /// parser's maximum depth.
+    DepthLimitExceeded { offset: usize },
+    /// The integer starting at `offset` was well-formed but too large for an
+    /// `i64`, and big integers were disabled with `Parser::set_big_integers`,
+    /// or it was too large for the type it was deserialized into.
+    IntegerOverflow { digits: String, offset: usize },
+    /// The byte string starting at `offset` declared a length above the
+    /// parser's maximum.
+    LengthLimitExceeded { offset: usize },
+    /// The token starting at `offset` extended past the parser's read limit,
+    /// see `Parser::with_read_limit` and `Limits::max_total_bytes`.
+    ReadLimitExceeded { offset: usize },
+    /// The value starting at `offset` was one more than
+    /// `Parser::set_max_items` or `Limits::max_items` allow.
+    ItemLimitExceeded { offset: usize },
+    /// In strict mode, the dictionary key at `offset` was not sorted after
+    /// its predecessor.
+    UnsortedKeys { offset: usize },
+    /// In strict mode, the dictionary key at `offset` repeated its
+    /// predecessor.
+    DuplicateKey { offset: usize },
+    /// The dictionary key at `offset` was not a byte string.
+    NonStringKey { offset: usize },
+    /// The dictionary closed at `offset` ended with a key that has no value.
+    OddDictLength { offset: usize },
+    /// The input was not a metainfo file with an `info` dictionary.
+    MissingInfo,
+    /// A metainfo field was missing or invalid, as described by the message.
+    /// Raised by `TorrentBuilder` and `torrent::check_sizes`.
+    InvalidMetainfo(&'static str),
+    /// A `Value` converted with `TryFrom` was not of the `expected` kind.
+    TypeMismatch {
+        expected: &'static str,
+        found: &'static str,
+    },
+    /// A message raised while (de)serializing with serde.
+    Custom(String),
+}
+
+impl Error {
+    /// The byte offset into the input at which the error was detected.
+    pub fn offset(&self) -> Option<usize> {
+        match *self {
+            Error::UnexpectedEof { offset }
+            | Error::TruncatedByteString { offset, .. }
+            | Error::UnterminatedInteger { offset, .. }
+            | Error::UnexpectedEnd { offset }
+            | Error::InvalidToken { offset, .. }
+            | Error::InvalidInteger { offset }
+            | Error::InvalidLength { offset }
+            | Error::IntegerOverflow { offset, .. }
+            | Error::Io { offset, .. }
+            | Error::TrailingData { offset }
+            | Error::DepthLimitExceeded { offset }
+            | Error::LengthLimitExceeded { offset }
+            | Error::ReadLimitExceeded { offset }
+            | Error::ItemLimitExceeded { offset }
+            | Error::UnsortedKeys { offset }
+            | Error::DuplicateKey { offset }
+            | Error::NonStringKey { offset }
+            | Error::OddDictLength { offset } => Some(offset),
+            Error::MissingInfo
+            | Error::InvalidMetainfo(_)
+            | Error::TypeMismatch { .. }
+            | Error::Custom(_) => None,
+        }
+    }
+
+    /// Whether the input ended in the middle of a value, which for a stream
+    /// may just mean that the rest hasn't arrived yet: `UnexpectedEof`,
+    /// `TruncatedByteString` or `UnterminatedInteger`.
+    pub fn is_unexpected_eof(&self) -> bool {
+        matches!(
+            self,
+            Error::UnexpectedEof { .. }
+                | Error::TruncatedByteString { .. }
+                | Error::UnterminatedInteger { .. }
+        )
+    }
+
+    pub(crate) fn io(error: io::Error, offset: usize) -> Self {
+        if io::is_unexpected_eof(&error) {
+            Error::UnexpectedEof { offset }
+        } else {
+            Error::Io { error, offset }
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::UnexpectedEof { .. } => write!(f, "unexpected end of input")?,
+            Error::TruncatedByteString {
+                declared,
+                available,
+                ..
+            } => write!(
+                f,
+                "byte string of length {} truncated after {} bytes",
+                declared, available
+            )?,
+            Error::UnterminatedInteger { .. } => write!(f, "unexpected end of input in integer")?,
+            Error::UnexpectedEnd { .. } => write!(f, "unexpected end of container")?,
+            Error::InvalidToken { byte, .. } => write!(f, "invalid token {:?}", *byte as char)?,
+            Error::InvalidInteger { .. } => write!(f, "invalid integer")?,
+            Error::InvalidLength { .. } => write!(f, "invalid byte string length")?,
+            Error::IntegerOverflow { digits, .. } => write!(f, "integer {} out of range", digits)?,
+            Error::Io { error, .. } => write!(f, "io error: {}", error)?,
+            Error::TrailingData { .. } => write!(f, "trailing data after value")?,
+            Error::DepthLimitExceeded { .. } => write!(f, "nesting depth limit exceeded")?,
+            Error::LengthLimitExceeded { .. } => write!(f, "byte string length limit exceeded")?,
+            Error::ReadLimitExceeded { .. } => write!(f, "read limit exceeded")?,
+            Error::ItemLimitExceeded { .. } => write!(f, "item count limit exceeded")?,
+            Error::UnsortedKeys { .. } => write!(f, "dictionary keys are not sorted")?,
+            Error::DuplicateKey { .. } => write!(f, "duplicate dictionary key")?,
+            Error::NonStringKey { .. } => write!(f, "dictionary key is not a byte string")?,
+            Error::OddDictLength { .. } => write!(f, "dictionary key without a value")?,
+            Error::MissingInfo => write!(f, "missing info dictionary")?,
+            Error::InvalidMetainfo(reason) => write!(f, "invalid metainfo: {}", reason)?,
+            Error::TypeMismatch { expected, found } => {
+                write!(f, "expected {}, found {}", expected, found)?
+            }
+            Error::Custom(msg) => write!(f, "{}", msg)?,
+        }
+        match self.offset() {
+            Some(offset) => write!(f, " at offset {}", offset),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,463 @@
+use crate::io::{self, Read, Write};
+use crate::{read_payload, Counting, Error, ParseResult, Parser, Value};
+use alloc::{string::String, vec::Vec};
+use core::mem;
+
+/// A single step of the streaming API, see `Parser::next_event`.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum Event {
+    /// A byte string of the given length. Its contents are left in the input
+    /// until they're fetched with `Parser::read_bytes`, and are skipped
+    /// without being allocated otherwise.
+    ByteString(usize),
+    /// Like `ByteString`, but a dictionary key. Only reported instead of
+    /// `ByteString` with `Parser::set_report_keys`.
+    DictKey(usize),
+    Integer(i64),
+    /// See `Value::BigInteger`.
+    BigInteger(String),
+    /// See `Value::RawInteger`.
+    RawInteger(String),
+    ListStart,
+    DictStart,
+    End,
+    Eof,
+}
+
+/// An open container, tracked for `Parser::set_report_keys`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Open {
+    List,
+    /// A dictionary whose next item is a key.
+    Key,
+    /// A dictionary whose next item is the value of the last key.
+    Value,
+}
+
+/// What a token does to the open containers.
+pub(crate) enum Shape {
+    Scalar,
+    List,
+    Dict,
+    End,
+}
+
+impl<R: Read> Parser<R> {
+    /// With `true`, `next_event` tells dictionary keys apart from values,
+    /// reporting keys as `Event::DictKey` rather than `Event::ByteString`.
+    /// The parser then keeps a stack of the open containers, updated for
+    /// every event or token consumed, including those consumed by `decode`
+    /// or `skip_value`:
+    ///
+    /// - `ListStart` and `DictStart` push a list, or a dictionary expecting
+    ///   a key, and `End` pops the innermost container.
+    /// - Inside a dictionary, every value alternates between key and value
+    ///   position, so a nested container counts as the value it is.
+    /// - A byte string in key position is a `DictKey`.
+    ///
+    /// Off by default. Enabling it starts at the top level, so do so before
+    /// reading the document.
+    ///
+    /// ```
+    /// use bencode_decode::{Event, Parser};
+    ///
+    /// let mut parser = Parser::new("d1:a1:bed1:ced".as_bytes());
+    /// parser.set_report_keys(true);
+    /// assert_eq!(parser.next_event().unwrap(), Event::DictStart);
+    /// assert_eq!(parser.next_event().unwrap(), Event::DictKey(1));
+    /// assert_eq!(parser.next_event().unwrap(), Event::ByteString(1));
+    /// assert_eq!(parser.next_event().unwrap(), Event::End);
+    /// ```
+    pub fn set_report_keys(&mut self, report: bool) {
+        self.open = if report { Some(Vec::new()) } else { None };
+    }
+
+    /// Updates the open containers for the next token, returning whether it
+    /// is in key position.
+    pub(crate) fn track(&mut self, shape: Shape) -> bool {
+        let open = match &mut self.open {
+            Some(open) => open,
+            None => return false,
+        };
+        if let Shape::End = shape {
+            open.pop();
+            return false;
+        }
+        let key = match open.last_mut() {
+            Some(top @ Open::Key) => {
+                *top = Open::Value;
+                true
+            }
+            Some(top @ Open::Value) => {
+                *top = Open::Key;
+                false
+            }
+            _ => false,
+        };
+        match shape {
+            Shape::List => open.push(Open::List),
+            Shape::Dict => open.push(Open::Key),
+            _ => {}
+        }
+        key
+    }
+
+    /// Pulls the next event off the input without building any `Value`s,
+    /// so that only the parts of a document the caller asks for are ever
+    /// allocated.
+    ///
+    /// ```
+    /// use bencode_decode::{Event, Parser};
+    ///
+    /// let mut parser = Parser::new("d6:pieces3:abc4:sizei3ee".as_bytes());
+    /// assert_eq!(parser.next_event().unwrap(), Event::DictStart);
+    /// assert_eq!(parser.next_event().unwrap(), Event::ByteString(6));
+    /// assert_eq!(parser.read_bytes().unwrap(), b"pieces");
+    /// parser.skip_value().unwrap();
+    /// assert_eq!(parser.next_event().unwrap(), Event::ByteString(4));
+    /// assert_eq!(parser.next_event().unwrap(), Event::Integer(3));
+    /// assert_eq!(parser.next_event().unwrap(), Event::End);
+    /// assert_eq!(parser.next_event().unwrap(), Event::Eof);
+    /// ```
+    pub fn next_event(&mut self) -> Result<Event, Error> {
+        let event = self.read_event()?;
+        if self.open.is_none() {
+            return Ok(event);
+        }
+        let shape = match event {
+            Event::ListStart => Shape::List,
+            Event::DictStart => Shape::Dict,
+            Event::End | Event::Eof => Shape::End,
+            _ => Shape::Scalar,
+        };
+        Ok(match (event, self.track(shape)) {
+            (Event::ByteString(len), true) => Event::DictKey(len),
+            (event, _) => event,
+        })
+    }
+
+    fn read_event(&mut self) -> Result<Event, Error> {
+        self.skip_pending()?;
+        if let Some((token, start)) = self.peeked.take() {
+            self.token_start = start;
+            return Ok(match token? {
+                ParseResult::ValueType(Value::ByteString(bytes)) => {
+                    let len = bytes.len();
+                    self.unread = Some(bytes);
+                    Event::ByteString(len)
+                }
+                ParseResult::ValueType(Value::Integer(int)) => Event::Integer(int),
+                ParseResult::ValueType(Value::BigInteger(digits)) => Event::BigInteger(digits),
+                ParseResult::ValueType(Value::RawInteger(digits)) => Event::RawInteger(digits),
+                ParseResult::ValueType(_) => unreachable!("the parser only emits scalars"),
+                ParseResult::ListStart => Event::ListStart,
+                ParseResult::DictStart => Event::DictStart,
+                ParseResult::End => Event::End,
+                ParseResult::EOF => Event::Eof,
+            });
+        }
+        self.token_start = self.pos;
+        let event = self.tokenize()?;
+        if let Event::ByteString(size) = event {
+            self.pending = size;
+        }
+        Ok(event)
+    }
+
+    /// Reads the contents of the byte string announced by the last
+    /// `Event::ByteString`, or returns an empty vector if they have already
+    /// been consumed.
+    pub fn read_bytes(&mut self) -> Result<Vec<u8>, Error> {
+        if let Some(bytes) = self.unread.take() {
+            return Ok(bytes);
+        }
+        let size = mem::take(&mut self.pending);
+        read_payload(&mut self.counting(), size)
+    }
+
+    /// Discards the next complete value, including any nested containers,
+    /// without allocating anything for it. A byte string already announced
+    /// by `next_event` doesn't count; its contents are dropped along the way.
+    pub fn skip_value(&mut self) -> Result<(), Error> {
+        let mut depth = 0usize;
+        loop {
+            match self.next_event()? {
+                Event::ListStart | Event::DictStart => depth += 1,
+                Event::End if depth > 0 => depth -= 1,
+                Event::End => {
+                    return Err(Error::UnexpectedEnd {
+                        offset: self.token_start,
+                    })
+                }
+                Event::Eof => {
+                    return Err(Error::UnexpectedEof {
+                        offset: self.token_start,
+                    })
+                }
+                Event::ByteString(_)
+                | Event::DictKey(_)
+                | Event::Integer(_)
+                | Event::BigInteger(_)
+                | Event::RawInteger(_) => {}
+            }
+            if depth == 0 {
+                return self.skip_pending();
+            }
+        }
+    }
+
+    /// Streams the contents of the byte string announced by the last
+    /// `Event::ByteString` into `writer` chunk by chunk, so that even huge
+    /// strings never have to be held in memory, and returns their length.
+    /// Writes nothing if they have already been consumed.
+    ///
+    /// ```
+    /// use bencode_decode::{Event, Parser};
+    ///
+    /// let mut parser = Parser::new("l4:spame".as_bytes());
+    /// parser.next_event().unwrap();
+    /// assert_eq!(parser.next_event().unwrap(), Event::ByteString(4));
+    /// let mut sink = vec![];
+    /// assert_eq!(parser.copy_bytes(&mut sink).unwrap(), 4);
+    /// assert_eq!(sink, b"spam");
+    /// ```
+    pub fn copy_bytes<W: Write>(&mut self, writer: &mut W) -> Result<usize, Error> {
+        if let Some(bytes) = self.unread.take() {
+            writer
+                .write_all(&bytes)
+                .map_err(|e| Error::io(e, self.pos))?;
+            return Ok(bytes.len());
+        }
+        let size = mem::take(&mut self.pending);
+        drain(&mut self.counting(), size, |chunk| writer.write_all(chunk))?;
+        Ok(size)
+    }
+
+    /// Drops byte string contents the caller didn't ask for. Runs before
+    /// every token, so it returns early in the common case of nothing left.
+    pub(crate) fn skip_pending(&mut self) -> Result<(), Error> {
+        self.unread = None;
+        if self.pending == 0 {
+            return Ok(());
+        }
+        let size = mem::take(&mut self.pending);
+        drain(&mut self.counting(), size, |_| Ok(()))
+    }
+}
+
+/// Reads `size` bytes off `reader` in fixed-size chunks, handing each one to
+/// `sink`.
+fn drain<R: Read>(
+    reader: &mut Counting<R>,
+    size: usize,
+    mut sink: impl FnMut(&[u8]) -> Result<(), io::Error>,
+) -> Result<(), Error> {
+    let mut remaining = size;
+    let mut buf = [0; 4096];
+    while remaining > 0 {
+        let len = remaining.min(buf.len());
+        match reader
+            .read(&mut buf[..len])
+            .map_err(|e| Error::io(e, reader.offset()))?
+        {
+            0 => {
+                return Err(Error::TruncatedByteString {
+                    declared: size,
+                    available: size - remaining,
+                    offset: reader.offset(),
+                })
+            }
+            read => {
+                sink(&buf[..read]).map_err(|e| Error::io(e, reader.offset()))?;
+                remaining -= read;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::decode;
+    #[cfg(feature = "std")]
+    use std::fs::File;
+
+    #[test]
+    fn events() {
+        let mut parser = Parser::new("li-1e0:l3:abcee".as_bytes());
+        let mut events = vec![];
+        loop {
+            match parser.next_event().unwrap() {
+                Event::Eof => break,
+                event => events.push(event),
+            }
+        }
+        assert_eq!(
+            events,
+            [
+                Event::ListStart,
+                Event::Integer(-1),
+                Event::ByteString(0),
+                Event::ListStart,
+                Event::ByteString(3),
+                Event::End,
+                Event::End,
+            ]
+        );
+    }
+
+    #[test]
+    fn dict_keys() {
+        let input = "d1:ad1:bl1:cee1:xd1:d1:ee1:fi1e1:g3:hij1:kd1:l1:me1:n1:oe";
+        let mut parser = Parser::new(input.as_bytes());
+        parser.set_report_keys(true);
+        let mut events = vec![];
+        loop {
+            match parser.next_event().unwrap() {
+                Event::Eof => break,
+                // Values consumed via other APIs are tracked as well.
+                Event::DictKey(len) => match parser.read_bytes().unwrap().as_slice() {
+                    b"g" => parser.skip_value().unwrap(),
+                    b"k" => drop(decode(&mut parser).unwrap()),
+                    _ => events.push(Event::DictKey(len)),
+                },
+                event => events.push(event),
+            }
+        }
+        use Event::*;
+        assert_eq!(
+            events,
+            [
+                DictStart,
+                DictKey(1),
+                DictStart,
+                DictKey(1),
+                ListStart,
+                ByteString(1),
+                End,
+                End,
+                DictKey(1),
+                DictStart,
+                DictKey(1),
+                ByteString(1),
+                End,
+                DictKey(1),
+                Integer(1),
+                DictKey(1),
+                ByteString(1),
+                End,
+            ]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn skip_value() {
+        let f = File::open("./test/ubuntu-18.04.4-live-server-amd64.iso.torrent").unwrap();
+        let mut parser = Parser::new(f);
+        assert_eq!(parser.next_event().unwrap(), Event::DictStart);
+        let mut keys = vec![];
+        while let Event::ByteString(_) = parser.next_event().unwrap() {
+            keys.push(parser.read_bytes().unwrap());
+            parser.skip_value().unwrap();
+        }
+        assert_eq!(
+            keys,
+            [
+                b"announce".to_vec(),
+                b"announce-list".to_vec(),
+                b"comment".to_vec(),
+                b"creation date".to_vec(),
+                b"info".to_vec(),
+            ]
+        );
+        assert_eq!(parser.next_event().unwrap(), Event::Eof);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn copy_bytes() {
+        /// Counts what's written to it without keeping anything.
+        struct Counter(usize);
+
+        impl std::io::Write for Counter {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0 += buf.len();
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let mut input = b"l100000:".to_vec();
+        input.resize(input.len() + 100_000, b'x');
+        input.extend_from_slice(b"i1ee");
+        let mut parser = Parser::new(input.as_slice());
+        assert_eq!(parser.next_event().unwrap(), Event::ListStart);
+        assert_eq!(parser.next_event().unwrap(), Event::ByteString(100_000));
+        let mut counter = Counter(0);
+        assert_eq!(parser.copy_bytes(&mut counter).unwrap(), 100_000);
+        assert_eq!(counter.0, 100_000);
+        assert_eq!(parser.copy_bytes(&mut counter).unwrap(), 0);
+        assert_eq!(parser.next_event().unwrap(), Event::Integer(1));
+
+        // Bytes buffered by `peek` are streamed as well.
+        let mut parser = Parser::new("3:abc".as_bytes());
+        parser.peek();
+        assert_eq!(parser.next_event().unwrap(), Event::ByteString(3));
+        let mut sink = vec![];
+        assert_eq!(parser.copy_bytes(&mut sink).unwrap(), 3);
+        assert_eq!(sink, b"abc");
+
+        let mut parser = Parser::new("5:abc".as_bytes());
+        parser.next_event().unwrap();
+        assert!(matches!(
+            parser.copy_bytes(&mut vec![]),
+            Err(Error::TruncatedByteString {
+                declared: 5,
+                available: 3,
+                offset: 5
+            })
+        ));
+    }
+
+    #[test]
+    fn mixed_with_decode() {
+        let mut parser = Parser::new("l3:abci1ei2ee".as_bytes());
+        assert_eq!(parser.next_event().unwrap(), Event::ListStart);
+        assert_eq!(parser.next_event().unwrap(), Event::ByteString(3));
+        parser.skip_value().unwrap();
+        assert_eq!(decode(&mut parser).unwrap(), crate::Value::Integer(2));
+    }
+
+    #[test]
+    fn malformed() {
+        let mut parser = Parser::new("e".as_bytes());
+        assert!(matches!(
+            parser.skip_value(),
+            Err(Error::UnexpectedEnd { offset: 0 })
+        ));
+        let mut parser = Parser::new("l5:ab".as_bytes());
+        assert!(matches!(
+            parser.skip_value(),
+            Err(Error::TruncatedByteString {
+                declared: 5,
+                available: 2,
+                offset: 5
+            })
+        ));
+        let mut parser = Parser::new("5:ab".as_bytes());
+        parser.next_event().unwrap();
+        assert!(matches!(
+            parser.read_bytes(),
+            Err(Error::TruncatedByteString {
+                declared: 5,
+                available: 2,
+                offset: 4
+            })
+        ));
+    }
+}
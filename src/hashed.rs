@@ -0,0 +1,150 @@
+use crate::io::Read;
+use crate::{build, Error, Key, Parser, Tree, Value};
+use alloc::{string::String, vec::Vec};
+use core::ops::Range;
+use std::collections::HashMap;
+
+/// Like `Value`, but dictionaries are `HashMap`s, as produced by
+/// `decode_hashed`. Lookups in large dictionaries are cheaper, at the cost of
+/// key order; convert with `to_value` to encode canonically.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum HashedValue {
+    ByteString(Vec<u8>),
+    Integer(i64),
+    BigInteger(String),
+    /// See `Value::RawInteger`.
+    RawInteger(String),
+    List(Vec<HashedValue>),
+    Dictionary(HashMap<Vec<u8>, HashedValue>),
+}
+
+impl HashedValue {
+    /// Looks up `key` if this is a `Dictionary`.
+    pub fn get(&self, key: impl AsRef<[u8]>) -> Option<&HashedValue> {
+        match self {
+            HashedValue::Dictionary(map) => map.get(key.as_ref()),
+            _ => None,
+        }
+    }
+
+    /// Converts into a plain `Value`, sorting dictionary keys.
+    pub fn to_value(&self) -> Value {
+        match self {
+            HashedValue::ByteString(bytes) => Value::ByteString(bytes.clone()),
+            HashedValue::Integer(int) => Value::Integer(*int),
+            HashedValue::BigInteger(digits) => Value::BigInteger(digits.clone()),
+            HashedValue::RawInteger(digits) => Value::RawInteger(digits.clone()),
+            HashedValue::List(list) => {
+                Value::List(list.iter().map(HashedValue::to_value).collect())
+            }
+            HashedValue::Dictionary(map) => Value::Dictionary(
+                map.iter()
+                    .map(|(key, value)| (Key::from(key.clone()), value.to_value()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// Like `decode`, but builds `HashMap` dictionaries, for lookup-heavy use of
+/// huge dictionaries where key order doesn't matter. Of duplicate keys, the
+/// last entry wins.
+///
+/// ```
+/// use bencode_decode::{decode_hashed, HashedValue, Parser};
+///
+/// let input = b"d4:name4:spam6:lengthi3ee";
+/// let value = decode_hashed(&mut Parser::new(&input[..])).unwrap();
+/// assert_eq!(value.get("length"), Some(&HashedValue::Integer(3)));
+/// ```
+pub fn decode_hashed<R: Read>(parser: &mut Parser<R>) -> Result<HashedValue, Error> {
+    build(parser, Hashed)
+}
+
+/// The `Tree` of `HashedValue`s.
+struct Hashed;
+
+impl Tree for Hashed {
+    type Node = HashedValue;
+
+    fn scalar(&mut self, value: Value, _: Range<usize>) -> HashedValue {
+        match value {
+            Value::ByteString(bytes) => HashedValue::ByteString(bytes),
+            Value::Integer(int) => HashedValue::Integer(int),
+            Value::BigInteger(digits) => HashedValue::BigInteger(digits),
+            Value::RawInteger(digits) => HashedValue::RawInteger(digits),
+            _ => unreachable!("the parser only emits scalars"),
+        }
+    }
+
+    fn list(&mut self, items: Vec<HashedValue>, _: Range<usize>) -> HashedValue {
+        HashedValue::List(items)
+    }
+
+    fn dict(
+        &mut self,
+        entries: Vec<(usize, Vec<u8>, HashedValue)>,
+        _: Range<usize>,
+    ) -> HashedValue {
+        HashedValue::Dictionary(
+            entries
+                .into_iter()
+                .map(|(_, key, value)| (key, value))
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::decode;
+    use std::fs;
+
+    #[test]
+    fn torrent() {
+        let raw = fs::read("./test/ubuntu-18.04.4-live-server-amd64.iso.torrent").unwrap();
+        let value = decode_hashed(&mut Parser::new(raw.as_slice())).unwrap();
+        let owned = decode(&mut Parser::new(raw.as_slice())).unwrap();
+        assert_eq!(value.to_value(), owned);
+        assert_eq!(
+            value.get("info").unwrap().get("name"),
+            Some(&HashedValue::ByteString(
+                b"ubuntu-18.04.4-live-server-amd64.iso".to_vec()
+            ))
+        );
+    }
+
+    #[test]
+    fn malformed() {
+        let decode = |input: &str, strict: bool| {
+            let mut parser = Parser::new(input.as_bytes());
+            parser.set_strict(strict);
+            decode_hashed(&mut parser)
+        };
+        assert_eq!(
+            decode("d1:ai1e1:ai2ee", false).unwrap().get("a"),
+            Some(&HashedValue::Integer(2))
+        );
+        assert!(matches!(
+            decode("d1:bi1e1:ai2ee", true),
+            Err(Error::UnsortedKeys { offset: 7 })
+        ));
+        assert!(matches!(
+            decode("d1:ai1e1:ai2ee", true),
+            Err(Error::DuplicateKey { offset: 7 })
+        ));
+        assert!(matches!(
+            decode("di1ei2ee", false),
+            Err(Error::NonStringKey { offset: 1 })
+        ));
+        assert!(matches!(
+            decode("d1:ae", false),
+            Err(Error::OddDictLength { offset: 4 })
+        ));
+        assert!(matches!(
+            decode("e", false),
+            Err(Error::UnexpectedEnd { offset: 0 })
+        ));
+    }
+}
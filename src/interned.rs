@@ -0,0 +1,185 @@
+use crate::io::Read;
+use crate::{build, Error, Parser, Tree, Value};
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::{rc::Rc, string::String, vec::Vec};
+use core::ops::Range;
+
+/// Byte strings up to this length are interned by `decode_interned`. That
+/// covers keys and path components, while long strings like `pieces` are
+/// unlikely to repeat and not worth looking up.
+const INTERN_LIMIT: usize = 64;
+
+/// Like `Value`, but byte strings are reference counted, as produced by
+/// `decode_interned`. Equal short strings share a single allocation.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum InternedValue {
+    ByteString(Rc<[u8]>),
+    Integer(i64),
+    BigInteger(String),
+    /// See `Value::RawInteger`.
+    RawInteger(String),
+    List(Vec<InternedValue>),
+    Dictionary(BTreeMap<Rc<[u8]>, InternedValue>),
+}
+
+impl InternedValue {
+    /// Looks up `key` if this is a `Dictionary`.
+    pub fn get(&self, key: impl AsRef<[u8]>) -> Option<&InternedValue> {
+        match self {
+            InternedValue::Dictionary(map) => map.get(key.as_ref()),
+            _ => None,
+        }
+    }
+
+    /// Converts into a plain `Value`, copying every byte string.
+    pub fn to_value(&self) -> Value {
+        match self {
+            InternedValue::ByteString(bytes) => Value::ByteString(bytes.to_vec()),
+            InternedValue::Integer(int) => Value::Integer(*int),
+            InternedValue::BigInteger(digits) => Value::BigInteger(digits.clone()),
+            InternedValue::RawInteger(digits) => Value::RawInteger(digits.clone()),
+            InternedValue::List(list) => {
+                Value::List(list.iter().map(InternedValue::to_value).collect())
+            }
+            InternedValue::Dictionary(map) => Value::Dictionary(
+                map.iter()
+                    .map(|(key, value)| (key.to_vec().into(), value.to_value()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// Like `decode`, but keys and other short byte strings that occur more than
+/// once are only allocated once. Multi-file torrents repeat keys like
+/// `length` and `path` for every file, so this cuts their memory use
+/// considerably.
+///
+/// ```
+/// use bencode_decode::{decode_interned, InternedValue, Parser};
+///
+/// let input = b"l4:spam4:spame";
+/// match decode_interned(&mut Parser::new(&input[..])).unwrap() {
+///     InternedValue::List(list) => assert_eq!(list[0], list[1]),
+///     _ => unreachable!(),
+/// }
+/// ```
+pub fn decode_interned<R: Read>(parser: &mut Parser<R>) -> Result<InternedValue, Error> {
+    build(parser, Interned::default())
+}
+
+/// The `Tree` of `InternedValue`s, along with the strings interned so far.
+#[derive(Default)]
+struct Interned {
+    pool: BTreeSet<Rc<[u8]>>,
+}
+
+impl Interned {
+    fn intern(&mut self, bytes: Vec<u8>) -> Rc<[u8]> {
+        if bytes.len() > INTERN_LIMIT {
+            return bytes.into();
+        }
+        if let Some(shared) = self.pool.get(bytes.as_slice()) {
+            return shared.clone();
+        }
+        let shared: Rc<[u8]> = bytes.into();
+        self.pool.insert(shared.clone());
+        shared
+    }
+}
+
+impl Tree for Interned {
+    type Node = InternedValue;
+
+    fn scalar(&mut self, value: Value, _: Range<usize>) -> InternedValue {
+        match value {
+            Value::ByteString(bytes) => InternedValue::ByteString(self.intern(bytes)),
+            Value::Integer(int) => InternedValue::Integer(int),
+            Value::BigInteger(digits) => InternedValue::BigInteger(digits),
+            Value::RawInteger(digits) => InternedValue::RawInteger(digits),
+            _ => unreachable!("the parser only emits scalars"),
+        }
+    }
+
+    fn list(&mut self, items: Vec<InternedValue>, _: Range<usize>) -> InternedValue {
+        InternedValue::List(items)
+    }
+
+    fn dict(
+        &mut self,
+        entries: Vec<(usize, Vec<u8>, InternedValue)>,
+        _: Range<usize>,
+    ) -> InternedValue {
+        InternedValue::Dictionary(
+            entries
+                .into_iter()
+                .map(|(_, key, value)| (self.intern(key), value))
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::decode;
+    use std::fs;
+
+    #[test]
+    fn shared() {
+        let raw = fs::read("./test/ubuntu-18.04.4-live-server-amd64.iso.torrent").unwrap();
+        let value = decode_interned(&mut Parser::new(raw.as_slice())).unwrap();
+        let owned = decode(&mut Parser::new(raw.as_slice())).unwrap();
+        assert_eq!(value.to_value(), owned);
+
+        let input = b"ld6:lengthi1e4:pathl1:aeed6:lengthi2e4:pathl1:aeee";
+        let files = match decode_interned(&mut Parser::new(&input[..])).unwrap() {
+            InternedValue::List(files) => files,
+            _ => unreachable!(),
+        };
+        let keys = |file: &InternedValue| match file {
+            InternedValue::Dictionary(map) => map.keys().cloned().collect::<Vec<_>>(),
+            _ => unreachable!(),
+        };
+        for (first, second) in keys(&files[0]).iter().zip(&keys(&files[1])) {
+            assert!(Rc::ptr_eq(first, second));
+        }
+        match (files[0].get("path"), files[1].get("path")) {
+            (Some(InternedValue::List(first)), Some(InternedValue::List(second))) => {
+                assert_eq!(first, second);
+                match (&first[0], &second[0]) {
+                    (InternedValue::ByteString(a), InternedValue::ByteString(b)) => {
+                        assert!(Rc::ptr_eq(a, b))
+                    }
+                    _ => unreachable!(),
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn malformed() {
+        let decode = |input: &str, strict: bool| {
+            let mut parser = Parser::new(input.as_bytes());
+            parser.set_strict(strict);
+            decode_interned(&mut parser)
+        };
+        assert!(matches!(
+            decode("d1:bi1e1:ai2ee", true),
+            Err(Error::UnsortedKeys { offset: 7 })
+        ));
+        assert!(matches!(
+            decode("di1ei2ee", false),
+            Err(Error::NonStringKey { offset: 1 })
+        ));
+        assert!(matches!(
+            decode("d1:ae", false),
+            Err(Error::OddDictLength { offset: 4 })
+        ));
+        assert!(matches!(
+            decode("e", false),
+            Err(Error::UnexpectedEnd { offset: 0 })
+        ));
+    }
+}
@@ -0,0 +1,127 @@
+//! The I/O traits the parser and encoder work with.
+//!
+//! With the default `std` feature these are simply `std::io::Read`,
+//! `std::io::Write` and `std::io::Error`. Without it, they are minimal
+//! stand-ins implemented for byte slices and `Vec<u8>`, which `no_std` users
+//! can implement for their own sources and sinks.
+
+#[cfg(feature = "std")]
+pub use std::io::{Error, Read, Write};
+
+#[cfg(not(feature = "std"))]
+pub use self::imp::{Error, Read, Write};
+
+#[cfg(feature = "std")]
+pub(crate) fn is_unexpected_eof(error: &Error) -> bool {
+    error.kind() == std::io::ErrorKind::UnexpectedEof
+}
+
+#[cfg(not(feature = "std"))]
+pub(crate) fn is_unexpected_eof(_: &Error) -> bool {
+    false
+}
+
+#[cfg(feature = "std")]
+pub(crate) fn is_interrupted(error: &Error) -> bool {
+    error.kind() == std::io::ErrorKind::Interrupted
+}
+
+#[cfg(not(feature = "std"))]
+pub(crate) fn is_interrupted(_: &Error) -> bool {
+    false
+}
+
+#[cfg(not(feature = "std"))]
+mod imp {
+    use alloc::vec::Vec;
+    use core::fmt;
+
+    /// An error raised by a `Read` or `Write` implementation.
+    #[derive(Debug)]
+    pub struct Error {
+        message: &'static str,
+    }
+
+    impl Error {
+        pub fn new(message: &'static str) -> Self {
+            Self { message }
+        }
+    }
+
+    impl fmt::Display for Error {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str(self.message)
+        }
+    }
+
+    /// A source of bytes, like `std::io::Read`.
+    pub trait Read {
+        /// Reads some bytes into `buf`, returning how many were read. `0`
+        /// signals the end of the input.
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error>;
+    }
+
+    impl Read for &[u8] {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+            let len = buf.len().min(self.len());
+            let (head, tail) = self.split_at(len);
+            buf[..len].copy_from_slice(head);
+            *self = tail;
+            Ok(len)
+        }
+    }
+
+    impl<R: Read + ?Sized> Read for &mut R {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+            (**self).read(buf)
+        }
+    }
+
+    impl<R: Read + ?Sized> Read for alloc::boxed::Box<R> {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+            (**self).read(buf)
+        }
+    }
+
+    /// A sink for bytes, like `std::io::Write`.
+    pub trait Write {
+        fn write_all(&mut self, buf: &[u8]) -> Result<(), Error>;
+
+        /// Backs the `write!` macro.
+        fn write_fmt(&mut self, args: fmt::Arguments) -> Result<(), Error> {
+            struct Adapter<'a, W: ?Sized> {
+                inner: &'a mut W,
+                error: Option<Error>,
+            }
+
+            impl<W: Write + ?Sized> fmt::Write for Adapter<'_, W> {
+                fn write_str(&mut self, s: &str) -> fmt::Result {
+                    self.inner.write_all(s.as_bytes()).map_err(|e| {
+                        self.error = Some(e);
+                        fmt::Error
+                    })
+                }
+            }
+
+            let mut adapter = Adapter {
+                inner: self,
+                error: None,
+            };
+            fmt::write(&mut adapter, args)
+                .map_err(|_| adapter.error.unwrap_or(Error::new("formatter error")))
+        }
+    }
+
+    impl Write for Vec<u8> {
+        fn write_all(&mut self, buf: &[u8]) -> Result<(), Error> {
+            self.extend_from_slice(buf);
+            Ok(())
+        }
+    }
+
+    impl<W: Write + ?Sized> Write for &mut W {
+        fn write_all(&mut self, buf: &[u8]) -> Result<(), Error> {
+            (**self).write_all(buf)
+        }
+    }
+}
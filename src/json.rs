@@ -0,0 +1,112 @@
+use crate::Value::{self, *};
+use serde_json::{Map, Value as Json};
+
+/// How `Value::to_json_with` renders byte strings that aren't valid UTF-8.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum BinaryEncoding {
+    /// Lowercase hexadecimal, two characters per byte.
+    Hex,
+    /// Standard base64 with padding.
+    Base64,
+}
+
+impl Value {
+    /// Converts this value into JSON for inspection and scripting. See
+    /// `to_json_with`; binary byte strings are hex encoded.
+    ///
+    /// ```
+    /// use bencode_decode::from_bytes;
+    ///
+    /// let value = from_bytes(b"d4:name4:spam6:pieces2:\xff\xfe4:sizei3ee").unwrap();
+    /// assert_eq!(
+    ///     value.to_json().to_string(),
+    ///     r#"{"name":"spam","pieces":"fffe","size":3}"#
+    /// );
+    /// ```
+    pub fn to_json(&self) -> Json {
+        self.to_json_with(BinaryEncoding::Hex)
+    }
+
+    /// Converts this value into JSON. Integers become numbers, lists arrays
+    /// and dictionaries objects; a `BigInteger` beyond `u64` becomes a
+    /// string of its digits. Byte strings - including dictionary keys -
+    /// become strings, using `binary` for those that aren't valid UTF-8.
+    /// Since both end up as JSON strings, the conversion can't be reversed.
+    pub fn to_json_with(&self, binary: BinaryEncoding) -> Json {
+        match self {
+            ByteString(bytes) => Json::String(json_string(bytes, binary)),
+            Integer(int) => Json::from(*int),
+            BigInteger(digits) => digits
+                .parse::<u64>()
+                .map(Json::from)
+                .unwrap_or_else(|_| Json::String(digits.clone())),
+            RawInteger(digits) => match (self.as_integer(), self.as_u64()) {
+                (Some(int), _) => Json::from(int),
+                (_, Some(int)) => Json::from(int),
+                _ => Json::String(digits.clone()),
+            },
+            List(list) => Json::Array(list.iter().map(|item| item.to_json_with(binary)).collect()),
+            Dictionary(dict) => Json::Object(
+                dict.iter()
+                    .map(|(key, value)| (json_string(key, binary), value.to_json_with(binary)))
+                    .collect::<Map<_, _>>(),
+            ),
+        }
+    }
+}
+
+fn json_string(bytes: &[u8], binary: BinaryEncoding) -> String {
+    match std::str::from_utf8(bytes) {
+        Ok(str) => str.to_string(),
+        Err(_) => match binary {
+            BinaryEncoding::Hex => bytes.iter().map(|byte| format!("{:02x}", byte)).collect(),
+            BinaryEncoding::Base64 => base64(bytes),
+        },
+    }
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &byte)| n | (byte as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::from_bytes;
+    use serde_json::json;
+
+    #[test]
+    fn convert() {
+        let value = from_bytes(b"d1:ali1e0:e2:\xff\x00i-2e1:bdee").unwrap();
+        assert_eq!(value.to_json(), json!({"a": [1, ""], "ff00": -2, "b": {}}));
+        assert_eq!(
+            value.to_json_with(BinaryEncoding::Base64),
+            json!({"a": [1, ""], "/wA=": -2, "b": {}})
+        );
+    }
+
+    #[test]
+    fn base64_padding() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+        assert_eq!(base64(&[0xff, 0xfe, 0xfd, 0xfc]), "//79/A==");
+    }
+}
@@ -1,13 +1,111 @@
-use std::{collections::BTreeMap, io::Read, result::Result};
+//! Minimal, lean, iterator-based bencode decoder.
+//!
+//! Most of the time, a whole document is decoded at once into a `Value`,
+//! whose typed accessors pick it apart:
+//!
+//! ```
+//! use bencode_decode::from_bytes;
+//!
+//! let torrent = from_bytes(b"d8:announce9:udp://one4:infod6:lengthi42e4:name4:spamee").unwrap();
+//! assert_eq!(torrent["announce"].as_str(), Some("udp://one"));
+//! assert_eq!(torrent["info"]["length"].as_integer(), Some(42));
+//! assert_eq!(torrent.get_path(["info", "name"]).and_then(|v| v.as_bytes()), Some(&b"spam"[..]));
+//! assert!(torrent.get("comment").is_none());
+//! ```
+//!
+//! Values encode back into canonical bencode, so decoding and re-encoding
+//! canonical input gives back the same bytes:
+//!
+//! ```
+//! use bencode_decode::{bencode, encode, from_bytes};
+//!
+//! let value = bencode!({ "list": [1, "two"], "name": "spam" });
+//! let bytes = encode(&value);
+//! assert_eq!(bytes, b"d4:listli1e3:twoe4:name4:spame");
+//! assert_eq!(from_bytes(&bytes).unwrap(), value);
+//! ```
+//!
+//! To read from an `io::Read` rather than a slice, see `from_reader`. A
+//! `Parser` gives access to the individual tokens and events, and to
+//! settings like resource limits and strict mode.
+
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+use alloc::{boxed::Box, collections::BTreeMap, string::String, vec, vec::Vec};
+use core::cmp::Ordering;
+use core::convert::TryFrom;
+use core::ops::Range;
+use io::Read;
+
+#[cfg(feature = "tokio")]
+mod async_parser;
+mod borrowed;
+mod canonical;
+#[cfg(feature = "serde")]
+pub mod de;
+mod display;
+mod encode;
+mod error;
+mod event;
+#[cfg(feature = "std")]
+mod hashed;
+mod interned;
+pub mod io;
+#[cfg(feature = "serde_json")]
+mod json;
+mod limits;
+mod ordered;
+#[cfg(feature = "peer")]
+pub mod peer;
+mod report;
+#[cfg(feature = "serde")]
+pub mod ser;
+mod spanned;
+mod tokenizer;
+#[cfg(feature = "torrent")]
+pub mod torrent;
+mod value;
+mod visit;
+#[cfg(feature = "tokio")]
+pub use async_parser::{decode_async, AsyncParser};
+pub use borrowed::{decode_slice, decode_slice_with, ValueRef};
+pub use canonical::{check_canonical, is_canonical};
+pub use encode::encode;
+pub use error::Error;
+pub use event::Event;
+use event::{Open, Shape};
+#[cfg(feature = "std")]
+pub use hashed::{decode_hashed, HashedValue};
+pub use interned::{decode_interned, InternedValue};
+#[cfg(feature = "serde_json")]
+pub use json::BinaryEncoding;
+pub use limits::{decode_with_limits, Limits};
+pub use ordered::{decode_ordered, OrderedValue};
+pub use report::{decode_with_report, ComplianceReport, Violation};
+pub use spanned::{decode_spanned, Spanned, SpannedValue};
+pub use tokenizer::Tokenizer;
+pub use value::Key;
+pub use visit::{decode_visit, Visitor};
 // Bencoding spec
 // https://wiki.theory.org/index.php/BitTorrentSpecification#Bencoding
 
-#[derive(PartialEq, Ord, PartialOrd, Eq, Debug, Clone)]
+#[derive(PartialEq, Ord, PartialOrd, Eq, Hash, Debug, Clone)]
 pub enum Value {
     ByteString(Vec<u8>),
     Integer(i64),
+    /// A well-formed integer outside the range of `i64`, kept as its decimal
+    /// digits (with a leading `-` if negative) so nothing is lost. It encodes
+    /// back into the same bytes.
+    BigInteger(String),
+    /// An integer with leading zeros or a negative zero, like `i007e`, kept
+    /// as written so it encodes back into the same bytes, see
+    /// `Parser::set_raw_integers`. Accessors and comparisons with `i64` go by
+    /// its value.
+    RawInteger(String),
     List(Vec<Value>),
-    Dictionary(BTreeMap<Vec<u8>, Value>),
+    Dictionary(BTreeMap<Key, Value>),
 }
 
 #[derive(PartialEq, Debug)]
@@ -20,39 +118,561 @@ pub enum ParseResult {
 }
 
 /// Constructs a `Parser` for bencoded data from a reader implementing
-/// `std::io::read`. The only exposed interface is an iterator, which
+/// `io::Read`. The only exposed interface is an iterator, which
 /// will emit parsed tokens `ParseResult` up until (but not including)
-/// EOF.
+/// EOF. Malformed input and read errors are yielded as an `Err`, after
+/// which the iterator ends.
 ///
 /// ```
 /// use bencode_decode::Parser;
-/// let input = std::io::Cursor::new(
-///            "d9:publisher3:bob17:publisher-webpage15:www.example.com18:publisher.location4:homee"
-///                .to_string()
-///                .into_bytes(),
-///        );
-/// let mut parser = Parser::new(input);
+/// let input = b"d9:publisher3:bob17:publisher-webpage15:www.example.com18:publisher.location4:homee";
+/// let parser = Parser::from_slice(input);
 /// for item in parser {
-///     println!("{:?}", item);
+///     println!("{:?}", item.unwrap());
 /// }
 /// ```
 pub struct Parser<R: Read> {
     reader: R,
+    max_depth: usize,
+    max_items: usize,
+    read_limit: usize,
+    strict: bool,
+    pos: usize,
+    token_start: usize,
+    pending: usize,
+    unread: Option<Vec<u8>>,
+    peeked: Option<(Result<ParseResult, Error>, usize)>,
+    buffer: Buffer,
+    open: Option<Vec<Open>>,
+    failed: bool,
+    trailing_whitespace: bool,
+    raw_integers: bool,
+    max_prealloc: usize,
+    tokenizer: Tokenizer,
+}
+
+impl<'a> Parser<&'a [u8]> {
+    /// Parses an in-memory buffer. Same as `Parser::new`, which accepts a
+    /// byte slice as well since it implements `Read`; to decode a single
+    /// value from a slice, see `from_bytes` and `decode_slice`.
+    ///
+    /// ```
+    /// use bencode_decode::{decode, Parser, Value};
+    ///
+    /// let mut parser = Parser::from_slice(b"i42e");
+    /// assert_eq!(decode(&mut parser).unwrap(), Value::Integer(42));
+    /// ```
+    pub fn from_slice(input: &'a [u8]) -> Self {
+        Self::new(input)
+    }
+}
+
+impl<'a> Parser<Box<dyn Read + 'a>> {
+    /// Parses a reader whose type is only known at runtime, so parsers over
+    /// files, sockets or decompressors can share one type. `&mut dyn Read`
+    /// works with `Parser::new` as well.
+    ///
+    /// ```
+    /// use bencode_decode::{decode, io::Read, Parser, Value};
+    ///
+    /// let reader: Box<dyn Read> = Box::new("i42e".as_bytes());
+    /// let mut parser = Parser::new_boxed(reader);
+    /// assert_eq!(decode(&mut parser).unwrap(), Value::Integer(42));
+    /// ```
+    pub fn new_boxed(reader: Box<dyn Read + 'a>) -> Self {
+        Self::new(reader)
+    }
 }
+
+/// Maximum number of nested containers `decode` accepts unless configured
+/// otherwise via `Parser::with_max_depth`.
+pub const DEFAULT_MAX_DEPTH: usize = 1000;
+
 impl<R: Read> Parser<R> {
     pub fn new(reader: R) -> Self {
-        Self { reader }
+        Self::with_max_depth(reader, DEFAULT_MAX_DEPTH)
+    }
+
+    /// Like `new`, but `decode` fails with `Error::DepthLimitExceeded` once
+    /// more than `max_depth` containers are open at the same time.
+    pub fn with_max_depth(reader: R, max_depth: usize) -> Self {
+        Self {
+            reader,
+            max_depth,
+            max_items: usize::MAX,
+            read_limit: usize::MAX,
+            strict: false,
+            pos: 0,
+            token_start: 0,
+            pending: 0,
+            unread: None,
+            peeked: None,
+            buffer: Buffer::default(),
+            open: None,
+            failed: false,
+            trailing_whitespace: false,
+            raw_integers: false,
+            max_prealloc: PREALLOC_LIMIT,
+            tokenizer: Tokenizer::new(),
+        }
+    }
+
+    /// Like `new`, but the parser doesn't read more than `max_bytes` bytes
+    /// from `reader` in total. Reading a token that would go past that fails
+    /// with `Error::ReadLimitExceeded` instead, and byte strings are checked
+    /// against the remaining budget before their contents are read, so a
+    /// malformed frame can't make the parser consume the rest of a stream.
+    ///
+    /// ```
+    /// use bencode_decode::{decode, Error, Parser, Value};
+    ///
+    /// let mut parser = Parser::with_read_limit("i1e99:spam".as_bytes(), 8);
+    /// assert_eq!(decode(&mut parser).unwrap(), Value::Integer(1));
+    /// assert!(matches!(
+    ///     decode(&mut parser),
+    ///     Err(Error::ReadLimitExceeded { offset: 3 })
+    /// ));
+    /// ```
+    pub fn with_read_limit(reader: R, max_bytes: usize) -> Self {
+        let mut parser = Self::new(reader);
+        parser.set_read_limit(max_bytes);
+        parser
+    }
+
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        self.max_depth = max_depth;
+    }
+
+    /// Sets the total number of bytes the parser may read, counted from the
+    /// start of the input. See `with_read_limit`.
+    pub fn set_read_limit(&mut self, max_bytes: usize) {
+        self.read_limit = max_bytes;
+    }
+
+    /// Byte strings declaring a length above `max_len` are rejected with
+    /// `Error::LengthLimitExceeded` before anything is allocated for them.
+    /// Unlimited by default.
+    pub fn set_max_byte_string_len(&mut self, max_len: usize) {
+        self.tokenizer.set_max_byte_string_len(max_len);
+    }
+
+    /// The memory for a byte string is allocated once its length is known,
+    /// but only up to `max_bytes` of it; longer ones grow as their contents
+    /// arrive, so that a bogus length can't make the parser allocate more
+    /// than the input provides. Byte strings up to `max_bytes` long are
+    /// thus never reallocated. 64 KiB by default, while `from_bytes` raises
+    /// it to the length of its input. For trusted input with large byte
+    /// strings, like the `pieces` of a torrent read from a file, pass the
+    /// size of the input or `usize::MAX` to read each in a single allocation.
+    ///
+    /// ```
+    /// use bencode_decode::{decode, Parser};
+    ///
+    /// let mut parser = Parser::new("4:spam".as_bytes());
+    /// parser.set_max_prealloc(usize::MAX);
+    /// let bytes = decode(&mut parser).unwrap().into_bytes().unwrap();
+    /// assert_eq!(bytes.capacity(), 4);
+    /// ```
+    pub fn set_max_prealloc(&mut self, max_bytes: usize) {
+        self.max_prealloc = max_bytes;
+    }
+
+    /// `decode` fails with `Error::ItemLimitExceeded` once a value holds more
+    /// than `max_items` values in total, counting containers as well as
+    /// everything in them. This bounds the number of nodes built from input
+    /// like a huge flat list of tiny integers. Unlimited by default. Applies
+    /// to every decoder reading from this parser, from `decode_ordered` to
+    /// `decode_visit` and `de::Deserializer`.
+    ///
+    /// ```
+    /// use bencode_decode::{decode, Error, Parser};
+    ///
+    /// let mut parser = Parser::new("lleleleee".as_bytes());
+    /// parser.set_max_items(3);
+    /// assert!(matches!(
+    ///     decode(&mut parser),
+    ///     Err(Error::ItemLimitExceeded { offset: 5 })
+    /// ));
+    /// ```
+    pub fn set_max_items(&mut self, max_items: usize) {
+        self.max_items = max_items;
+    }
+
+    /// In strict mode, `decode` insists on canonical dictionaries: keys have
+    /// to be unique and appear in sorted order, otherwise it fails with
+    /// `Error::DuplicateKey` or `Error::UnsortedKeys`. Off by default.
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+        self.tokenizer
+            .set_raw_integers(self.raw_integers && !self.strict);
+    }
+
+    /// Lets ASCII whitespace follow the value checked by `decode_strict`, as
+    /// some tools append a newline to `.torrent` files, and separate the
+    /// values read by `decode_values`. Anything else after the value still
+    /// fails with `Error::TrailingData`. Off by default.
+    ///
+    /// ```
+    /// use bencode_decode::{decode_strict, Error, Parser, Value};
+    ///
+    /// let mut parser = Parser::new("i1e \r\n".as_bytes());
+    /// parser.set_trailing_whitespace(true);
+    /// assert_eq!(decode_strict(&mut parser).unwrap(), Value::Integer(1));
+    ///
+    /// let mut parser = Parser::new("i1e\nx".as_bytes());
+    /// parser.set_trailing_whitespace(true);
+    /// assert!(matches!(
+    ///     decode_strict(&mut parser),
+    ///     Err(Error::TrailingData { offset: 4 })
+    /// ));
+    /// ```
+    pub fn set_trailing_whitespace(&mut self, allow: bool) {
+        self.trailing_whitespace = allow;
+    }
+
+    /// Integers beyond the range of an `i64` are decoded as
+    /// `Value::BigInteger` by default. With `false`, they fail with
+    /// `Error::IntegerOverflow` instead, which is distinct from the
+    /// `Error::InvalidInteger` raised for malformed ones. Digits beyond the
+    /// longest `i64` aren't read then, so `digits` holds at most 20 of them.
+    ///
+    /// ```
+    /// use bencode_decode::{decode, Error, Parser};
+    ///
+    /// let mut parser = Parser::new("i99999999999999999999e".as_bytes());
+    /// parser.set_big_integers(false);
+    /// assert!(matches!(
+    ///     decode(&mut parser),
+    ///     Err(Error::IntegerOverflow { digits, offset: 0 }) if digits == "99999999999999999999"
+    /// ));
+    /// ```
+    pub fn set_big_integers(&mut self, allow: bool) {
+        self.tokenizer.set_big_integers(allow);
+    }
+
+    /// Integers with leading zeros or a negative zero, like `i007e` or
+    /// `i-0e`, fail with `Error::InvalidInteger` by default. With `true`,
+    /// they are decoded as a `Value::RawInteger` holding their contents as
+    /// written instead, so that encoding the value reproduces the input byte
+    /// for byte, while `Value::canonicalize` still normalizes them. They are
+    /// kept regardless of `set_big_integers`, but strict mode rejects them.
+    ///
+    /// ```
+    /// use bencode_decode::{decode, Parser, Value};
+    ///
+    /// let mut parser = Parser::new("li007ei-0ei7ee".as_bytes());
+    /// parser.set_raw_integers(true);
+    /// let value = decode(&mut parser).unwrap();
+    /// assert_eq!(value[0], Value::RawInteger("007".into()));
+    /// assert_eq!(value[0], 7);
+    /// assert_eq!(value[2], Value::Integer(7));
+    /// assert_eq!(value.to_bytes(), b"li007ei-0ei7ee");
+    /// assert_eq!(value.canonicalize().unwrap(), b"li7ei0ei7ee");
+    /// ```
+    pub fn set_raw_integers(&mut self, allow: bool) {
+        self.raw_integers = allow;
+        self.tokenizer
+            .set_raw_integers(self.raw_integers && !self.strict);
+    }
+
+    /// Tokens are scanned a byte at a time, which costs a system call per
+    /// byte on unbuffered readers like a `TcpStream`. With a capacity above
+    /// zero, the parser reads ahead up to that many bytes at once instead.
+    /// Bytes read ahead but not parsed yet are available via `buffer`.
+    /// Unbuffered by default, so that the reader is never consumed past the
+    /// end of the input parsed so far.
+    ///
+    /// ```
+    /// use bencode_decode::{decode, Parser, Value};
+    ///
+    /// let mut parser = Parser::new("i1eextra".as_bytes());
+    /// parser.set_buffer_capacity(8 * 1024);
+    /// assert_eq!(decode(&mut parser).unwrap(), Value::Integer(1));
+    /// assert_eq!(parser.buffer(), b"extra");
+    /// ```
+    pub fn set_buffer_capacity(&mut self, capacity: usize) {
+        self.buffer.capacity = capacity;
+    }
+
+    /// The bytes read ahead from the reader that haven't been parsed yet.
+    pub fn buffer(&self) -> &[u8] {
+        &self.buffer.data[self.buffer.start..self.buffer.end]
+    }
+
+    /// How many bytes of input the parser has consumed so far, for showing
+    /// progress. This is the offset errors are reported at; bytes read ahead
+    /// into `buffer` don't count until they're parsed.
+    ///
+    /// ```
+    /// use bencode_decode::{Event, Parser};
+    ///
+    /// let mut parser = Parser::from_slice(b"l4:spami42ee");
+    /// parser.next_event().unwrap();
+    /// assert_eq!(parser.position(), 1);
+    /// parser.skip_value().unwrap();
+    /// assert_eq!(parser.position(), 7);
+    /// ```
+    pub fn position(&self) -> u64 {
+        self.pos as u64
+    }
+
+    /// The underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// The underlying reader. Reading from it directly skips over whatever
+    /// the parser would have seen next, so mind `buffer`.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    /// Returns the underlying reader, positioned right after the last parsed
+    /// token unless buffering is enabled; see `into_parts` for that case.
+    /// A token fetched by `peek` but not consumed is lost, as are the
+    /// contents of a byte string announced by `next_event` but not read yet.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Like `into_inner`, but also returns the bytes read ahead into the
+    /// buffer, which come before whatever is left in the reader.
+    ///
+    /// ```
+    /// use bencode_decode::{decode, Parser, Value};
+    /// use std::io::Read;
+    ///
+    /// let mut parser = Parser::new("i1eraw tail".as_bytes());
+    /// parser.set_buffer_capacity(4);
+    /// assert_eq!(decode(&mut parser).unwrap(), Value::Integer(1));
+    /// let (mut reader, mut rest) = parser.into_parts();
+    /// reader.read_to_end(&mut rest).unwrap();
+    /// assert_eq!(rest, b"raw tail");
+    /// ```
+    pub fn into_parts(self) -> (R, Vec<u8>) {
+        let buffered = self.buffer().to_vec();
+        (self.reader, buffered)
+    }
+
+    /// Starts over on the input from `reader`, as if the parser had just been
+    /// created for it, but with the same settings and without freeing the
+    /// memory used to read ahead. Decoding many small messages from separate
+    /// sources, like requests from a pool of connections, then doesn't need
+    /// a new parser and its allocations for each.
+    ///
+    /// ```
+    /// use bencode_decode::{decode_strict, Parser, Value};
+    ///
+    /// let mut parser = Parser::new("i1e".as_bytes());
+    /// parser.set_buffer_capacity(8 * 1024);
+    /// assert_eq!(decode_strict(&mut parser).unwrap(), Value::Integer(1));
+    /// parser.reset("i2e".as_bytes());
+    /// assert_eq!(decode_strict(&mut parser).unwrap(), Value::Integer(2));
+    /// assert_eq!(parser.position(), 3);
+    /// ```
+    pub fn reset(&mut self, reader: R) {
+        self.reader = reader;
+        self.pos = 0;
+        self.token_start = 0;
+        self.pending = 0;
+        self.unread = None;
+        self.peeked = None;
+        self.buffer.start = 0;
+        self.buffer.end = 0;
+        if let Some(open) = &mut self.open {
+            open.clear();
+        }
+        self.failed = false;
+    }
+
+    /// Returns the next token without consuming it, or `None` at EOF or if
+    /// the input is malformed; the error is then returned by whatever
+    /// consumes the token.
+    ///
+    /// ```
+    /// use bencode_decode::{decode, Parser, ParseResult, Value};
+    ///
+    /// let mut parser = Parser::new("li1ei2ee".as_bytes());
+    /// assert_eq!(parser.next().unwrap().unwrap(), ParseResult::ListStart);
+    /// let mut sum = 0;
+    /// while parser.peek() != Some(&ParseResult::End) {
+    ///     sum += decode(&mut parser).unwrap().as_integer().unwrap();
+    /// }
+    /// assert_eq!(sum, 3);
+    /// ```
+    pub fn peek(&mut self) -> Option<&ParseResult> {
+        if self.peeked.is_none() {
+            let token = self.read_token();
+            self.peeked = Some((token, self.token_start));
+        }
+        match &self.peeked {
+            Some((Ok(token), _)) if *token != EOF => Some(token),
+            _ => None,
+        }
+    }
+
+    fn next_token(&mut self) -> Result<ParseResult, Error> {
+        let token = match self.peeked.take() {
+            Some((token, start)) => {
+                self.token_start = start;
+                token?
+            }
+            None => self.read_token()?,
+        };
+        if self.open.is_some() {
+            self.track(match token {
+                ValueType(_) => Shape::Scalar,
+                ListStart => Shape::List,
+                DictStart => Shape::Dict,
+                End | EOF => Shape::End,
+            });
+        }
+        Ok(token)
+    }
+
+    fn read_token(&mut self) -> Result<ParseResult, Error> {
+        self.skip_pending()?;
+        self.token_start = self.pos;
+        Ok(match self.tokenize()? {
+            Event::ByteString(size) => {
+                ValueType(ByteString(read_payload(&mut self.counting(), size)?))
+            }
+            event => token(event),
+        })
+    }
+
+    /// Reads the next token through the tokenizer, stopping right after the
+    /// `:` of a byte string and leaving its contents in the reader.
+    fn tokenize(&mut self) -> Result<Event, Error> {
+        let start = self.pos;
+        self.tokenizer.discard();
+        loop {
+            let offset = self.pos;
+            let event = match self.counting().read_byte()? {
+                Some(byte) => self.tokenizer.event(byte, offset)?,
+                None => {
+                    let end = self.tokenizer.end(offset);
+                    if offset >= self.read_limit {
+                        return Err(Error::ReadLimitExceeded { offset: start });
+                    }
+                    Some(end?)
+                }
+            };
+            match event {
+                Some(Event::ByteString(size)) if size > self.counting().remaining() => {
+                    return Err(Error::ReadLimitExceeded { offset: start })
+                }
+                Some(event) => return Ok(event),
+                None => {}
+            }
+        }
+    }
+
+    fn counting(&mut self) -> Counting<'_, R> {
+        Counting {
+            inner: &mut self.reader,
+            pos: &mut self.pos,
+            buffer: &mut self.buffer,
+            limit: self.read_limit,
+            max_prealloc: self.max_prealloc,
+        }
+    }
+}
+
+/// Bytes read ahead from the reader, of which `data[start..end]` haven't been
+/// consumed yet.
+#[derive(Default)]
+struct Buffer {
+    data: Vec<u8>,
+    start: usize,
+    end: usize,
+    capacity: usize,
+}
+
+/// Keeps track of how many bytes have been read through it, and reads ahead
+/// into `buffer` if it has a capacity. The input ends at offset `limit`.
+/// Byte strings reserve up to `max_prealloc` bytes up front.
+struct Counting<'a, R> {
+    inner: &'a mut R,
+    pos: &'a mut usize,
+    buffer: &'a mut Buffer,
+    limit: usize,
+    max_prealloc: usize,
+}
+
+impl<R: Read> Counting<'_, R> {
+    fn offset(&self) -> usize {
+        *self.pos
+    }
+
+    /// The number of bytes left before the read limit.
+    fn remaining(&self) -> usize {
+        self.limit.saturating_sub(*self.pos)
+    }
+
+    /// Reads a single byte, or `None` at EOF.
+    fn read_byte(&mut self) -> Result<Option<u8>, Error> {
+        let mut buf = [0; 1];
+        match self.read(&mut buf) {
+            Ok(0) => Ok(None),
+            Ok(_) => Ok(Some(buf[0])),
+            Err(e) => Err(Error::io(e, self.offset())),
+        }
+    }
+}
+
+impl<R: Read> Read for Counting<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, io::Error> {
+        let remaining = self.remaining();
+        let len = remaining.min(buf.len());
+        let buf = &mut buf[..len];
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let buffer = &mut *self.buffer;
+        if buffer.start == buffer.end {
+            // Like `BufReader`, large reads bypass the buffer.
+            if buf.len() >= buffer.capacity {
+                let read = read_retrying(self.inner, buf)?;
+                *self.pos += read;
+                return Ok(read);
+            }
+            buffer.data.resize(buffer.capacity.min(remaining), 0);
+            buffer.end = read_retrying(self.inner, &mut buffer.data)?;
+            buffer.start = 0;
+        }
+        let available = &buffer.data[buffer.start..buffer.end];
+        let read = available.len().min(buf.len());
+        buf[..read].copy_from_slice(&available[..read]);
+        buffer.start += read;
+        *self.pos += read;
+        Ok(read)
+    }
+}
+
+/// Reads from `reader`, retrying reads that were interrupted.
+fn read_retrying<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<usize, io::Error> {
+    loop {
+        match reader.read(buf) {
+            Err(e) if io::is_interrupted(&e) => {}
+            result => return result,
+        }
     }
 }
 
 impl<R: Read> Iterator for Parser<R> {
-    type Item = ParseResult;
+    type Item = Result<ParseResult, Error>;
     fn next(&mut self) -> Option<Self::Item> {
-        let res = parse(&mut self.reader).ok();
-        if res == Some(ParseResult::EOF) {
-            None
-        } else {
-            res
+        if self.failed {
+            return None;
+        }
+        match self.next_token() {
+            Ok(EOF) => None,
+            Ok(token) => Some(Ok(token)),
+            Err(e) => {
+                self.failed = true;
+                Some(Err(e))
+            }
         }
     }
 }
@@ -65,120 +685,2085 @@ use Value::*;
 ///
 /// ```
 /// use bencode_decode::{Parser, decode};
+/// # #[cfg(feature = "std")] {
 /// use std::fs::File;
 ///
 /// let f = File::open("./test/ubuntu-18.04.4-live-server-amd64.iso.torrent").unwrap();
 /// let mut parser = Parser::new(f);
-/// let res = decode(&mut parser, None).unwrap();
-/// ```
-pub fn decode<R: Read>(parser: &mut Parser<R>, current: Option<ParseResult>) -> Option<Value> {
-    match current.or_else(|| parser.next()) {
-        Some(ValueType(val)) => Some(val),
-        Some(t @ DictStart) | Some(t @ ListStart) => {
-            let mut data = vec![];
-            let mut next = parser.next().expect("Unexpected EOF");
-            while next != End {
-                data.push(decode(parser, Some(next)).unwrap());
-                next = parser.next().expect("Unexpected EOF");
-            }
-            if t == ListStart {
-                Some(Value::List(data))
-            } else {
-                let mut map = BTreeMap::new();
-                let mut input = data.into_iter();
-                while let (Some(ByteString(key)), Some(value)) = (input.next(), input.next()) {
-                    map.insert(key, value);
+/// let res = decode(&mut parser).unwrap();
+/// # }
+/// ```
+///
+/// Malformed input is reported as an `Error` rather than a panic.
+pub fn decode<R: Read>(parser: &mut Parser<R>) -> Result<Value, Error> {
+    build(parser, Plain)
+}
+
+/// Like `decode`, but afterwards checks that the reader is exhausted, failing
+/// with `Error::TrailingData` pointing at the first byte after the value
+/// otherwise.
+///
+/// ```
+/// use bencode_decode::{decode_strict, Error, Parser};
+///
+/// let mut parser = Parser::new("i1ei2e".as_bytes());
+/// assert!(matches!(
+///     decode_strict(&mut parser),
+///     Err(Error::TrailingData { offset: 3 })
+/// ));
+/// ```
+pub fn decode_strict<R: Read>(parser: &mut Parser<R>) -> Result<Value, Error> {
+    let value = decode(parser)?;
+    parser.expect_eof()?;
+    Ok(value)
+}
+
+/// Like `decode`, for input whose top-level value is a list: its items
+/// replace the contents of `buf` instead of going into a new `Vec`. Decoding
+/// message after message into the same `buf` thus reuses its allocation, and
+/// its capacity can be reserved up front. Fails with `Error::TypeMismatch` if
+/// the value isn't a list; on errors, `buf` holds the items read so far.
+///
+/// ```
+/// use bencode_decode::{decode_into, Parser, Value};
+///
+/// let mut parser = Parser::new("li1e4:spamelli2eee".as_bytes());
+/// let mut buf = Vec::with_capacity(16);
+/// decode_into(&mut parser, &mut buf).unwrap();
+/// assert_eq!(buf, [Value::Integer(1), Value::from("spam")]);
+/// decode_into(&mut parser, &mut buf).unwrap();
+/// assert_eq!(buf, [Value::List(vec![Value::Integer(2)])]);
+/// assert!(buf.capacity() >= 16);
+/// ```
+pub fn decode_into<R: Read>(parser: &mut Parser<R>, buf: &mut Vec<Value>) -> Result<(), Error> {
+    buf.clear();
+    let offset = match parser.next_token()? {
+        ListStart => parser.token_start,
+        ValueType(value) => {
+            return Err(Error::TypeMismatch {
+                expected: "list",
+                found: value.type_name(),
+            })
+        }
+        DictStart => {
+            return Err(Error::TypeMismatch {
+                expected: "list",
+                found: "dictionary",
+            })
+        }
+        End => {
+            return Err(Error::UnexpectedEnd {
+                offset: parser.token_start,
+            })
+        }
+        EOF => {
+            return Err(Error::UnexpectedEof {
+                offset: parser.token_start,
+            })
+        }
+    };
+    // The list itself counts towards the limits, as with `decode`.
+    if parser.max_items == 0 {
+        return Err(Error::ItemLimitExceeded { offset });
+    }
+    if parser.max_depth == 0 {
+        return Err(Error::DepthLimitExceeded { offset });
+    }
+    let mut builder = Builder::new(
+        Plain,
+        parser.max_depth - 1,
+        parser.strict,
+        parser.max_items - 1,
+    );
+    loop {
+        let token = parser.next_token()?;
+        if token == End && builder.stack.is_empty() {
+            return Ok(());
+        }
+        if let Some(value) = builder.push(token, parser.token_start..parser.pos)? {
+            buf.push(value);
+        }
+    }
+}
+
+impl<R: Read> Parser<R> {
+    fn expect_eof(&mut self) -> Result<(), Error> {
+        if self.trailing_whitespace {
+            return self.expect_whitespace();
+        }
+        match self.next_token() {
+            // The input ends at the read limit.
+            Ok(EOF) | Err(Error::ReadLimitExceeded { .. })
+                if self.token_start == self.read_limit =>
+            {
+                Ok(())
+            }
+            Ok(EOF) => Ok(()),
+            Err(e @ Error::Io { .. }) => Err(e),
+            _ => Err(Error::TrailingData {
+                offset: self.token_start,
+            }),
+        }
+    }
+
+    /// Like `expect_eof`, but skips ASCII whitespace before the end.
+    fn expect_whitespace(&mut self) -> Result<(), Error> {
+        match self.peeked.take() {
+            None => self.skip_pending()?,
+            Some((Err(Error::InvalidToken { byte, .. }), _)) if byte.is_ascii_whitespace() => {}
+            Some((Ok(EOF), _)) => return Ok(()),
+            Some((Err(e @ Error::Io { .. }), _)) => return Err(e),
+            Some((_, start)) => return Err(Error::TrailingData { offset: start }),
+        }
+        loop {
+            let offset = self.pos;
+            match self.counting().read_byte()? {
+                None => return Ok(()),
+                Some(byte) if byte.is_ascii_whitespace() => {}
+                Some(_) => return Err(Error::TrailingData { offset }),
+            }
+        }
+    }
+}
+
+/// Decodes the single bencoded value contained in `reader`, failing with
+/// `Error::TrailingData` if anything follows it.
+///
+/// ```
+/// use bencode_decode::from_reader;
+/// # #[cfg(feature = "std")] {
+/// use std::fs::File;
+///
+/// let f = File::open("./test/ubuntu-18.04.4-live-server-amd64.iso.torrent").unwrap();
+/// let torrent = from_reader(f).unwrap();
+/// # }
+/// ```
+pub fn from_reader<R: Read>(reader: R) -> Result<Value, Error> {
+    decode_strict(&mut Parser::new(reader))
+}
+
+/// Decodes the single bencoded value making up `input`. See `from_reader`.
+///
+/// ```
+/// use bencode_decode::{from_bytes, Value};
+///
+/// assert_eq!(from_bytes(b"i42e").unwrap(), Value::Integer(42));
+/// assert!(from_bytes(b"i42ei43e").is_err());
+/// ```
+pub fn from_bytes(input: &[u8]) -> Result<Value, Error> {
+    let mut parser = Parser::new(input);
+    // Nothing longer than the input can be read from it.
+    parser.set_max_prealloc(input.len());
+    decode_strict(&mut parser)
+}
+
+/// Decodes the bencoded value at the start of `input`, returning it along
+/// with the rest of `input` after it, for messages where bencode is followed
+/// by opaque data.
+///
+/// ```
+/// use bencode_decode::{decode_prefix, Value};
+///
+/// let (value, rest) = decode_prefix(b"d1:ai1ee\x00\x01").unwrap();
+/// assert_eq!(value["a"], 1);
+/// assert_eq!(rest, [0, 1]);
+/// ```
+pub fn decode_prefix(input: &[u8]) -> Result<(Value, &[u8]), Error> {
+    let mut parser = Parser::from_slice(input);
+    let value = decode(&mut parser)?;
+    Ok((value, &input[parser.pos..]))
+}
+
+/// Decodes a frame of the form `<u32 length><bencode>`, as used by several
+/// BitTorrent-adjacent protocols: a 4-byte big-endian length, followed by
+/// exactly that many bytes holding a single bencoded value. Nothing past the
+/// frame is read, so pass `&mut reader` to read consecutive frames. Fails
+/// with `Error::TrailingData` if the value ends before the frame does, with
+/// `Error::ReadLimitExceeded` if it runs past it, and with
+/// `Error::UnexpectedEof` if the input ends inside the frame. Offsets count
+/// from the start of the length prefix.
+///
+/// ```
+/// use bencode_decode::{decode_framed, Value};
+///
+/// let mut stream = &b"\x00\x00\x00\x03i1e\x00\x00\x00\x06d1:aiee"[..];
+/// assert_eq!(decode_framed(&mut stream).unwrap(), Value::Integer(1));
+/// assert!(decode_framed(&mut stream).is_err());
+/// ```
+pub fn decode_framed<R: Read>(mut reader: R) -> Result<Value, Error> {
+    let mut header = [0; 4];
+    let mut filled = 0;
+    while filled < header.len() {
+        match read_retrying(&mut reader, &mut header[filled..]) {
+            Ok(0) => return Err(Error::UnexpectedEof { offset: filled }),
+            Ok(read) => filled += read,
+            Err(e) => return Err(Error::io(e, filled)),
+        }
+    }
+    let len = usize::try_from(u32::from_be_bytes(header)).unwrap_or(usize::MAX);
+    let mut parser = Parser::new(reader);
+    parser.pos = header.len();
+    parser.read_limit = len.saturating_add(header.len());
+    let value = decode_strict(&mut parser)?;
+    if parser.pos < parser.read_limit {
+        return Err(Error::UnexpectedEof { offset: parser.pos });
+    }
+    Ok(value)
+}
+
+/// Decodes the back-to-back top-level values in `reader` one after another,
+/// until the reader is exhausted. The iterator ends after the first error.
+///
+/// ```
+/// use bencode_decode::{decode_all, Value};
+///
+/// let values: Result<Vec<_>, _> = decode_all("i1e4:spamle".as_bytes()).collect();
+/// assert_eq!(
+///     values.unwrap(),
+///     [
+///         Value::Integer(1),
+///         Value::ByteString(b"spam".to_vec()),
+///         Value::List(vec![])
+///     ]
+/// );
+/// ```
+pub fn decode_all<R: Read>(reader: R) -> impl Iterator<Item = Result<Value, Error>> {
+    let mut parser = Parser::new(reader);
+    let mut failed = false;
+    core::iter::from_fn(move || next_value(&mut parser, &mut failed))
+}
+
+/// Like `decode_all`, but reads from a configured `parser`, which can be
+/// used further once the iterator is dropped. With
+/// `Parser::set_trailing_whitespace`, ASCII whitespace between and after the
+/// values is skipped, as in newline-separated messages on stdin.
+///
+/// ```
+/// use bencode_decode::{decode_values, Parser, Value};
+///
+/// let mut parser = Parser::new("i1e\nle\n".as_bytes());
+/// parser.set_trailing_whitespace(true);
+/// let values: Result<Vec<_>, _> = decode_values(&mut parser).collect();
+/// assert_eq!(values.unwrap(), [Value::Integer(1), Value::List(vec![])]);
+/// ```
+pub fn decode_values<R: Read>(
+    parser: &mut Parser<R>,
+) -> impl Iterator<Item = Result<Value, Error>> + '_ {
+    let mut failed = false;
+    core::iter::from_fn(move || next_value(parser, &mut failed))
+}
+
+/// Decodes the next of the values read by `decode_all`, setting `failed`
+/// after an error.
+fn next_value<R: Read>(parser: &mut Parser<R>, failed: &mut bool) -> Option<Result<Value, Error>> {
+    if *failed {
+        return None;
+    }
+    let result = loop {
+        break match parser.next_token() {
+            Ok(EOF) => return None,
+            Ok(token) => build_from(parser, Plain, token),
+            // Whitespace is rejected as soon as the parser reads it.
+            Err(Error::InvalidToken { byte, .. })
+                if parser.trailing_whitespace && byte.is_ascii_whitespace() =>
+            {
+                continue
+            }
+            Err(e) => Err(e),
+        };
+    };
+    *failed = result.is_err();
+    Some(result)
+}
+
+/// The byte strings and integers that `Builder` is fed: `Value`s read by
+/// `Parser`, or `ValueRef`s borrowing from the input of `decode_slice`.
+pub(crate) trait Scalar: Sized {
+    type Key: AsRef<[u8]>;
+
+    /// Returns the contents if this is a byte string, and itself otherwise.
+    fn into_key(self) -> Result<Self::Key, Self>;
+}
+
+impl Scalar for Value {
+    type Key = Vec<u8>;
+
+    fn into_key(self) -> Result<Vec<u8>, Value> {
+        match self {
+            ByteString(key) => Ok(key),
+            value => Err(value),
+        }
+    }
+}
+
+/// A token as `Builder` takes it, with byte strings and integers held as `S`.
+pub(crate) enum Token<S> {
+    Scalar(S),
+    ListStart,
+    DictStart,
+    End,
+    Eof,
+}
+
+impl From<ParseResult> for Token<Value> {
+    fn from(token: ParseResult) -> Self {
+        match token {
+            ValueType(value) => Token::Scalar(value),
+            ListStart => Token::ListStart,
+            DictStart => Token::DictStart,
+            End => Token::End,
+            EOF => Token::Eof,
+        }
+    }
+}
+
+/// A tree that `Builder` assembles from tokens: how to make its scalars,
+/// lists and dictionaries, each from the input range `span`, and what to do
+/// about dictionary keys out of order. Implemented by every decoder that
+/// builds a whole tree, so they share one grammar and one set of limits.
+pub(crate) trait Tree<S: Scalar = Value> {
+    type Node;
+
+    /// Makes a byte string or integer, as emitted by the parser.
+    fn scalar(&mut self, value: S, span: Range<usize>) -> Self::Node;
+
+    fn list(&mut self, items: Vec<Self::Node>, span: Range<usize>) -> Self::Node;
+
+    /// Makes a dictionary from its entries in input order, each with the
+    /// offset of its key.
+    fn dict(&mut self, entries: Vec<(usize, S::Key, Self::Node)>, span: Range<usize>)
+        -> Self::Node;
+
+    /// Called with `Error::UnsortedKeys` or `Error::DuplicateKey` for a key
+    /// that doesn't sort after the one before it. Fails in strict mode and
+    /// accepts the key otherwise.
+    fn misordered_key(&mut self, error: Error, strict: bool) -> Result<(), Error> {
+        if strict {
+            Err(error)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// The `Tree` of plain `Value`s. Of duplicate keys, the last entry wins.
+pub(crate) struct Plain;
+
+impl Tree for Plain {
+    type Node = Value;
+
+    fn scalar(&mut self, value: Value, _: Range<usize>) -> Value {
+        value
+    }
+
+    fn list(&mut self, items: Vec<Value>, _: Range<usize>) -> Value {
+        List(items)
+    }
+
+    fn dict(&mut self, entries: Vec<(usize, Vec<u8>, Value)>, _: Range<usize>) -> Value {
+        Dictionary(
+            entries
+                .into_iter()
+                .map(|(_, key, value)| (Key::from(key), value))
+                .collect(),
+        )
+    }
+}
+
+/// Counts the values read against `Parser::set_max_items`, the same way for
+/// every decoder: each byte string, integer and container counts, including
+/// dictionary keys.
+pub(crate) struct Items {
+    count: usize,
+    max: usize,
+}
+
+impl Items {
+    pub(crate) fn new(max: usize) -> Self {
+        Self { count: 0, max }
+    }
+
+    /// Counts the value starting at `offset`.
+    pub(crate) fn count(&mut self, offset: usize) -> Result<(), Error> {
+        self.count += 1;
+        if self.count > self.max {
+            return Err(Error::ItemLimitExceeded { offset });
+        }
+        Ok(())
+    }
+}
+
+/// An open container: whether it is a list, its starting offset, the keys
+/// read so far along with their offsets if it is a dictionary, and the
+/// other values.
+struct Frame<N, K> {
+    list: bool,
+    start: usize,
+    keys: Vec<(usize, K)>,
+    items: Vec<N>,
+}
+
+/// Assembles a stream of tokens into a `Tree`. Containers that are still
+/// open are kept on an explicit stack rather than the call stack, so nesting
+/// depth is bounded by the heap.
+pub(crate) struct Builder<T: Tree<S>, S: Scalar = Value> {
+    tree: T,
+    stack: Vec<Frame<T::Node, S::Key>>,
+    max_depth: usize,
+    strict: bool,
+    items: Items,
+}
+
+impl<T: Tree<S>, S: Scalar> Builder<T, S> {
+    pub(crate) fn new(tree: T, max_depth: usize, strict: bool, max_items: usize) -> Self {
+        Self {
+            tree,
+            stack: vec![],
+            max_depth,
+            strict,
+            items: Items::new(max_items),
+        }
+    }
+
+    /// Whether the next value is a dictionary key.
+    fn at_key(&self) -> bool {
+        matches!(self.stack.last(), Some(frame) if !frame.list && frame.keys.len() == frame.items.len())
+    }
+
+    /// Feeds the next token, read from the input range `span`, returning the
+    /// top-level node once it is complete.
+    pub(crate) fn push(
+        &mut self,
+        token: impl Into<Token<S>>,
+        span: Range<usize>,
+    ) -> Result<Option<T::Node>, Error> {
+        let token = token.into();
+        let offset = span.start;
+        if !matches!(token, Token::End | Token::Eof) {
+            self.items.count(offset)?;
+        }
+        let node = match token {
+            Token::Scalar(scalar) if self.at_key() => match scalar.into_key() {
+                Ok(key) => {
+                    self.key(key, offset)?;
+                    return Ok(None);
                 }
-                Some(Dictionary(map))
+                Err(_) => return Err(Error::NonStringKey { offset }),
+            },
+            Token::Eof => return Err(Error::UnexpectedEof { offset }),
+            Token::End => match self.stack.pop() {
+                Some(frame) if frame.list => self.tree.list(frame.items, frame.start..span.end),
+                Some(frame) if frame.keys.len() > frame.items.len() => {
+                    return Err(Error::OddDictLength { offset })
+                }
+                Some(frame) => {
+                    let entries = frame
+                        .keys
+                        .into_iter()
+                        .zip(frame.items)
+                        .map(|((offset, key), value)| (offset, key, value))
+                        .collect();
+                    self.tree.dict(entries, frame.start..span.end)
+                }
+                None => return Err(Error::UnexpectedEnd { offset }),
+            },
+            _ if self.at_key() => return Err(Error::NonStringKey { offset }),
+            Token::Scalar(scalar) => self.tree.scalar(scalar, span),
+            Token::ListStart | Token::DictStart => {
+                if self.stack.len() >= self.max_depth {
+                    return Err(Error::DepthLimitExceeded { offset });
+                }
+                self.stack.push(Frame {
+                    list: matches!(token, Token::ListStart),
+                    start: offset,
+                    keys: vec![],
+                    items: vec![],
+                });
+                return Ok(None);
+            }
+        };
+        match self.stack.last_mut() {
+            Some(frame) => {
+                frame.items.push(node);
+                Ok(None)
             }
+            None => Ok(Some(node)),
+        }
+    }
+
+    /// Adds a key at `offset` to the innermost dictionary, checking that it
+    /// sorts after the previous one.
+    fn key(&mut self, key: S::Key, offset: usize) -> Result<(), Error> {
+        let keys = match self.stack.last_mut() {
+            Some(frame) => &mut frame.keys,
+            None => unreachable!("keys only occur in dictionaries"),
+        };
+        let error = match keys.last().map(|(_, last)| key.as_ref().cmp(last.as_ref())) {
+            Some(Ordering::Less) => Some(Error::UnsortedKeys { offset }),
+            Some(Ordering::Equal) => Some(Error::DuplicateKey { offset }),
+            _ => None,
+        };
+        keys.push((offset, key));
+        match error {
+            Some(error) => self.tree.misordered_key(error, self.strict),
+            None => Ok(()),
         }
-        Some(End) => unreachable!(),
-        Some(EOF) => unreachable!(),
-        None => None,
     }
 }
 
-fn parse<R: Read>(reader: &mut R) -> Result<ParseResult, Box<dyn std::error::Error>> {
-    let mut buf = [0; 1];
-    let mut vec = vec![];
+/// Reads the next value from `parser` into `tree`, like `decode` does into a
+/// `Value`.
+pub(crate) fn build<R: Read, T: Tree>(parser: &mut Parser<R>, tree: T) -> Result<T::Node, Error> {
+    let token = parser.next_token()?;
+    build_from(parser, tree, token)
+}
+
+/// Like `build`, for the value starting with `token`, which has already been
+/// read from `parser`.
+fn build_from<R: Read, T: Tree>(
+    parser: &mut Parser<R>,
+    tree: T,
+    mut token: ParseResult,
+) -> Result<T::Node, Error> {
+    let mut builder = Builder::new(tree, parser.max_depth, parser.strict, parser.max_items);
     loop {
-        let read_bytes = reader.read(&mut buf)?;
-        if read_bytes == 0 {
-            return Ok(EOF);
-        }
-        match buf[0] {
-            n @ b'0'..=b'9' => vec.push(n),
-            b':' => {
-                let size = String::from_utf8(vec)?.parse()?;
-                let mut str = vec![0; size];
-                reader.read_exact(&mut str)?;
-                return Ok(ValueType(ByteString(str)));
-            }
-            b'i' => {
-                let mut b = [0; 1];
-                reader.read_exact(&mut b)?;
-                let mut vec = vec![];
-                while b[0] != b'e' {
-                    vec.push(b[0]);
-                    reader.read_exact(&mut b)?;
-                }
-                let int: i64 = String::from_utf8(vec)?.parse()?;
-                return Ok(ValueType(Integer(int)));
+        if let Some(node) = builder.push(token, parser.token_start..parser.pos)? {
+            return Ok(node);
+        }
+        token = parser.next_token()?;
+    }
+}
+
+/// Upper bound for the capacity reserved up front for a byte string, unless
+/// configured otherwise via `Parser::set_max_prealloc`. Longer strings grow
+/// as their data actually arrives, so a bogus length header can't make us
+/// allocate more memory than the input provides.
+const PREALLOC_LIMIT: usize = 64 * 1024;
+
+/// The token for `event`, which isn't a byte string; their contents are read
+/// by whoever drives the tokenizer.
+fn token(event: Event) -> ParseResult {
+    match event {
+        Event::Integer(int) => ValueType(Integer(int)),
+        Event::BigInteger(digits) => ValueType(BigInteger(digits)),
+        Event::RawInteger(digits) => ValueType(RawInteger(digits)),
+        Event::ListStart => ListStart,
+        Event::DictStart => DictStart,
+        Event::End => End,
+        Event::Eof => EOF,
+        Event::ByteString(_) | Event::DictKey(_) => {
+            unreachable!("byte strings are read by the caller")
+        }
+    }
+}
+
+/// Reads the `size` bytes of contents of a byte string.
+fn read_payload<R: Read>(reader: &mut Counting<R>, size: usize) -> Result<Vec<u8>, Error> {
+    let mut str = vec![0; size.min(reader.max_prealloc)];
+    let mut filled = 0;
+    while filled < size {
+        if filled == str.len() {
+            str.resize(size.min((filled * 2).max(PREALLOC_LIMIT)), 0);
+        }
+        match reader
+            .read(&mut str[filled..])
+            .map_err(|e| Error::io(e, reader.offset()))?
+        {
+            0 => {
+                return Err(Error::TruncatedByteString {
+                    declared: size,
+                    available: filled,
+                    offset: reader.offset(),
+                })
             }
-            b'e' => return Ok(End),
-            b'l' => return Ok(ListStart),
-            b'd' => return Ok(DictStart),
-            _ => unreachable!("unexpected token"),
+            read => filled += read,
+        }
+    }
+    Ok(str)
+}
+
+/// Converts the declared length `len` of a byte string starting at `offset`
+/// into a `usize`, or whatever integer type `max_len` is. Lengths above
+/// `max_len` fail with `Error::LengthLimitExceeded`, and so do those that
+/// don't fit the type, which on 32-bit targets are well-formed lengths.
+fn check_length<T: TryFrom<u64> + PartialOrd>(
+    len: u64,
+    max_len: T,
+    offset: usize,
+) -> Result<T, Error> {
+    match T::try_from(len) {
+        Ok(size) if size <= max_len => Ok(size),
+        _ => Err(Error::LengthLimitExceeded { offset }),
+    }
+}
+
+/// An integer as returned by `parse_integer`.
+enum ParsedInteger<'a> {
+    Small(i64),
+    /// Too large for an `i64`, see `Value::BigInteger`.
+    Big(&'a str),
+}
+
+impl From<ParsedInteger<'_>> for Event {
+    fn from(int: ParsedInteger) -> Self {
+        match int {
+            ParsedInteger::Small(int) => Event::Integer(int),
+            ParsedInteger::Big(digits) => Event::BigInteger(digits.into()),
         }
     }
 }
 
+/// Rewrites the contents of an integer that is well-formed except for
+/// leading zeros or a negative zero into their canonical form, e.g. `-007`
+/// into `-7`.
+fn canonical_integer(digits: &[u8]) -> Option<Vec<u8>> {
+    let magnitude = digits.strip_prefix(b"-").unwrap_or(digits);
+    if magnitude.is_empty() || !magnitude.iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+    let negative = magnitude.len() < digits.len();
+    let zeros = magnitude.iter().take_while(|&&digit| digit == b'0').count();
+    let magnitude = &magnitude[zeros.min(magnitude.len() - 1)..];
+    let mut canonical = vec![];
+    if negative && magnitude != b"0" {
+        canonical.push(b'-');
+    }
+    canonical.extend_from_slice(magnitude);
+    Some(canonical)
+}
+
+/// The longest an integer that fits an `i64` can be written, as in
+/// `-9223372036854775808`.
+const I64_LEN: usize = 20;
+
+/// Whether `byte` may occur at `index` among the digits of an integer. The
+/// tokenizer checks this as it goes, so an integer that can't be valid fails
+/// right away instead of being read up to its `e`.
+fn integer_byte(byte: u8, index: usize) -> bool {
+    byte.is_ascii_digit() || (byte == b'-' && index == 0)
+}
+
+/// Parses the contents of an `i...e` integer starting at `offset`: an
+/// optional `-` followed by at least one decimal digit. As mandated by the
+/// spec, leading zeros and negative zero are rejected, and so are a `+`
+/// sign, whitespace, and empty or sign-only contents like in `ie` and `i-e`.
+fn parse_integer(digits: &[u8], offset: usize) -> Result<ParsedInteger<'_>, Error> {
+    let magnitude = digits.strip_prefix(b"-").unwrap_or(digits);
+    let canonical = match magnitude {
+        [] => false,
+        [b'0'] => magnitude.len() == digits.len(),
+        [b'0', ..] => false,
+        _ => magnitude.iter().all(u8::is_ascii_digit),
+    };
+    if !canonical {
+        return Err(Error::InvalidInteger { offset });
+    }
+    // Accumulated as a negative number, which also covers `i64::MIN`.
+    let negative = magnitude.iter().try_fold(0i64, |int, digit| {
+        int.checked_mul(10)?.checked_sub(i64::from(digit - b'0'))
+    });
+    let int = match negative {
+        Some(int) if magnitude.len() < digits.len() => Some(int),
+        Some(int) => int.checked_neg(),
+        None => None,
+    };
+    Ok(match int {
+        Some(int) => ParsedInteger::Small(int),
+        // Only ASCII is left at this point.
+        None => ParsedInteger::Big(
+            core::str::from_utf8(digits).map_err(|_| Error::InvalidInteger { offset })?,
+        ),
+    })
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
+    #[cfg(feature = "std")]
     use std::fs::File;
+
     #[test]
+    #[cfg(feature = "std")]
     fn torrent() {
         let f = File::open("./test/ubuntu-18.04.4-live-server-amd64.iso.torrent").unwrap();
         let mut parser = Parser::new(f);
-        let res = decode(&mut parser, None).unwrap();
-        if let Value::Dictionary(x) = res {
-            if let Value::Dictionary(y) = x.get(&b"info".to_vec()).unwrap() {
-                let path = y.get(&b"name".to_vec()).unwrap();
-                let length = y.get(&b"length".to_vec()).unwrap();
-                if let (Value::ByteString(path), Value::Integer(length)) = (path, length) {
-                    let path = String::from_utf8_lossy(path);
-                    println!("{} -> {} bytes", path, length);
-                    assert_eq!(path, "ubuntu-18.04.4-live-server-amd64.iso");
-                    assert_eq!(*length, 912_261_120);
+        let res = decode(&mut parser).unwrap();
+        assert_eq!(res["info"]["name"], "ubuntu-18.04.4-live-server-amd64.iso");
+        assert_eq!(res["info"]["length"], 912_261_120);
+    }
+
+    #[test]
+    fn spec() {
+        let res = from_bytes(
+            b"d9:publisher3:bob17:publisher-webpage15:www.example.com18:publisher.location4:homee",
+        )
+        .unwrap();
+        assert_eq!(
+            res,
+            bencode!({
+                "publisher" => "bob",
+                "publisher-webpage" => "www.example.com",
+                "publisher.location" => "home",
+            })
+        );
+    }
+
+    #[test]
+    fn empty() {
+        let empty_map = || Dictionary(BTreeMap::new());
+        assert_eq!(decode_str("0:").unwrap(), ByteString(vec![]));
+        assert_eq!(decode_str("le").unwrap(), List(vec![]));
+        assert_eq!(decode_str("de").unwrap(), empty_map());
+        assert_eq!(
+            decode_str("d0:0:1:ade1:ble1:c0:e").unwrap(),
+            Dictionary(
+                IntoIterator::into_iter([
+                    (Key::default(), ByteString(vec![])),
+                    ("a".into(), empty_map()),
+                    ("b".into(), List(vec![])),
+                    ("c".into(), ByteString(vec![])),
+                ])
+                .collect()
+            )
+        );
+        assert_eq!(
+            decode_str("l0:lede0:e").unwrap(),
+            List(vec![
+                ByteString(vec![]),
+                List(vec![]),
+                empty_map(),
+                ByteString(vec![])
+            ])
+        );
+        for input in ["0:", "le", "de", "d0:0:e", "l0:lede0:e"] {
+            assert_eq!(
+                crate::decode_slice(input.as_bytes()).unwrap().to_value(),
+                decode_str(input).unwrap()
+            );
+        }
+
+        let mut parser = Parser::new("0:".as_bytes());
+        parser.set_strict(true);
+        assert_eq!(decode_strict(&mut parser).unwrap(), ByteString(vec![]));
+        let mut parser = Parser::new("d0:i1e1:ai2ee".as_bytes());
+        parser.set_strict(true);
+        assert_eq!(decode(&mut parser).unwrap()[""], Integer(1));
+    }
+
+    #[test]
+    fn deeply_nested() {
+        let depth = 300_000;
+        let input = "l".repeat(depth) + &"e".repeat(depth);
+        let mut parser = Parser::with_max_depth(input.as_bytes(), usize::MAX);
+        let mut value = decode(&mut parser).unwrap();
+        // Unwrap the levels one at a time; dropping the whole tree at once
+        // would recurse just as deeply as a recursive decoder.
+        let mut levels = 1;
+        while let Value::List(mut list) = value {
+            match list.pop() {
+                Some(inner) => {
+                    value = inner;
+                    levels += 1;
                 }
+                None => break,
             }
         }
+        assert_eq!(levels, depth);
     }
 
     #[test]
-    fn spec() {
-        let input = std::io::Cursor::new(
-            "d9:publisher3:bob17:publisher-webpage15:www.example.com18:publisher.location4:homee"
-                .to_string()
-                .into_bytes(),
+    fn max_depth() {
+        let input = "l".repeat(3) + &"e".repeat(3);
+        let mut parser = Parser::with_max_depth(input.as_bytes(), 3);
+        assert!(decode(&mut parser).is_ok());
+        let mut parser = Parser::with_max_depth(input.as_bytes(), 2);
+        assert!(matches!(
+            decode(&mut parser),
+            Err(Error::DepthLimitExceeded { .. })
+        ));
+
+        let input = "l".repeat(DEFAULT_MAX_DEPTH + 1);
+        assert!(matches!(
+            decode_str(&input),
+            Err(Error::DepthLimitExceeded { .. })
+        ));
+    }
+
+    #[test]
+    fn max_byte_string_len() {
+        let mut parser = Parser::new("5:hello".as_bytes());
+        parser.set_max_byte_string_len(5);
+        assert!(decode(&mut parser).is_ok());
+        let mut parser = Parser::new("6:hello!".as_bytes());
+        parser.set_max_byte_string_len(5);
+        assert!(matches!(
+            decode(&mut parser),
+            Err(Error::LengthLimitExceeded { .. })
+        ));
+
+        // Without a limit, a huge declared length must not be allocated
+        // up front.
+        assert!(matches!(
+            decode_str("999999999999:short"),
+            Err(Error::TruncatedByteString { available: 5, .. })
+        ));
+    }
+
+    #[test]
+    fn read_limit() {
+        // A value may end right at the limit, even if more input follows.
+        let mut parser = Parser::with_read_limit("li1e3:abce".as_bytes(), 10);
+        assert!(decode_strict(&mut parser).is_ok());
+        let mut parser = Parser::with_read_limit("li1eei2e".as_bytes(), 5);
+        assert!(decode_strict(&mut parser).is_ok());
+
+        for (input, offset) in [
+            ("li1e3:abce", 4),
+            ("li1ei2ee", 4),
+            ("l3:abcdefe", 1),
+            ("i12345e", 0),
+            ("i1e12345", 3),
+        ] {
+            let mut parser = Parser::with_read_limit(input.as_bytes(), 5);
+            assert!(
+                matches!(
+                    decode(&mut parser).and_then(|_| decode(&mut parser)),
+                    Err(Error::ReadLimitExceeded { offset: o }) if o == offset
+                ),
+                "{}",
+                input
+            );
+        }
+
+        // Nothing beyond the limit is consumed from the reader, even when
+        // reading ahead.
+        let input = b"li1ei2ei3eei4e";
+        let mut reader = &input[..];
+        let mut parser = Parser::with_read_limit(&mut reader, 11);
+        parser.set_buffer_capacity(64);
+        assert!(decode(&mut parser).is_ok());
+        assert_eq!(parser.buffer(), b"");
+        drop(parser);
+        assert_eq!(reader, b"i4e");
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn dyn_reader() {
+        let raw = std::fs::read("./test/ubuntu-18.04.4-live-server-amd64.iso.torrent").unwrap();
+        let expected = decode(&mut Parser::new(raw.as_slice())).unwrap();
+        for from_file in [true, false] {
+            let reader: Box<dyn Read> = if from_file {
+                Box::new(File::open("./test/ubuntu-18.04.4-live-server-amd64.iso.torrent").unwrap())
+            } else {
+                Box::new(raw.as_slice())
+            };
+            let mut parser = Parser::new_boxed(reader);
+            assert_eq!(decode(&mut parser).unwrap(), expected);
+        }
+
+        let mut slice = "li1ee".as_bytes();
+        let reader: &mut dyn Read = &mut slice;
+        let mut parser = Parser::new(reader);
+        assert_eq!(decode(&mut parser).unwrap(), List(vec![Integer(1)]));
+    }
+
+    #[test]
+    fn decode_framed() {
+        let frame = |payload: &[u8]| {
+            let mut frame = (payload.len() as u32).to_be_bytes().to_vec();
+            frame.extend_from_slice(payload);
+            frame
+        };
+        let raw = std::fs::read("./test/ubuntu-18.04.4-live-server-amd64.iso.torrent").unwrap();
+        let mut stream = frame(&raw);
+        stream.extend(frame(b"le"));
+        let mut reader = stream.as_slice();
+        assert_eq!(
+            super::decode_framed(&mut reader).unwrap(),
+            from_bytes(&raw).unwrap()
         );
-        let mut parser = Parser::new(input);
-        let res = decode(&mut parser, None).unwrap();
-        let mut map = BTreeMap::new();
-        vec![
-            ("publisher", "bob"),
-            ("publisher-webpage", "www.example.com"),
-            ("publisher.location", "home"),
-        ]
-        .into_iter()
-        .for_each(|(k, v)| {
-            map.insert(
-                k.as_bytes().to_vec(),
-                Value::ByteString(v.as_bytes().to_vec()),
+        assert_eq!(super::decode_framed(&mut reader).unwrap(), List(vec![]));
+        assert!(matches!(
+            super::decode_framed(&mut reader),
+            Err(Error::UnexpectedEof { offset: 0 })
+        ));
+
+        let decode = |input: &[u8]| super::decode_framed(input);
+        assert!(matches!(
+            decode(b"\x00\x00"),
+            Err(Error::UnexpectedEof { offset: 2 })
+        ));
+        assert!(matches!(
+            decode(b"\x00\x00\x00\x04i1e"),
+            Err(Error::UnexpectedEof { offset: 7 })
+        ));
+        assert!(matches!(
+            decode(b"\x00\x00\x00\x04i1ei2e"),
+            Err(Error::TrailingData { offset: 7 })
+        ));
+        assert!(matches!(
+            decode(b"\x00\x00\x00\x03li1ee"),
+            Err(Error::ReadLimitExceeded { offset: 5 })
+        ));
+        assert!(matches!(
+            decode(b"\x00\x00\x00\x00i1e"),
+            Err(Error::ReadLimitExceeded { offset: 4 })
+        ));
+        assert!(matches!(
+            decode(b"\xff\xff\xff\xff4:spam"),
+            Err(Error::UnexpectedEof { offset: 10 })
+        ));
+    }
+
+    #[test]
+    fn decode_prefix() {
+        let (value, rest) = super::decode_prefix(b"li1e3:abcee").unwrap();
+        assert_eq!(value, bencode!([1, "abc"]));
+        assert_eq!(rest, b"e");
+        let (value, rest) = super::decode_prefix(b"0:").unwrap();
+        assert_eq!(value, Value::ByteString(vec![]));
+        assert!(rest.is_empty());
+
+        assert!(matches!(
+            super::decode_prefix(b""),
+            Err(Error::UnexpectedEof { offset: 0 })
+        ));
+        assert!(matches!(
+            super::decode_prefix(b"li1e"),
+            Err(Error::UnexpectedEof { offset: 4 })
+        ));
+    }
+
+    #[test]
+    fn decode_into() {
+        let raw = std::fs::read("./test/ubuntu-18.04.4-live-server-amd64.iso.torrent").unwrap();
+        let torrent = from_bytes(&raw).unwrap();
+        let mut input = b"l".to_vec();
+        input.extend_from_slice(&raw);
+        input.extend_from_slice(b"i1eli2eee");
+        let mut parser = Parser::new(input.as_slice());
+        let mut buf = vec![Integer(0)];
+        super::decode_into(&mut parser, &mut buf).unwrap();
+        assert_eq!(buf, [torrent, Integer(1), bencode!([2])]);
+        let allocation = buf.as_ptr();
+        parser.reset("le".as_bytes());
+        super::decode_into(&mut parser, &mut buf).unwrap();
+        assert!(buf.is_empty());
+        assert_eq!(buf.as_ptr(), allocation);
+
+        let decode_into = |input: &str, configure: fn(&mut Parser<&[u8]>)| {
+            let mut parser = Parser::new(input.as_bytes());
+            configure(&mut parser);
+            super::decode_into(&mut parser, &mut vec![])
+        };
+        assert!(matches!(
+            decode_into("d1:ai1ee", |_| {}),
+            Err(Error::TypeMismatch {
+                expected: "list",
+                found: "dictionary"
+            })
+        ));
+        assert!(matches!(
+            decode_into("i1e", |_| {}),
+            Err(Error::TypeMismatch {
+                expected: "list",
+                found: "integer"
+            })
+        ));
+        assert!(matches!(
+            decode_into("e", |_| {}),
+            Err(Error::UnexpectedEnd { offset: 0 })
+        ));
+        assert!(matches!(
+            decode_into("li1e", |_| {}),
+            Err(Error::UnexpectedEof { offset: 4 })
+        ));
+        assert!(matches!(
+            decode_into("le", |p| p.set_max_depth(0)),
+            Err(Error::DepthLimitExceeded { offset: 0 })
+        ));
+        assert!(matches!(
+            decode_into("li1elee", |p| p.set_max_depth(1)),
+            Err(Error::DepthLimitExceeded { offset: 4 })
+        ));
+        assert!(decode_into("li1elee", |p| p.set_max_depth(2)).is_ok());
+        assert!(matches!(
+            decode_into("li1ei2ee", |p| p.set_max_items(2)),
+            Err(Error::ItemLimitExceeded { offset: 4 })
+        ));
+        assert!(matches!(
+            decode_into("ld1:bi1e1:ai2eee", |p| p.set_strict(true)),
+            Err(Error::UnsortedKeys { offset: 8 })
+        ));
+    }
+
+    #[test]
+    fn max_prealloc() {
+        let pieces = vec![7; 3 * PREALLOC_LIMIT + 1];
+        let mut input = format!("d6:pieces{}:", pieces.len()).into_bytes();
+        input.extend_from_slice(&pieces);
+        input.push(b'e');
+        let value = from_bytes(&input).unwrap();
+        assert_eq!(value["pieces"], pieces.as_slice());
+        let bytes = value.into_dict().unwrap().remove(&b"pieces"[..]).unwrap();
+        assert_eq!(bytes.into_bytes().unwrap().capacity(), pieces.len());
+
+        for max_prealloc in [0, 1, PREALLOC_LIMIT, usize::MAX] {
+            let mut parser = Parser::new(input.as_slice());
+            parser.set_max_prealloc(max_prealloc);
+            assert_eq!(
+                decode_strict(&mut parser).unwrap()["pieces"],
+                pieces.as_slice()
+            );
+        }
+
+        // A bogus length still allocates no more than the input holds.
+        assert!(matches!(
+            from_bytes(b"999999999:ab"),
+            Err(Error::TruncatedByteString {
+                declared: 999_999_999,
+                available: 2,
+                offset: 12
+            })
+        ));
+    }
+
+    #[test]
+    fn max_items() {
+        let input = "l".to_string() + &"i1e".repeat(4) + "e";
+        let mut parser = Parser::new(input.as_bytes());
+        parser.set_max_items(5);
+        assert!(decode(&mut parser).is_ok());
+        let mut parser = Parser::new(input.as_bytes());
+        parser.set_max_items(4);
+        assert!(matches!(
+            decode(&mut parser),
+            Err(Error::ItemLimitExceeded { offset: 10 })
+        ));
+
+        // Every decoder counts the same way, keys included.
+        struct Ignore;
+        impl Visitor for Ignore {}
+        for (input, offset) in [(input.as_str(), 10), ("d1:ai1e1:bi2ee", 10)] {
+            let parser = || {
+                let mut parser = Parser::new(input.as_bytes());
+                parser.set_max_items(4);
+                parser
+            };
+            let mut results = vec![];
+            results.extend([
+                decode(&mut parser()).map(|_| ()),
+                decode_ordered(&mut parser()).map(|_| ()),
+                decode_interned(&mut parser()).map(|_| ()),
+                decode_spanned(&mut parser()).map(|_| ()),
+                decode_with_report(&mut parser()).map(|_| ()),
+                decode_visit(&mut parser(), &mut Ignore),
+            ]);
+            #[cfg(feature = "std")]
+            results.push(decode_hashed(&mut parser()).map(|_| ()));
+            for (decoder, result) in results.iter().enumerate() {
+                assert!(
+                    matches!(result, Err(Error::ItemLimitExceeded { offset: o }) if *o == offset),
+                    "{} with decoder {}: {:?}",
+                    input,
+                    decoder,
+                    result
+                );
+            }
+        }
+
+        // The limit applies to each top-level value separately.
+        let mut parser = Parser::new("lelele".as_bytes());
+        parser.set_max_items(1);
+        for _ in 0..3 {
+            assert_eq!(decode(&mut parser).unwrap(), Value::List(vec![]));
+        }
+    }
+
+    #[test]
+    fn integers() {
+        for (input, expected) in [
+            ("i0e", 0),
+            ("i-42e", -42),
+            ("i42e", 42),
+            ("i10e", 10),
+            ("i9223372036854775807e", i64::MAX),
+        ] {
+            assert_eq!(decode_str(input).unwrap(), Value::Integer(expected));
+        }
+        for input in ["i9223372036854775808e", "i-99999999999999999999e"] {
+            let value = decode_str(input).unwrap();
+            assert_eq!(
+                value,
+                Value::BigInteger(input[1..input.len() - 1].to_string())
+            );
+            assert_eq!(value.to_bytes(), input.as_bytes());
+        }
+        assert_eq!(
+            decode_str("i-9223372036854775808e").unwrap(),
+            Value::Integer(i64::MIN)
+        );
+        for input in ["i03e", "i-0e", "i-03e", "ie", "i-e", "i+5e", "i 5e"] {
+            assert!(
+                matches!(decode_str(input), Err(Error::InvalidInteger { .. })),
+                "{}",
+                input
+            );
+        }
+
+        // Every decoder rejects the same malformed integers, pointing at the `i`.
+        for input in [
+            "ie", "i-e", "i+5e", "i--5e", "i5-e", "i 5e", "i5 e", "i-+5e",
+        ] {
+            let nested = format!("l{}e", input);
+            assert!(
+                matches!(
+                    decode_str(&nested),
+                    Err(Error::InvalidInteger { offset: 1 })
+                ),
+                "{}",
+                input
             );
+            assert!(
+                matches!(
+                    decode_slice(nested.as_bytes()),
+                    Err(Error::InvalidInteger { offset: 1 })
+                ),
+                "{}",
+                input
+            );
+            let mut parser = Parser::new(input.as_bytes());
+            assert!(
+                matches!(
+                    parser.next_event(),
+                    Err(Error::InvalidInteger { offset: 0 })
+                ),
+                "{}",
+                input
+            );
+        }
+
+        // Without reading on to an `e` that never comes.
+        #[cfg(feature = "std")]
+        {
+            let endless = std::io::Read::chain(&b"li1ei-1"[..], std::io::repeat(b'-'));
+            assert!(matches!(
+                decode(&mut Parser::new(endless)),
+                Err(Error::InvalidInteger { offset: 4 })
+            ));
+        }
+
+        let mut parser = Parser::new("li1ei9223372036854775808ee".as_bytes());
+        parser.set_big_integers(false);
+        assert!(matches!(
+            decode(&mut parser),
+            Err(Error::IntegerOverflow { digits, offset: 4 }) if digits == "9223372036854775808"
+        ));
+        let mut parser = Parser::new("i-9223372036854775809e".as_bytes());
+        parser.set_big_integers(false);
+        assert!(matches!(
+            parser.next_event(),
+            Err(Error::IntegerOverflow { offset: 0, .. })
+        ));
+        let mut parser = Parser::new("i-9223372036854775808e".as_bytes());
+        parser.set_big_integers(false);
+        assert_eq!(decode(&mut parser).unwrap(), Integer(i64::MIN));
+    }
+
+    #[test]
+    fn negative_integers() {
+        let tokenize = |input: &str| {
+            let mut tokenizer = Tokenizer::new();
+            let mut tokens = vec![];
+            for &byte in input.as_bytes() {
+                tokens.extend(tokenizer.push(byte)?);
+            }
+            tokenizer.finish()?;
+            Ok::<_, Error>(tokens)
+        };
+        for (input, expected) in [
+            ("i-42e", -42),
+            ("i0e", 0),
+            ("i-1e", -1),
+            ("i-10e", -10),
+            ("i-9223372036854775808e", i64::MIN),
+        ] {
+            assert_eq!(decode_str(input).unwrap(), Integer(expected));
+            assert_eq!(
+                decode_slice(input.as_bytes()).unwrap(),
+                ValueRef::Integer(expected)
+            );
+            assert_eq!(
+                Parser::new(input.as_bytes()).next_event().unwrap(),
+                Event::Integer(expected)
+            );
+            assert_eq!(tokenize(input).unwrap(), [ValueType(Integer(expected))]);
+            assert_eq!(Integer(expected).to_bytes(), input.as_bytes());
+        }
+
+        // Negative zero, with or without leading zeros, is never canonical.
+        for input in ["i-0e", "i-00e", "i-01e"] {
+            assert!(
+                matches!(decode_str(input), Err(Error::InvalidInteger { offset: 0 })),
+                "{}",
+                input
+            );
+            assert!(
+                matches!(
+                    decode_strict(&mut Parser::new(input.as_bytes())),
+                    Err(Error::InvalidInteger { offset: 0 })
+                ),
+                "{}",
+                input
+            );
+            assert!(
+                matches!(
+                    decode_slice(input.as_bytes()),
+                    Err(Error::InvalidInteger { offset: 0 })
+                ),
+                "{}",
+                input
+            );
+            assert!(
+                matches!(tokenize(input), Err(Error::InvalidInteger { offset: 0 })),
+                "{}",
+                input
+            );
+        }
+
+        // A sign is no part of a byte string length.
+        assert!(matches!(
+            decode_str("-3:abc"),
+            Err(Error::InvalidToken {
+                byte: b'-',
+                offset: 0
+            })
+        ));
+        assert!(matches!(
+            decode_str("l3-:abce"),
+            Err(Error::InvalidLength { offset: 1 })
+        ));
+        assert!(matches!(
+            decode_slice(b"l3-:abce"),
+            Err(Error::InvalidLength { offset: 1 })
+        ));
+    }
+
+    #[test]
+    fn raw_integers() {
+        let input = "d1:ai007e1:bi-0e1:ci-00e1:di00099999999999999999999ee";
+        let mut parser = Parser::new(input.as_bytes());
+        parser.set_raw_integers(true);
+        parser.set_big_integers(false);
+        let value = decode(&mut parser).unwrap();
+        assert_eq!(value["a"], RawInteger("007".into()));
+        assert_eq!(value["b"], RawInteger("-0".into()));
+        assert_eq!(value["c"], RawInteger("-00".into()));
+        // They go by their value otherwise.
+        assert_eq!(value["a"].as_integer(), Some(7));
+        assert_eq!(value["a"].as_u64(), Some(7));
+        assert_eq!(value["a"], 7);
+        assert_eq!(value["c"], 0);
+        assert!(value["a"].semantic_eq(&Integer(7)));
+        assert_eq!(value["d"].as_integer(), None);
+        assert_eq!(value["d"].as_u64(), None);
+        assert!(value["d"].semantic_eq(&BigInteger("99999999999999999999".into())));
+        assert_eq!(value.to_bytes(), input.as_bytes());
+        assert_eq!(
+            value.canonicalize().unwrap(),
+            b"d1:ai7e1:bi0e1:ci0e1:di99999999999999999999ee"
+        );
+
+        // The events agree, and canonical integers are unaffected.
+        let mut parser = Parser::new("i-07ei-7ei9223372036854775808e".as_bytes());
+        parser.set_raw_integers(true);
+        parser.set_big_integers(false);
+        assert_eq!(
+            parser.next_event().unwrap(),
+            Event::RawInteger("-07".into())
+        );
+        assert_eq!(parser.next_event().unwrap(), Event::Integer(-7));
+        assert!(matches!(
+            parser.next_event(),
+            Err(Error::IntegerOverflow { offset: 9, .. })
+        ));
+
+        for input in ["i--7e", "i0-7e", "ie", "i-e", "i+07e"] {
+            let mut parser = Parser::new(input.as_bytes());
+            parser.set_raw_integers(true);
+            assert!(
+                matches!(
+                    decode(&mut parser),
+                    Err(Error::InvalidInteger { offset: 0 })
+                ),
+                "{}",
+                input
+            );
+        }
+        let mut parser = Parser::new("i007e".as_bytes());
+        parser.set_raw_integers(true);
+        parser.set_strict(true);
+        assert!(matches!(
+            decode(&mut parser),
+            Err(Error::InvalidInteger { offset: 0 })
+        ));
+    }
+
+    #[test]
+    fn strict_keys() {
+        let decode_strict = |input: &str| {
+            let mut parser = Parser::new(input.as_bytes());
+            parser.set_strict(true);
+            decode(&mut parser)
+        };
+        assert!(decode_strict("d1:ai1e1:bi2ee").is_ok());
+        assert!(matches!(
+            decode_strict("d1:bi1e1:ai2ee"),
+            Err(Error::UnsortedKeys { .. })
+        ));
+        assert!(matches!(
+            decode_strict("d1:ai1e1:ai2ee"),
+            Err(Error::DuplicateKey { .. })
+        ));
+        assert!(matches!(
+            decode_strict("ld1:ai1e1:ai2eee"),
+            Err(Error::DuplicateKey { .. })
+        ));
+
+        // Lenient by default: later duplicates win.
+        let mut map = BTreeMap::new();
+        map.insert("a".into(), Value::Integer(2));
+        map.insert("b".into(), Value::Integer(1));
+        assert_eq!(
+            decode_str("d1:bi1e1:ai1e1:ai2ee").unwrap(),
+            Value::Dictionary(map)
+        );
+    }
+
+    #[test]
+    fn dictionary_shape() {
+        assert!(matches!(
+            decode_str("d1:ai1eli1ee1:be"),
+            Err(Error::NonStringKey { offset: 7 })
+        ));
+        assert!(matches!(
+            decode_str("di1e1:ae"),
+            Err(Error::NonStringKey { offset: 1 })
+        ));
+        assert!(matches!(
+            decode_str("d1:ai1e1:be"),
+            Err(Error::OddDictLength { offset: 10 })
+        ));
+        assert!(matches!(
+            decode_str("ld1:aee"),
+            Err(Error::OddDictLength { offset: 5 })
+        ));
+    }
+
+    #[test]
+    fn non_string_keys() {
+        // Every decoder rejects keys that aren't byte strings, wherever the
+        // dictionary is, rather than dropping the entries from there on.
+        for (input, offset) in [
+            ("di1e1:ae", 1),
+            ("dli1ee1:ae", 1),
+            ("dde1:ae", 1),
+            ("d1:ai1ei2e1:be", 7),
+            ("l0:d1:ad1:bi1eeli1e2:xyeee", 15),
+            ("d1:ad1:bi1ei-1e0:ee", 11),
+        ] {
+            let parser = || Parser::new(input.as_bytes());
+            let mut offsets = vec![];
+            offsets.extend([
+                decode(&mut parser()).map(|_| ()),
+                decode_slice(input.as_bytes()).map(|_| ()),
+                decode_ordered(&mut parser()).map(|_| ()),
+                decode_interned(&mut parser()).map(|_| ()),
+                decode_spanned(&mut parser()).map(|_| ()),
+                decode_with_report(&mut parser()).map(|_| ()),
+                decode_with_limits(input.as_bytes(), &Limits::default()).map(|_| ()),
+            ]);
+            #[cfg(feature = "std")]
+            offsets.push(decode_hashed(&mut parser()).map(|_| ()));
+            for (decoder, result) in offsets.iter().enumerate() {
+                assert!(
+                    matches!(result, Err(Error::NonStringKey { offset: o }) if *o == offset),
+                    "{} with decoder {}: {:?}",
+                    input,
+                    decoder,
+                    result
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn peek() {
+        let mut parser = Parser::new("l4:spami1ee".as_bytes());
+        assert_eq!(parser.peek(), Some(&ListStart));
+        assert_eq!(parser.peek(), Some(&ListStart));
+        assert_eq!(parser.next().unwrap().unwrap(), ListStart);
+        assert_eq!(
+            parser.peek(),
+            Some(&ValueType(ByteString(b"spam".to_vec())))
+        );
+        assert_eq!(parser.next_event().unwrap(), Event::ByteString(4));
+        assert_eq!(parser.read_bytes().unwrap(), b"spam");
+        assert_eq!(parser.peek(), Some(&ValueType(Integer(1))));
+        parser.skip_value().unwrap();
+        assert_eq!(parser.peek(), Some(&End));
+        assert_eq!(parser.next().unwrap().unwrap(), End);
+        assert_eq!(parser.peek(), None);
+        assert!(parser.next().is_none());
+
+        let mut parser = Parser::new("i1ei2xe".as_bytes());
+        parser.peek();
+        assert_eq!(decode(&mut parser).unwrap(), Integer(1));
+        assert_eq!(parser.peek(), None);
+        assert!(matches!(
+            decode(&mut parser),
+            Err(Error::InvalidInteger { offset: 3 })
+        ));
+
+        let mut parser = Parser::new("i1ei2e".as_bytes());
+        decode(&mut parser).unwrap();
+        parser.peek();
+        assert!(matches!(
+            parser.expect_eof(),
+            Err(Error::TrailingData { offset: 3 })
+        ));
+    }
+
+    /// Fails every read, like a dropped connection.
+    #[cfg(feature = "std")]
+    struct Broken;
+
+    #[cfg(feature = "std")]
+    impl Read for Broken {
+        fn read(&mut self, _: &mut [u8]) -> Result<usize, io::Error> {
+            Err(io::Error::from(std::io::ErrorKind::ConnectionReset))
+        }
+    }
+
+    #[test]
+    fn iterator_errors() {
+        let mut parser = Parser::new("li1ex".as_bytes());
+        assert_eq!(parser.next().unwrap().unwrap(), ListStart);
+        assert_eq!(parser.next().unwrap().unwrap(), ValueType(Integer(1)));
+        assert!(matches!(
+            parser.next(),
+            Some(Err(Error::InvalidToken { offset: 4, .. }))
+        ));
+        assert!(parser.next().is_none());
+
+        #[cfg(feature = "std")]
+        {
+            let mut parser = Parser::new(Broken);
+            assert!(matches!(
+                parser.next(),
+                Some(Err(Error::Io { offset: 0, .. }))
+            ));
+            assert!(parser.next().is_none());
+        }
+    }
+
+    /// Fails every other read with `ErrorKind::Interrupted`, starting with
+    /// the first, like a pipe receiving signals.
+    #[cfg(feature = "std")]
+    struct Interrupting<'a> {
+        input: &'a [u8],
+        interrupt: bool,
+    }
+
+    #[cfg(feature = "std")]
+    impl Read for Interrupting<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, io::Error> {
+            self.interrupt = !self.interrupt;
+            if self.interrupt {
+                return Err(io::Error::from(std::io::ErrorKind::Interrupted));
+            }
+            self.input.read(buf)
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn interrupted() {
+        let raw = std::fs::read("./test/ubuntu-18.04.4-live-server-amd64.iso.torrent").unwrap();
+        let expected = from_bytes(&raw).unwrap();
+        for capacity in [0, 4096] {
+            let mut parser = Parser::new(Interrupting {
+                input: &raw,
+                interrupt: false,
+            });
+            parser.set_buffer_capacity(capacity);
+            assert_eq!(decode_strict(&mut parser).unwrap(), expected);
+        }
+        let mut parser = Parser::new(Interrupting {
+            input: b"l4:spami1ee",
+            interrupt: false,
         });
+        assert_eq!(parser.next_event().unwrap(), Event::ListStart);
+        assert_eq!(parser.next_event().unwrap(), Event::ByteString(4));
+        let mut spam = vec![];
+        parser.copy_bytes(&mut spam).unwrap();
+        assert_eq!(spam, b"spam");
+        let framed = Interrupting {
+            input: b"\x00\x00\x00\x03i1e",
+            interrupt: false,
+        };
+        assert_eq!(super::decode_framed(framed).unwrap(), Integer(1));
+    }
+
+    /// Hands out at most three bytes per call, counting the calls.
+    struct Trickle<'a> {
+        input: &'a [u8],
+        reads: usize,
+    }
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, io::Error> {
+            self.reads += 1;
+            let len = buf.len().min(self.input.len()).min(3);
+            buf[..len].copy_from_slice(&self.input[..len]);
+            self.input = &self.input[len..];
+            Ok(len)
+        }
+    }
+
+    #[test]
+    fn buffering() {
+        let raw = std::fs::read("./test/ubuntu-18.04.4-live-server-amd64.iso.torrent").unwrap();
+        let expected = from_bytes(&raw).unwrap();
+        for capacity in [0, 1, 2, 7, 4096] {
+            let mut reader = Trickle {
+                input: &raw,
+                reads: 0,
+            };
+            let mut parser = Parser::new(&mut reader);
+            parser.set_buffer_capacity(capacity);
+            assert_eq!(decode_strict(&mut parser).unwrap(), expected);
+            assert!(parser.buffer().is_empty());
+            drop(parser);
+            if capacity == 4096 {
+                assert!(reader.reads < raw.len() / 2);
+            }
+        }
+
+        let mut parser = Parser::new("li1ee4:spam".as_bytes());
+        parser.set_buffer_capacity(4);
+        assert_eq!(decode(&mut parser).unwrap(), List(vec![Integer(1)]));
+        assert!(matches!(
+            parser.expect_eof(),
+            Err(Error::TrailingData { offset: 5 })
+        ));
+    }
+
+    #[test]
+    fn position() {
+        let raw = std::fs::read("./test/ubuntu-18.04.4-live-server-amd64.iso.torrent").unwrap();
+        let mut parser = Parser::new(raw.as_slice());
+        parser.set_buffer_capacity(4096);
+        let mut last = 0;
+        while let Some(token) = parser.next() {
+            assert!(parser.position() > last, "{:?}", token);
+            last = parser.position();
+        }
+        assert_eq!(last, raw.len() as u64);
+    }
+
+    #[test]
+    fn into_inner() {
+        // A bencoded header followed by a raw body, as in the BitTorrent
+        // extension protocol's metadata messages.
+        let input = b"d5:piecei0eeRAW";
+        let mut parser = Parser::new(&input[..]);
+        let header = decode(&mut parser).unwrap();
+        assert_eq!(header["piece"], Integer(0));
+        assert_eq!(parser.get_ref(), b"RAW");
+        assert_eq!(parser.into_inner(), b"RAW");
+
+        let mut parser = Parser::new(&input[..]);
+        parser.set_buffer_capacity(8);
+        decode(&mut parser).unwrap();
+        let (reader, buffered) = parser.into_parts();
+        assert_eq!([buffered.as_slice(), reader].concat(), b"RAW");
+
+        #[cfg(feature = "std")]
+        {
+            let mut parser = Parser::new(&input[..]);
+            parser.get_mut().read_exact(&mut [0; 1]).unwrap();
+            assert_eq!(decode(&mut parser).unwrap(), ByteString(b"piece".to_vec()));
+        }
+    }
+
+    #[test]
+    fn reset() {
+        let mut parser = Parser::new("d1:ai1e1:b3:xyzeextra".as_bytes());
+        parser.set_buffer_capacity(8);
+        parser.set_max_depth(1);
+        parser.set_report_keys(true);
+        assert_eq!(parser.next_event().unwrap(), Event::DictStart);
+        assert_eq!(parser.next_event().unwrap(), Event::DictKey(1));
+        assert!(parser.peek().is_some());
+
+        parser.reset("d1:ci2ee".as_bytes());
+        assert_eq!(parser.position(), 0);
+        assert_eq!(parser.buffer(), b"");
+        assert_eq!(parser.next_event().unwrap(), Event::DictStart);
+        assert_eq!(parser.next_event().unwrap(), Event::DictKey(1));
+        assert_eq!(parser.read_bytes().unwrap(), b"c");
+        assert_eq!(parser.next_event().unwrap(), Event::Integer(2));
+        assert_eq!(parser.next_event().unwrap(), Event::End);
+        assert_eq!(parser.next_event().unwrap(), Event::Eof);
+
+        // Settings are kept, and so is nothing else.
+        parser.reset("ll".as_bytes());
+        parser.set_report_keys(false);
+        assert!(matches!(
+            decode(&mut parser),
+            Err(Error::DepthLimitExceeded { offset: 1 })
+        ));
+        assert!(parser.next().is_none());
+        parser.reset("i3e".as_bytes());
+        assert_eq!(parser.next().unwrap().unwrap(), ValueType(Integer(3)));
+    }
+
+    #[test]
+    fn all() {
+        let values: Vec<_> = decode_all("i1eli2ee".as_bytes())
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(values, [Integer(1), List(vec![Integer(2)])]);
+        assert_eq!(decode_all("".as_bytes()).count(), 0);
+
+        let mut values = decode_all("i1ei2xi3e".as_bytes());
+        assert_eq!(values.next().unwrap().unwrap(), Integer(1));
+        assert!(matches!(
+            values.next(),
+            Some(Err(Error::InvalidInteger { offset: 3 }))
+        ));
+        assert!(values.next().is_none());
+    }
+
+    #[test]
+    fn values() {
+        let mut parser = Parser::new(" i1e\r\n\tli2ee\n\ni3e\n".as_bytes());
+        parser.set_trailing_whitespace(true);
+        let values: Vec<_> = decode_values(&mut parser)
+            .take(2)
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(values, [Integer(1), List(vec![Integer(2)])]);
+        let rest: Vec<_> = decode_values(&mut parser).collect();
+        assert_eq!(rest.len(), 1);
+        assert_eq!(rest[0].as_ref().unwrap(), &Integer(3));
+
+        let mut parser = Parser::new("i1e i2e".as_bytes());
+        let mut values = decode_values(&mut parser);
+        assert_eq!(values.next().unwrap().unwrap(), Integer(1));
+        assert!(matches!(
+            values.next(),
+            Some(Err(Error::InvalidToken {
+                byte: b' ',
+                offset: 3
+            }))
+        ));
+        assert!(values.next().is_none());
+    }
+
+    #[test]
+    fn trailing_data() {
+        assert_eq!(from_bytes(b"le").unwrap(), Value::List(vec![]));
+        assert!(matches!(
+            from_bytes(b"i1ei2e"),
+            Err(Error::TrailingData { offset: 3 })
+        ));
+        assert!(matches!(
+            from_bytes(b"lee"),
+            Err(Error::TrailingData { offset: 2 })
+        ));
+        assert!(matches!(
+            from_bytes(b"5:helloi1"),
+            Err(Error::TrailingData { offset: 7 })
+        ));
+        assert!(matches!(
+            from_bytes(b"lejunk"),
+            Err(Error::TrailingData { offset: 2 })
+        ));
+        assert!(matches!(from_bytes(b""), Err(Error::UnexpectedEof { .. })));
+    }
+
+    #[test]
+    fn trailing_whitespace() {
+        let mut raw = std::fs::read("./test/ubuntu-18.04.4-live-server-amd64.iso.torrent").unwrap();
+        let expected = from_bytes(&raw).unwrap();
+        raw.push(b'\n');
+        assert!(matches!(
+            decode_strict(&mut Parser::new(raw.as_slice())),
+            Err(Error::TrailingData { .. })
+        ));
+        let mut parser = Parser::new(raw.as_slice());
+        parser.set_trailing_whitespace(true);
+        assert_eq!(decode_strict(&mut parser).unwrap(), expected);
+
+        let lenient = |input: &'static str| {
+            let mut parser = Parser::new(input.as_bytes());
+            parser.set_trailing_whitespace(true);
+            let value = decode(&mut parser).unwrap();
+            // A peeked token has been read already.
+            parser.peek();
+            parser.expect_eof().map(|()| value)
+        };
+        assert_eq!(lenient("le\t\n").unwrap(), List(vec![]));
+        assert_eq!(lenient("le").unwrap(), List(vec![]));
+        assert!(matches!(
+            lenient("le\n\nle"),
+            Err(Error::TrailingData { offset: 4 })
+        ));
+        assert!(matches!(
+            lenient("lei1e"),
+            Err(Error::TrailingData { offset: 2 })
+        ));
+    }
+
+    #[test]
+    fn offsets() {
+        let offset = |input: &str| decode_str(input).unwrap_err().offset();
+        assert_eq!(offset(""), Some(0));
+        assert_eq!(offset("li1e"), Some(4));
+        assert_eq!(offset("l5:ab"), Some(5));
+        assert_eq!(offset("li1ei12"), Some(7));
+        assert_eq!(offset("li1ex"), Some(4));
+        assert_eq!(offset("li1e3x:abc"), Some(4));
+        assert_eq!(offset("li1eli03ee"), Some(5));
+        assert!(matches!(
+            decode_str("l4:spamle-e"),
+            Err(Error::InvalidToken {
+                byte: b'-',
+                offset: 9
+            })
+        ));
+
+        let mut parser = Parser::new("d1:ai1e1:cd1:bi1e1:ai2eee".as_bytes());
+        parser.set_strict(true);
+        assert!(matches!(
+            decode(&mut parser),
+            Err(Error::UnsortedKeys { offset: 17 })
+        ));
+        // Keys are checked as they are read, so a container in place of a
+        // key fails before anything inside it.
+        let mut parser = Parser::new("d1:ai1ed1:bi1e1:ai2eee".as_bytes());
+        parser.set_strict(true);
+        assert!(matches!(
+            decode(&mut parser),
+            Err(Error::NonStringKey { offset: 7 })
+        ));
+        let mut parser = Parser::with_max_depth("lllee".as_bytes(), 2);
+        assert!(matches!(
+            decode(&mut parser),
+            Err(Error::DepthLimitExceeded { offset: 2 })
+        ));
+    }
+
+    fn decode_str(input: &str) -> Result<Value, Error> {
+        let mut parser = Parser::new(input.as_bytes());
+        decode(&mut parser)
+    }
+
+    /// The tokens read up to the end of the input or the first error, which
+    /// is kept as displayed, along with its offset.
+    type Tokens = (Vec<ParseResult>, Option<String>);
+
+    fn read_tokens(mut next: impl FnMut() -> Result<ParseResult, Error>) -> Tokens {
+        let mut tokens = vec![];
+        loop {
+            match next() {
+                Ok(EOF) => return (tokens, None),
+                Ok(token) => tokens.push(token),
+                Err(e) => return (tokens, Some(e.to_string())),
+            }
+        }
+    }
+
+    #[test]
+    fn grammars() {
+        // `Parser`, the parser behind `decode_slice` and `AsyncParser` drive
+        // a `Tokenizer`, but read byte strings and handle the end of the
+        // input by themselves. They have to agree on every token and every
+        // error.
+        let raw = std::fs::read("./test/ubuntu-18.04.4-live-server-amd64.iso.torrent").unwrap();
+        let inputs = [
+            &raw[..],
+            b"i0e",
+            b"i-42e",
+            b"i9223372036854775808e",
+            b"i-18446744073709551616e",
+            b"0:",
+            b"4:spam",
+            b"le",
+            b"de",
+            b"d1:ai1e1:bli2eee",
+            b"li1e4:spamd1:xleee",
+            b"i1ei2e",
+            b"",
+            b"e",
+            b"ee",
+            b"x",
+            b"lx",
+            b"-1:a",
+            b"i",
+            b"i12",
+            b"i-",
+            b"ie",
+            b"i-e",
+            b"i01e",
+            b"i-0e",
+            b"i1-2e",
+            b"i1x2e",
+            b"i1x",
+            b"i 1e",
+            b"i+1e",
+            b"3",
+            b"3:",
+            b"3:ab",
+            b"3x:abc",
+            b"01:a",
+            b"99999999999999999999999:",
+            b"l",
+            b"li1e",
+            b"l5:ab",
+            b"d1:a",
+        ];
+        for input in inputs.iter() {
+            let mut parser = Parser::new(*input);
+            let expected = read_tokens(|| parser.next_token());
+
+            let mut tokenizer = Tokenizer::new();
+            let mut bytes = input.iter();
+            let tokenized = read_tokens(|| loop {
+                match bytes.next() {
+                    Some(&byte) => {
+                        if let Some(token) = tokenizer.push(byte)? {
+                            return Ok(token);
+                        }
+                    }
+                    None => return tokenizer.finish(),
+                }
+            });
+            assert_eq!(tokenized, expected, "{:?}", input);
+
+            let mut slice = borrowed::SliceParser::with_limits(input, &Limits::default());
+            let sliced = read_tokens(|| {
+                Ok(match slice.next_token()? {
+                    Token::Scalar(value) => ValueType(value.to_value()),
+                    Token::ListStart => ListStart,
+                    Token::DictStart => DictStart,
+                    Token::End => End,
+                    Token::Eof => EOF,
+                })
+            });
+            assert_eq!(sliced, expected, "{:?}", input);
 
-        assert_eq!(res, Value::Dictionary(map));
+            #[cfg(feature = "tokio")]
+            {
+                let runtime = tokio::runtime::Builder::new_current_thread()
+                    .build()
+                    .unwrap();
+                let mut parser = AsyncParser::new(*input);
+                let read = read_tokens(|| runtime.block_on(parser.next_token()));
+                assert_eq!(read, expected, "{:?}", input);
+            }
+        }
+    }
+
+    #[test]
+    fn malformed() {
+        assert!(matches!(decode_str(""), Err(Error::UnexpectedEof { .. })));
+        assert!(matches!(decode_str("l"), Err(Error::UnexpectedEof { .. })));
+        assert!(matches!(
+            decode_str("5:ab"),
+            Err(Error::TruncatedByteString {
+                declared: 5,
+                available: 2,
+                offset: 4
+            })
+        ));
+        assert_eq!(decode_str("i12e").unwrap(), Integer(12));
+        let error = decode_str("i12").unwrap_err();
+        assert!(matches!(
+            error,
+            Error::UnterminatedInteger {
+                start: 0,
+                offset: 3
+            }
+        ));
+        assert!(error.is_unexpected_eof());
+        assert_eq!(
+            error.to_string(),
+            "unexpected end of input in integer at offset 3"
+        );
+        assert!(matches!(
+            decode_str("li1ei-"),
+            Err(Error::UnterminatedInteger {
+                start: 4,
+                offset: 6
+            })
+        ));
+        let mut parser = Parser::with_read_limit("i12e".as_bytes(), 3);
+        assert!(matches!(
+            decode(&mut parser),
+            Err(Error::ReadLimitExceeded { offset: 0 })
+        ));
+        assert!(matches!(
+            decode_str("x"),
+            Err(Error::InvalidToken { byte: b'x', .. })
+        ));
+        assert!(matches!(
+            decode_str("e"),
+            Err(Error::UnexpectedEnd { offset: 0 })
+        ));
+        assert!(matches!(
+            decode_str("iabce"),
+            Err(Error::InvalidInteger { .. })
+        ));
+        assert!(matches!(
+            decode_str("3x:abc"),
+            Err(Error::InvalidLength { .. })
+        ));
+        assert!(matches!(
+            decode_str("99999999999999999999999:"),
+            Err(Error::InvalidLength { .. })
+        ));
+    }
+
+    #[test]
+    fn length_overflow() {
+        // What a 32-bit target sees for lengths around `u32::MAX`.
+        let max = u64::from(u32::MAX);
+        assert_eq!(check_length(max, u32::MAX, 0).unwrap(), u32::MAX);
+        assert!(matches!(
+            check_length(max + 1, u32::MAX, 3),
+            Err(Error::LengthLimitExceeded { offset: 3 })
+        ));
+        assert!(matches!(
+            check_length(max, 1000u32, 0),
+            Err(Error::LengthLimitExceeded { .. })
+        ));
+
+        // The largest `u64` is a well-formed length, if likely too long.
+        let mut parser = Parser::new("18446744073709551615:".as_bytes());
+        parser.set_max_byte_string_len(1 << 20);
+        assert!(matches!(
+            decode(&mut parser),
+            Err(Error::LengthLimitExceeded { offset: 0 })
+        ));
+        assert!(matches!(
+            decode_str("18446744073709551616:"),
+            Err(Error::InvalidLength { offset: 0 })
+        ));
+        assert!(matches!(
+            decode_slice(b"l18446744073709551616:e"),
+            Err(Error::InvalidLength { offset: 1 })
+        ));
+    }
+
+    #[test]
+    fn length_headers() {
+        // Anything but digits between the start of a length and its `:` is
+        // an invalid length.
+        for input in ["3 :abc", "3+:abc", "3x:abc", "0x3:abc", "1 2:ab", "3\t:abc"] {
+            for result in [
+                decode_str(input).map(|_| ()),
+                crate::decode_slice(input.as_bytes()).map(|_| ()),
+            ] {
+                assert!(
+                    matches!(result, Err(Error::InvalidLength { offset: 0 })),
+                    "{:?}: {:?}",
+                    input,
+                    result
+                );
+            }
+        }
+        // A sign or whitespace in front can't start any token.
+        for input in ["+3:abc", " 3:abc", "-3:abc", "\n3:abc"] {
+            for result in [
+                decode_str(input).map(|_| ()),
+                crate::decode_slice(input.as_bytes()).map(|_| ()),
+            ] {
+                assert!(
+                    matches!(result, Err(Error::InvalidToken { offset: 0, .. })),
+                    "{:?}: {:?}",
+                    input,
+                    result
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_token_bytes() {
+        // Any byte that can't start a token is an error, never a panic.
+        for byte in (0..=u8::MAX).filter(|b| !b"0123456789ield".contains(b)) {
+            let input = [b'l', byte, b'e'];
+            for result in [
+                decode(&mut Parser::new(&input[..])).map(|_| ()),
+                crate::decode_slice(&input).map(|_| ()),
+            ] {
+                assert!(
+                    matches!(result, Err(Error::InvalidToken { byte: b, offset: 1 }) if b == byte),
+                    "{:?}: {:?}",
+                    byte,
+                    result
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn random_input() {
+        // Thousands of garbled documents, from a fixed xorshift stream: random
+        // bencode-ish bytes, and mutated prefixes of a real torrent. Every
+        // decoder has to return rather than panic, and they have to agree.
+        let torrent = std::fs::read("./test/ubuntu-18.04.4-live-server-amd64.iso.torrent").unwrap();
+        let mut state = 0x9e37_79b9_7f4a_7c15_u64;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        for round in 0..4000 {
+            let input: Vec<u8> = if round % 2 == 0 {
+                let len = next() as usize % 64;
+                (0..len)
+                    .map(|_| b"0123456789:ildex-"[next() as usize % 17])
+                    .collect()
+            } else {
+                let mut input = torrent[..next() as usize % 400].to_vec();
+                for _ in 0..next() % 4 {
+                    if !input.is_empty() {
+                        let at = next() as usize % input.len();
+                        input[at] = next() as u8;
+                    }
+                }
+                input
+            };
+
+            let value = from_bytes(&input);
+            let borrowed = crate::decode_slice(&input);
+            assert_eq!(
+                value.as_ref().ok(),
+                borrowed.as_ref().ok().map(ValueRef::to_value).as_ref(),
+                "{:?}",
+                input
+            );
+            assert_eq!(value.is_ok(), borrowed.is_ok(), "{:?}", input);
+            let _ = decode_ordered(&mut Parser::new(&input[..]));
+            let _ = decode_spanned(&mut Parser::new(&input[..]));
+            let _ = decode_with_limits(&input[..], &Limits::default());
+            let _ = Parser::new(&input[..]).skip_value();
+        }
     }
 }
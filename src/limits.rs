@@ -0,0 +1,159 @@
+use crate::io::Read;
+use crate::{decode_strict, Error, Parser, Value, DEFAULT_MAX_DEPTH};
+
+/// All the resource limits that `decode_with_limits` enforces on untrusted
+/// input. Each one trips its own `Error` variant, so callers can tell which
+/// bound was hit. `Default` allows `DEFAULT_MAX_DEPTH` levels of nesting and
+/// leaves everything else unlimited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// Maximum number of nested containers, see `Error::DepthLimitExceeded`.
+    pub max_depth: usize,
+    /// Maximum length of a single byte string, see
+    /// `Error::LengthLimitExceeded`.
+    pub max_string_len: usize,
+    /// Maximum size of the encoded value in bytes, see
+    /// `Error::ReadLimitExceeded`.
+    pub max_total_bytes: usize,
+    /// Maximum number of values, counting containers as well as everything
+    /// in them, see `Error::ItemLimitExceeded`.
+    pub max_items: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_depth: DEFAULT_MAX_DEPTH,
+            max_string_len: usize::MAX,
+            max_total_bytes: usize::MAX,
+            max_items: usize::MAX,
+        }
+    }
+}
+
+/// Like `from_reader`, but enforces `limits`. Byte strings are checked
+/// against the length and size limits before anything is allocated for them,
+/// and no more than `max_total_bytes` are read from `reader`, so a value that
+/// fills them isn't checked for trailing data.
+///
+/// ```
+/// use bencode_decode::{decode_with_limits, Error, Limits};
+///
+/// let limits = Limits {
+///     max_items: 3,
+///     ..Limits::default()
+/// };
+/// assert!(decode_with_limits("li1ei2ee".as_bytes(), &limits).is_ok());
+/// assert!(matches!(
+///     decode_with_limits("li1ei2ei3ee".as_bytes(), &limits),
+///     Err(Error::ItemLimitExceeded { offset: 7 })
+/// ));
+/// ```
+pub fn decode_with_limits<R: Read>(reader: R, limits: &Limits) -> Result<Value, Error> {
+    let mut parser = Parser::with_max_depth(reader, limits.max_depth);
+    parser.set_max_byte_string_len(limits.max_string_len);
+    parser.set_max_items(limits.max_items);
+    parser.set_read_limit(limits.max_total_bytes);
+    decode_strict(&mut parser)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::from_bytes;
+    use std::fs;
+
+    fn decode(input: &str, limits: Limits) -> Result<Value, Error> {
+        decode_with_limits(input.as_bytes(), &limits)
+    }
+
+    #[test]
+    fn defaults() {
+        let raw = fs::read("./test/ubuntu-18.04.4-live-server-amd64.iso.torrent").unwrap();
+        assert_eq!(
+            decode_with_limits(raw.as_slice(), &Limits::default()).unwrap(),
+            from_bytes(&raw).unwrap()
+        );
+        assert!(matches!(
+            decode("i1ei2e", Limits::default()),
+            Err(Error::TrailingData { offset: 3 })
+        ));
+    }
+
+    #[test]
+    fn limits() {
+        let limits = Limits::default();
+        assert!(matches!(
+            decode(
+                "lli1eee",
+                Limits {
+                    max_depth: 1,
+                    ..limits
+                }
+            ),
+            Err(Error::DepthLimitExceeded { offset: 1 })
+        ));
+        assert!(matches!(
+            decode(
+                "l3:abce",
+                Limits {
+                    max_string_len: 2,
+                    ..limits
+                }
+            ),
+            Err(Error::LengthLimitExceeded { offset: 1 })
+        ));
+
+        let total = |max_total_bytes| Limits {
+            max_total_bytes,
+            ..limits
+        };
+        assert!(decode("l3:abce", total(7)).is_ok());
+        // Nothing past the limit is read.
+        assert!(decode("l3:abcei1e", total(7)).is_ok());
+        assert!(matches!(
+            decode("l3:abcei1e", total(8)),
+            Err(Error::TrailingData { offset: 7 })
+        ));
+        assert!(matches!(
+            decode("l3:abce", total(6)),
+            Err(Error::ReadLimitExceeded { offset: 6 })
+        ));
+        assert!(matches!(
+            decode("l3:abce", total(5)),
+            Err(Error::ReadLimitExceeded { offset: 1 })
+        ));
+        // A huge declared length is refused without reading the payload.
+        assert!(matches!(
+            decode("99999999999:", total(1024)),
+            Err(Error::ReadLimitExceeded { offset: 0 })
+        ));
+        // So is an endless integer, without reading more than the limit.
+        #[cfg(feature = "std")]
+        {
+            let endless = |byte| std::io::Read::chain(&b"li1ei"[..], std::io::repeat(byte));
+            assert!(matches!(
+                decode_with_limits(endless(b'1'), &total(1024)),
+                Err(Error::ReadLimitExceeded { offset: 4 })
+            ));
+            assert!(matches!(
+                decode_with_limits(endless(b'x'), &limits),
+                Err(Error::InvalidInteger { offset: 4 })
+            ));
+        }
+
+        let items = |max_items| Limits {
+            max_items,
+            ..limits
+        };
+        assert!(decode("d1:ai1ee", items(3)).is_ok());
+        assert!(matches!(
+            decode("d1:ai1ee", items(2)),
+            Err(Error::ItemLimitExceeded { offset: 4 })
+        ));
+        assert!(matches!(
+            decode("le", items(0)),
+            Err(Error::ItemLimitExceeded { offset: 0 })
+        ));
+    }
+}
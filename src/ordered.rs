@@ -0,0 +1,312 @@
+use crate::encode::Piece;
+use crate::io::{self, Read, Write};
+use crate::{build, Error, Key, Parser, Tree, Value};
+use alloc::{collections::BTreeMap, string::String, vec, vec::Vec};
+use core::ops::Range;
+
+/// Like `Value`, but dictionaries keep their entries in input order,
+/// including duplicate keys, as produced by `decode_ordered`. Re-encoding one
+/// reproduces the input byte for byte, even if it wasn't canonical.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum OrderedValue {
+    ByteString(Vec<u8>),
+    Integer(i64),
+    BigInteger(String),
+    /// See `Value::RawInteger`.
+    RawInteger(String),
+    List(Vec<OrderedValue>),
+    Dictionary(Vec<(Vec<u8>, OrderedValue)>),
+}
+
+impl OrderedValue {
+    /// Looks up the first entry for `key` if this is a `Dictionary`.
+    pub fn get(&self, key: impl AsRef<[u8]>) -> Option<&OrderedValue> {
+        match self {
+            OrderedValue::Dictionary(entries) => entries
+                .iter()
+                .find(|(k, _)| k.as_slice() == key.as_ref())
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    /// Converts into a plain `Value`, sorting dictionary keys. Of duplicate
+    /// keys, the last entry wins, as in `decode`.
+    pub fn to_value(&self) -> Value {
+        match self {
+            OrderedValue::ByteString(bytes) => Value::ByteString(bytes.clone()),
+            OrderedValue::Integer(int) => Value::Integer(*int),
+            OrderedValue::BigInteger(digits) => Value::BigInteger(digits.clone()),
+            OrderedValue::RawInteger(digits) => Value::RawInteger(digits.clone()),
+            OrderedValue::List(list) => {
+                Value::List(list.iter().map(OrderedValue::to_value).collect())
+            }
+            OrderedValue::Dictionary(entries) => Value::Dictionary(
+                entries
+                    .iter()
+                    .map(|(key, value)| (Key::from(key.clone()), value.to_value()))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Whether both values mean the same, regardless of the order of
+    /// dictionary entries: dictionaries compare by their keys and, for
+    /// duplicate keys, the last entry, which is what `to_value` keeps.
+    /// Integers compare by value as in `Value::semantic_eq`.
+    ///
+    /// ```
+    /// use bencode_decode::{decode_ordered, Parser};
+    ///
+    /// let decode = |input: &[u8]| decode_ordered(&mut Parser::new(input)).unwrap();
+    /// let unsorted = decode(b"d1:bi1e1:ai2e1:bi3ee");
+    /// assert_ne!(unsorted, decode(b"d1:ai2e1:bi3ee"));
+    /// assert!(unsorted.semantic_eq(&decode(b"d1:ai2e1:bi3ee")));
+    /// assert!(!unsorted.semantic_eq(&decode(b"d1:ai2e1:bi1ee")));
+    /// ```
+    pub fn semantic_eq(&self, other: &OrderedValue) -> bool {
+        match (self, other) {
+            (this, other) if this.is_integer() && other.is_integer() => {
+                this.to_value().semantic_eq(&other.to_value())
+            }
+            (OrderedValue::List(list), OrderedValue::List(other)) => {
+                list.len() == other.len() && list.iter().zip(other).all(|(a, b)| a.semantic_eq(b))
+            }
+            (OrderedValue::Dictionary(entries), OrderedValue::Dictionary(other)) => {
+                let (map, other) = (by_key(entries), by_key(other));
+                map.len() == other.len()
+                    && map
+                        .iter()
+                        .zip(&other)
+                        .all(|((k, a), (l, b))| k == l && a.semantic_eq(b))
+            }
+            (this, other) => this == other,
+        }
+    }
+
+    fn is_integer(&self) -> bool {
+        matches!(
+            self,
+            OrderedValue::Integer(_) | OrderedValue::BigInteger(_) | OrderedValue::RawInteger(_)
+        )
+    }
+
+    /// Writes the bencoded representation of this value to `writer`, with
+    /// dictionary entries in their stored order.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        self.pieces().try_for_each(|piece| piece.write_to(writer))
+    }
+
+    /// The pieces this value is written as, in order, like
+    /// `Value::pieces`.
+    fn pieces(&self) -> impl Iterator<Item = Piece<'_, OrderedValue>> {
+        let mut stack = vec![Piece::Value(self)];
+        core::iter::from_fn(move || {
+            let piece = stack.pop()?;
+            // Reverse order, so that popping the stack yields items in order.
+            match piece {
+                Piece::Value(OrderedValue::List(list)) => {
+                    stack.push(Piece::End);
+                    stack.extend(list.iter().rev().map(Piece::Value));
+                }
+                Piece::Value(OrderedValue::Dictionary(entries)) => {
+                    stack.push(Piece::End);
+                    for (key, value) in entries.iter().rev() {
+                        stack.push(Piece::Value(value));
+                        stack.push(Piece::Key(key));
+                    }
+                }
+                _ => {}
+            }
+            Some(piece)
+        })
+    }
+
+    /// Returns the bencoded representation of this value. See `write_to`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = vec![];
+        self.write_to(&mut buf)
+            .expect("Writing to a Vec never fails");
+        buf
+    }
+}
+
+impl Piece<'_, OrderedValue> {
+    fn write_to<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        match *self {
+            Piece::Value(OrderedValue::ByteString(bytes)) => {
+                write!(writer, "{}:", bytes.len())?;
+                writer.write_all(bytes)
+            }
+            Piece::Value(OrderedValue::Integer(int)) => write!(writer, "i{}e", int),
+            Piece::Value(OrderedValue::BigInteger(digits) | OrderedValue::RawInteger(digits)) => {
+                write!(writer, "i{}e", digits)
+            }
+            Piece::Value(OrderedValue::List(_)) => writer.write_all(b"l"),
+            Piece::Value(OrderedValue::Dictionary(_)) => writer.write_all(b"d"),
+            Piece::Key(key) => {
+                write!(writer, "{}:", key.len())?;
+                writer.write_all(key)
+            }
+            Piece::End => writer.write_all(b"e"),
+        }
+    }
+}
+
+/// The entries of a dictionary sorted by key, the last one winning for
+/// duplicate keys.
+fn by_key(entries: &[(Vec<u8>, OrderedValue)]) -> BTreeMap<&[u8], &OrderedValue> {
+    entries
+        .iter()
+        .map(|(key, value)| (key.as_slice(), value))
+        .collect()
+}
+
+/// Like `decode`, but keeps dictionary entries in the order they appear in
+/// the input, for tools that need to reproduce non-canonical input exactly.
+///
+/// ```
+/// use bencode_decode::{decode_ordered, Parser};
+///
+/// let input = b"d1:bi1e1:ai2ee";
+/// let value = decode_ordered(&mut Parser::new(&input[..])).unwrap();
+/// assert_eq!(value.to_bytes(), input);
+/// assert_eq!(value.to_value().to_bytes(), b"d1:ai2e1:bi1ee");
+/// ```
+pub fn decode_ordered<R: Read>(parser: &mut Parser<R>) -> Result<OrderedValue, Error> {
+    build(parser, Ordered)
+}
+
+/// The `Tree` of `OrderedValue`s.
+struct Ordered;
+
+impl Tree for Ordered {
+    type Node = OrderedValue;
+
+    fn scalar(&mut self, value: Value, _: Range<usize>) -> OrderedValue {
+        match value {
+            Value::ByteString(bytes) => OrderedValue::ByteString(bytes),
+            Value::Integer(int) => OrderedValue::Integer(int),
+            Value::BigInteger(digits) => OrderedValue::BigInteger(digits),
+            Value::RawInteger(digits) => OrderedValue::RawInteger(digits),
+            _ => unreachable!("the parser only emits scalars"),
+        }
+    }
+
+    fn list(&mut self, items: Vec<OrderedValue>, _: Range<usize>) -> OrderedValue {
+        OrderedValue::List(items)
+    }
+
+    fn dict(
+        &mut self,
+        entries: Vec<(usize, Vec<u8>, OrderedValue)>,
+        _: Range<usize>,
+    ) -> OrderedValue {
+        OrderedValue::Dictionary(
+            entries
+                .into_iter()
+                .map(|(_, key, value)| (key, value))
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::decode;
+    use std::fs;
+
+    #[test]
+    fn round_trip() {
+        for input in [
+            &b"d1:bi1e1:ai2ee"[..],
+            b"d1:ai1e1:ai2ee",
+            b"ld1:zle1:yd1:ci1e1:bi2eeei-18446744073709551616ee",
+            b"de",
+        ] {
+            let value = decode_ordered(&mut Parser::new(input)).unwrap();
+            assert_eq!(value.to_bytes(), input);
+        }
+
+        let raw = fs::read("./test/ubuntu-18.04.4-live-server-amd64.iso.torrent").unwrap();
+        let value = decode_ordered(&mut Parser::new(raw.as_slice())).unwrap();
+        assert_eq!(value.to_bytes(), raw);
+        assert_eq!(
+            value.to_value(),
+            decode(&mut Parser::new(raw.as_slice())).unwrap()
+        );
+    }
+
+    #[test]
+    fn deep() {
+        let mut deep = OrderedValue::Integer(0);
+        for _ in 0..200_000 {
+            deep = OrderedValue::List(vec![deep]);
+        }
+        deep = OrderedValue::Dictionary(vec![(b"a".to_vec(), deep)]);
+        let mut expected = b"d1:a".to_vec();
+        expected.extend(vec![b'l'; 200_000]);
+        expected.extend_from_slice(b"i0e");
+        expected.extend(vec![b'e'; 200_001]);
+        assert!(deep.to_bytes() == expected);
+        // Dropping is recursive, so unwind the list by hand.
+        if let OrderedValue::Dictionary(mut entries) = deep {
+            let (_, mut value) = entries.pop().unwrap();
+            while let OrderedValue::List(mut list) = value {
+                value = list.pop().unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn duplicates() {
+        let value = decode_ordered(&mut Parser::new("d1:ai1e1:ai2ee".as_bytes())).unwrap();
+        assert_eq!(value.get("a"), Some(&OrderedValue::Integer(1)));
+        assert_eq!(value.to_value()["a"], Value::Integer(2));
+    }
+
+    #[test]
+    fn semantic_eq() {
+        let decode = |input: &str| decode_ordered(&mut Parser::new(input.as_bytes())).unwrap();
+        let value = decode("ld1:bli1ei2ee1:ad1:yi1e1:xi2eeee");
+        assert!(value.semantic_eq(&decode("ld1:ad1:xi2e1:yi1ee1:bli1ei2eeee")));
+        assert!(!value.semantic_eq(&decode("ld1:ad1:xi2e1:yi1ee1:bli2ei1eeee")));
+        assert!(!value.semantic_eq(&decode("ld1:ad1:xi2ee1:bli1ei2eeee")));
+        assert!(!decode("d1:ai1ee").semantic_eq(&decode("d1:bi1ee")));
+        assert!(OrderedValue::BigInteger("-3".into()).semantic_eq(&OrderedValue::Integer(-3)));
+    }
+
+    #[test]
+    fn malformed() {
+        let decode = |input: &str, strict: bool| {
+            let mut parser = Parser::new(input.as_bytes());
+            parser.set_strict(strict);
+            decode_ordered(&mut parser)
+        };
+        assert!(matches!(
+            decode("d1:bi1e1:ai2ee", true),
+            Err(Error::UnsortedKeys { offset: 7 })
+        ));
+        assert!(matches!(
+            decode("d1:ai1e1:ai2ee", true),
+            Err(Error::DuplicateKey { offset: 7 })
+        ));
+        assert!(matches!(
+            decode("di1ei2ee", false),
+            Err(Error::NonStringKey { offset: 1 })
+        ));
+        assert!(matches!(
+            decode("d1:ae", false),
+            Err(Error::OddDictLength { offset: 4 })
+        ));
+        assert!(matches!(
+            decode("l", false),
+            Err(Error::UnexpectedEof { offset: 1 })
+        ));
+        assert!(matches!(
+            decode("e", false),
+            Err(Error::UnexpectedEnd { offset: 0 })
+        ));
+    }
+}
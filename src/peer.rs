@@ -0,0 +1,87 @@
+//! Helpers for the BitTorrent peer wire protocol.
+//!
+//! Extension messages (BEP 10) carry a bencoded dictionary, which for some
+//! of them is followed by raw binary data, like a piece of the `info`
+//! dictionary in a ut_metadata (BEP 9) `data` message. On the wire, such a
+//! message is a 4-byte big-endian length, the message ID 20 and an extended
+//! message ID byte; what's left of the message after those is its body, as
+//! taken by `decode_message`.
+//!
+//! ```
+//! use bencode_decode::peer::decode_message;
+//!
+//! let body = b"d8:msg_typei1e5:piecei0e10:total_sizei5eeINFO!";
+//! let (dict, metadata) = decode_message(body).unwrap();
+//! assert_eq!(dict["msg_type"], 1);
+//! assert_eq!(dict["total_size"].as_u64(), Some(5));
+//! assert_eq!(metadata, b"INFO!");
+//! ```
+
+use crate::{decode_prefix, Error, Value};
+
+/// Decodes the bencoded dictionary at the start of an extension message
+/// `body`, returning it along with the bytes following it, which are empty
+/// for most messages. Fails with `Error::TypeMismatch` if `body` starts with
+/// a value other than a dictionary. See `decode_prefix`.
+pub fn decode_message(body: &[u8]) -> Result<(Value, &[u8]), Error> {
+    let (value, rest) = decode_prefix(body)?;
+    match value {
+        Value::Dictionary(_) => Ok((value, rest)),
+        value => Err(Error::TypeMismatch {
+            expected: "dictionary",
+            found: value.type_name(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bencode;
+
+    #[test]
+    fn handshake() {
+        let body = b"d1:md11:ut_metadatai3ee13:metadata_sizei31235ee";
+        let (dict, rest) = decode_message(body).unwrap();
+        assert_eq!(
+            dict,
+            bencode!({ "m": { "ut_metadata": 3 }, "metadata_size": 31235 })
+        );
+        assert!(rest.is_empty());
+    }
+
+    #[test]
+    fn trailing_data() {
+        // Data following the dictionary is returned as is, even if it looks
+        // like bencode.
+        let (dict, rest) = decode_message(b"d8:msg_typei1e5:piecei1eei1e\x00\xff").unwrap();
+        assert_eq!(dict["piece"], 1);
+        assert_eq!(rest, b"i1e\x00\xff");
+    }
+
+    #[test]
+    fn malformed() {
+        assert!(matches!(
+            decode_message(b"li1ee"),
+            Err(Error::TypeMismatch {
+                expected: "dictionary",
+                found: "list"
+            })
+        ));
+        assert!(matches!(
+            decode_message(b""),
+            Err(Error::UnexpectedEof { offset: 0 })
+        ));
+        assert!(matches!(
+            decode_message(b"d8:msg_typei1e"),
+            Err(Error::UnexpectedEof { offset: 14 })
+        ));
+        assert!(matches!(
+            decode_message(b"d5:piece\xff"),
+            Err(Error::InvalidToken {
+                byte: 0xff,
+                offset: 8
+            })
+        ));
+    }
+}
@@ -0,0 +1,223 @@
+use crate::io::Read;
+use crate::{build, Error, Key, Parser, Plain, Tree, Value};
+use alloc::{collections::BTreeMap, vec, vec::Vec};
+use core::ops::Range;
+
+/// A deviation from canonical bencode, as listed by `decode_with_report`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Violation {
+    /// An integer with leading zeros or a negative zero, like `i03e` or
+    /// `i-0e`.
+    NonCanonicalInteger { offset: usize },
+    /// A dictionary key sorting before the key preceding it.
+    UnsortedKeys { offset: usize },
+    /// A dictionary key occurring more than once. The last entry wins.
+    DuplicateKey { offset: usize },
+}
+
+impl Violation {
+    /// Offset of the integer or key in question.
+    pub fn offset(&self) -> usize {
+        match *self {
+            Violation::NonCanonicalInteger { offset }
+            | Violation::UnsortedKeys { offset }
+            | Violation::DuplicateKey { offset } => offset,
+        }
+    }
+}
+
+/// The violations found by `decode_with_report`, ordered by offset.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ComplianceReport {
+    pub violations: Vec<Violation>,
+}
+
+impl ComplianceReport {
+    /// Whether the input was canonical bencode.
+    pub fn is_compliant(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+/// Like `decode`, but rather than failing on input that is well-formed yet
+/// not canonical, lists where it deviates. Integers with leading zeros or a
+/// negative zero are read as their canonical counterparts, and dictionaries
+/// with unsorted or duplicate keys are accepted and listed even if the
+/// parser is in strict mode, where `decode` would fail on them. Anything
+/// else that's malformed still fails, and the parser's limits on depth and
+/// items apply as with `decode`.
+///
+/// ```
+/// use bencode_decode::{decode_with_report, Parser, Value, Violation};
+///
+/// let mut parser = Parser::new("d1:bi03e1:ai-0ee".as_bytes());
+/// let (value, report) = decode_with_report(&mut parser).unwrap();
+/// assert_eq!(value["b"], Value::Integer(3));
+/// assert_eq!(
+///     report.violations,
+///     [
+///         Violation::NonCanonicalInteger { offset: 4 },
+///         Violation::UnsortedKeys { offset: 8 },
+///         Violation::NonCanonicalInteger { offset: 11 },
+///     ]
+/// );
+/// ```
+pub fn decode_with_report<R: Read>(
+    parser: &mut Parser<R>,
+) -> Result<(Value, ComplianceReport), Error> {
+    let mut keys = BTreeMap::new();
+    parser.tokenizer.noncanonical = Some(vec![]);
+    let value = build(parser, Report { keys: &mut keys });
+    let integers = parser.tokenizer.noncanonical.take().unwrap_or_default();
+    let mut violations: Vec<_> = keys.into_values().collect();
+    violations.extend(
+        integers
+            .into_iter()
+            .map(|offset| Violation::NonCanonicalInteger { offset }),
+    );
+    violations.sort_by_key(Violation::offset);
+    Ok((value?, ComplianceReport { violations }))
+}
+
+/// The `Tree` of plain `Value`s that records dictionary keys out of order,
+/// by their offsets, rather than failing on them.
+struct Report<'a> {
+    keys: &'a mut BTreeMap<usize, Violation>,
+}
+
+impl Tree for Report<'_> {
+    type Node = Value;
+
+    fn scalar(&mut self, value: Value, span: Range<usize>) -> Value {
+        Plain.scalar(value, span)
+    }
+
+    fn list(&mut self, items: Vec<Value>, span: Range<usize>) -> Value {
+        Plain.list(items, span)
+    }
+
+    /// Also records keys that repeat one further back than the key right
+    /// before them, which `misordered_key` has taken as unsorted.
+    fn dict(&mut self, entries: Vec<(usize, Vec<u8>, Value)>, _: Range<usize>) -> Value {
+        let mut map = BTreeMap::new();
+        for (offset, key, value) in entries {
+            if map.insert(Key::from(key), value).is_some() {
+                self.keys.insert(offset, Violation::DuplicateKey { offset });
+            }
+        }
+        Value::Dictionary(map)
+    }
+
+    fn misordered_key(&mut self, error: Error, _: bool) -> Result<(), Error> {
+        let violation = match error {
+            Error::UnsortedKeys { offset } => Violation::UnsortedKeys { offset },
+            Error::DuplicateKey { offset } => Violation::DuplicateKey { offset },
+            error => return Err(error),
+        };
+        self.keys.insert(violation.offset(), violation);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::decode_strict;
+    use std::fs;
+
+    fn decode_str(input: &str) -> Result<(Value, ComplianceReport), Error> {
+        let mut parser = Parser::new(input.as_bytes());
+        parser.set_strict(true);
+        decode_with_report(&mut parser)
+    }
+
+    #[test]
+    fn canonical() {
+        let raw = fs::read("./test/ubuntu-18.04.4-live-server-amd64.iso.torrent").unwrap();
+        let (value, report) = decode_with_report(&mut Parser::new(raw.as_slice())).unwrap();
+        assert!(report.is_compliant());
+        assert_eq!(
+            value,
+            decode_strict(&mut Parser::new(raw.as_slice())).unwrap()
+        );
+    }
+
+    #[test]
+    fn integers() {
+        let (value, report) =
+            decode_str("li00ei-007ei-0ei0ei-0000ei0009223372036854775808ei-09223372036854775808ee")
+                .unwrap();
+        assert_eq!(
+            value,
+            Value::List(vec![
+                Value::Integer(0),
+                Value::Integer(-7),
+                Value::Integer(0),
+                Value::Integer(0),
+                Value::Integer(0),
+                Value::BigInteger("9223372036854775808".into()),
+                Value::Integer(i64::MIN),
+            ])
+        );
+        let offsets: Vec<_> = report.violations.iter().map(Violation::offset).collect();
+        assert_eq!(offsets, [1, 5, 11, 18, 25, 49]);
+
+        assert!(matches!(
+            decode_str("i+1e"),
+            Err(Error::InvalidInteger { offset: 0 })
+        ));
+        assert!(matches!(
+            decode_str("li-ee"),
+            Err(Error::InvalidInteger { offset: 1 })
+        ));
+        // Integers are strict again afterwards.
+        let mut parser = Parser::new("i01ei01e".as_bytes());
+        decode_with_report(&mut parser).unwrap();
+        assert!(matches!(
+            decode_strict(&mut parser),
+            Err(Error::InvalidInteger { offset: 4 })
+        ));
+    }
+
+    #[test]
+    fn dictionaries() {
+        let (value, report) = decode_str("d1:bi1e1:ai2e1:bi3e1:ci4ee").unwrap();
+        assert_eq!(value["b"], Value::Integer(3));
+        assert_eq!(
+            report.violations,
+            [
+                Violation::UnsortedKeys { offset: 7 },
+                Violation::DuplicateKey { offset: 13 },
+            ]
+        );
+        // A key that repeats an earlier one counts as a duplicate, even if
+        // it is also out of order.
+        let (_, report) = decode_str("d1:ci1e1:ai2e1:bi3e1:ai4ee").unwrap();
+        assert_eq!(
+            report.violations,
+            [
+                Violation::UnsortedKeys { offset: 7 },
+                Violation::DuplicateKey { offset: 19 },
+            ]
+        );
+        assert!(matches!(
+            decode_str("d1:ae"),
+            Err(Error::OddDictLength { offset: 4 })
+        ));
+    }
+
+    #[test]
+    fn limits() {
+        let mut parser = Parser::new("d1:bi1e1:ai2ee".as_bytes());
+        parser.set_max_items(4);
+        assert!(matches!(
+            decode_with_report(&mut parser),
+            Err(Error::ItemLimitExceeded { offset: 10 })
+        ));
+        let mut parser = Parser::with_max_depth("d1:ald1:bleee".as_bytes(), 2);
+        assert!(matches!(
+            decode_with_report(&mut parser),
+            Err(Error::DepthLimitExceeded { offset: 5 })
+        ));
+    }
+}
@@ -0,0 +1,477 @@
+//! Serialization of any type implementing `serde::Serialize` into bencode.
+//!
+//! Values are first collected into a `Value` tree and then encoded, so
+//! struct fields and map entries always come out with sorted keys. `None`
+//! and `()` have no bencode representation; struct fields and map entries
+//! holding them are skipped, anywhere else they are an error. The same goes
+//! for floating point numbers. Enums are externally tagged, mirroring the
+//! deserializer in `de`.
+//!
+//! ```
+//! use serde::Serialize;
+//!
+//! #[derive(Serialize)]
+//! struct Info {
+//!     name: String,
+//!     length: u64,
+//!     private: Option<bool>,
+//! }
+//!
+//! let info = Info { name: "spam".to_string(), length: 42, private: None };
+//! assert_eq!(
+//!     bencode_decode::ser::to_bytes(&info).unwrap(),
+//!     b"d6:lengthi42e4:name4:spame"
+//! );
+//! ```
+
+use crate::{encode, Error, Key, Value};
+use serde::ser::{self, Serialize};
+use std::{collections::BTreeMap, convert::TryInto, fmt};
+
+impl ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error::Custom(msg.to_string())
+    }
+}
+
+/// Serializes `value` into bencoded bytes.
+pub fn to_bytes<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, Error> {
+    Ok(encode(&to_value(value)?))
+}
+
+/// Converts `value` into a `Value` tree.
+pub fn to_value<T: Serialize + ?Sized>(value: &T) -> Result<Value, Error> {
+    value
+        .serialize(Serializer)
+        .and_then(|value| value.ok_or_else(|| ser::Error::custom("value has no bencode form")))
+}
+
+/// A `serde::Serializer` producing a `Value`, or `None` for values that
+/// bencode cannot represent and that containers should skip.
+pub struct Serializer;
+
+/// Integers beyond `i64` become a `Value::BigInteger`.
+fn integer<T: TryInto<i64> + fmt::Display + Copy>(v: T) -> Result<Option<Value>, Error> {
+    Ok(Some(match v.try_into() {
+        Ok(int) => Value::Integer(int),
+        Err(_) => Value::BigInteger(v.to_string()),
+    }))
+}
+
+fn bytes(v: &[u8]) -> Result<Option<Value>, Error> {
+    Ok(Some(Value::ByteString(v.to_vec())))
+}
+
+fn tagged(variant: &str, value: Value) -> Result<Option<Value>, Error> {
+    let mut map = BTreeMap::new();
+    map.insert(Key::from(variant), value);
+    Ok(Some(Value::Dictionary(map)))
+}
+
+impl ser::Serializer for Serializer {
+    type Ok = Option<Value>;
+    type Error = Error;
+    type SerializeSeq = SerializeList;
+    type SerializeTuple = SerializeList;
+    type SerializeTupleStruct = SerializeList;
+    type SerializeTupleVariant = SerializeList;
+    type SerializeMap = SerializeDict;
+    type SerializeStruct = SerializeDict;
+    type SerializeStructVariant = SerializeDict;
+
+    fn serialize_bool(self, v: bool) -> Result<Self::Ok, Error> {
+        integer(v as i64)
+    }
+    fn serialize_i8(self, v: i8) -> Result<Self::Ok, Error> {
+        integer(v)
+    }
+    fn serialize_i16(self, v: i16) -> Result<Self::Ok, Error> {
+        integer(v)
+    }
+    fn serialize_i32(self, v: i32) -> Result<Self::Ok, Error> {
+        integer(v)
+    }
+    fn serialize_i64(self, v: i64) -> Result<Self::Ok, Error> {
+        integer(v)
+    }
+    fn serialize_i128(self, v: i128) -> Result<Self::Ok, Error> {
+        integer(v)
+    }
+    fn serialize_u8(self, v: u8) -> Result<Self::Ok, Error> {
+        integer(v)
+    }
+    fn serialize_u16(self, v: u16) -> Result<Self::Ok, Error> {
+        integer(v)
+    }
+    fn serialize_u32(self, v: u32) -> Result<Self::Ok, Error> {
+        integer(v)
+    }
+    fn serialize_u64(self, v: u64) -> Result<Self::Ok, Error> {
+        integer(v)
+    }
+    fn serialize_u128(self, v: u128) -> Result<Self::Ok, Error> {
+        integer(v)
+    }
+    fn serialize_f32(self, _: f32) -> Result<Self::Ok, Error> {
+        Err(ser::Error::custom("bencode has no floating point numbers"))
+    }
+    fn serialize_f64(self, _: f64) -> Result<Self::Ok, Error> {
+        Err(ser::Error::custom("bencode has no floating point numbers"))
+    }
+    fn serialize_char(self, v: char) -> Result<Self::Ok, Error> {
+        bytes(v.encode_utf8(&mut [0; 4]).as_bytes())
+    }
+    fn serialize_str(self, v: &str) -> Result<Self::Ok, Error> {
+        bytes(v.as_bytes())
+    }
+    fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, Error> {
+        bytes(v)
+    }
+    fn serialize_none(self) -> Result<Self::Ok, Error> {
+        Ok(None)
+    }
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Self::Ok, Error> {
+        value.serialize(self)
+    }
+    fn serialize_unit(self) -> Result<Self::Ok, Error> {
+        Ok(None)
+    }
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok, Error> {
+        Ok(None)
+    }
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<Self::Ok, Error> {
+        bytes(variant.as_bytes())
+    }
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Error> {
+        value.serialize(self)
+    }
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Error> {
+        tagged(variant, to_value(value)?)
+    }
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, Error> {
+        Ok(SerializeList {
+            list: Vec::with_capacity(len.unwrap_or(0)),
+            variant: None,
+        })
+    }
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Error> {
+        self.serialize_seq(Some(len))
+    }
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Error> {
+        self.serialize_seq(Some(len))
+    }
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Error> {
+        Ok(SerializeList {
+            list: Vec::with_capacity(len),
+            variant: Some(variant),
+        })
+    }
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Error> {
+        Ok(SerializeDict {
+            map: BTreeMap::new(),
+            key: None,
+            variant: None,
+        })
+    }
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStruct, Error> {
+        self.serialize_map(Some(len))
+    }
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Error> {
+        Ok(SerializeDict {
+            map: BTreeMap::new(),
+            key: None,
+            variant: Some(variant),
+        })
+    }
+}
+
+pub struct SerializeList {
+    list: Vec<Value>,
+    variant: Option<&'static str>,
+}
+
+impl SerializeList {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.list.push(to_value(value)?);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Option<Value>, Error> {
+        match self.variant {
+            Some(variant) => tagged(variant, Value::List(self.list)),
+            None => Ok(Some(Value::List(self.list))),
+        }
+    }
+}
+
+impl ser::SerializeSeq for SerializeList {
+    type Ok = Option<Value>;
+    type Error = Error;
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+    fn end(self) -> Result<Self::Ok, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for SerializeList {
+    type Ok = Option<Value>;
+    type Error = Error;
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+    fn end(self) -> Result<Self::Ok, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeList {
+    type Ok = Option<Value>;
+    type Error = Error;
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+    fn end(self) -> Result<Self::Ok, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleVariant for SerializeList {
+    type Ok = Option<Value>;
+    type Error = Error;
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+    fn end(self) -> Result<Self::Ok, Error> {
+        self.finish()
+    }
+}
+
+pub struct SerializeDict {
+    map: BTreeMap<Key, Value>,
+    key: Option<Vec<u8>>,
+    variant: Option<&'static str>,
+}
+
+impl SerializeDict {
+    fn insert<T: Serialize + ?Sized>(&mut self, key: Vec<u8>, value: &T) -> Result<(), Error> {
+        if let Some(value) = value.serialize(Serializer)? {
+            self.map.insert(Key::from(key), value);
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Option<Value>, Error> {
+        match self.variant {
+            Some(variant) => tagged(variant, Value::Dictionary(self.map)),
+            None => Ok(Some(Value::Dictionary(self.map))),
+        }
+    }
+}
+
+impl ser::SerializeMap for SerializeDict {
+    type Ok = Option<Value>;
+    type Error = Error;
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        match to_value(key)? {
+            Value::ByteString(key) => {
+                self.key = Some(key);
+                Ok(())
+            }
+            _ => Err(ser::Error::custom("dictionary keys must be byte strings")),
+        }
+    }
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| Error::Custom("serialize_value called before serialize_key".into()))?;
+        self.insert(key, value)
+    }
+    fn end(self) -> Result<Self::Ok, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStruct for SerializeDict {
+    type Ok = Option<Value>;
+    type Error = Error;
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.insert(key.as_bytes().to_vec(), value)
+    }
+    fn end(self) -> Result<Self::Ok, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStructVariant for SerializeDict {
+    type Ok = Option<Value>;
+    type Error = Error;
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.insert(key.as_bytes().to_vec(), value)
+    }
+    fn end(self) -> Result<Self::Ok, Error> {
+        self.finish()
+    }
+}
+
+/// Makes serializers see a byte slice as bytes rather than a sequence.
+struct RawBytes<'a>(&'a [u8]);
+
+impl Serialize for RawBytes<'_> {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(self.0)
+    }
+}
+
+impl Serialize for Value {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use ser::{SerializeMap, SerializeSeq};
+        match self {
+            Value::ByteString(bytes) => serializer.serialize_bytes(bytes),
+            Value::Integer(int) => serializer.serialize_i64(*int),
+            Value::BigInteger(digits) => match (digits.parse(), digits.parse()) {
+                (Ok(int), _) => serializer.serialize_i128(int),
+                (_, Ok(int)) => serializer.serialize_u128(int),
+                _ => Err(ser::Error::custom("integer out of range")),
+            },
+            Value::RawInteger(_) => match (self.as_integer(), self.as_u64()) {
+                (Some(int), _) => serializer.serialize_i64(int),
+                (_, Some(int)) => serializer.serialize_u64(int),
+                _ => Err(ser::Error::custom("integer out of range")),
+            },
+            Value::List(list) => {
+                let mut seq = serializer.serialize_seq(Some(list.len()))?;
+                for item in list {
+                    seq.serialize_element(item)?;
+                }
+                seq.end()
+            }
+            Value::Dictionary(dict) => {
+                let mut map = serializer.serialize_map(Some(dict.len()))?;
+                for (key, value) in dict {
+                    map.serialize_entry(&RawBytes(key), value)?;
+                }
+                map.end()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::de::from_bytes;
+    use serde::{Deserialize, Serialize};
+    use std::collections::HashMap;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    enum Kind {
+        Plain,
+        Sized(u32),
+        Pair(i8, i8),
+        Named { id: u8 },
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Message {
+        name: String,
+        #[serde(with = "serde_bytes")]
+        raw: Vec<u8>,
+        count: u16,
+        ok: bool,
+        missing: Option<i64>,
+        kinds: Vec<Kind>,
+        extra: HashMap<String, (i64, String)>,
+    }
+
+    #[test]
+    fn roundtrip() {
+        let mut extra = HashMap::new();
+        extra.insert("x".to_string(), (-1, "y".to_string()));
+        extra.insert("a".to_string(), (2, "b".to_string()));
+        let message = Message {
+            name: "spam".to_string(),
+            raw: vec![0, 255],
+            count: 7,
+            ok: true,
+            missing: None,
+            kinds: vec![
+                Kind::Plain,
+                Kind::Sized(3),
+                Kind::Pair(1, 2),
+                Kind::Named { id: 4 },
+            ],
+            extra,
+        };
+        let bytes = to_bytes(&message).unwrap();
+        assert_eq!(
+            bytes,
+            b"d5:counti7e5:extrad1:ali2e1:be1:xli-1e1:yee5:kindsl5:Plaind5:Sizedi3eed4:Pairli1ei2eeed5:Namedd2:idi4eeee4:name4:spam2:oki1e3:raw2:\x00\xffe"
+        );
+        assert_eq!(from_bytes::<Message>(&bytes).unwrap(), message);
+    }
+
+    #[test]
+    fn value() {
+        let value =
+            crate::from_bytes(b"d1:ali1e2:xye1:bd0:i0ee1:ci-10000000000000000000ee").unwrap();
+        assert_eq!(to_value(&value).unwrap(), value);
+    }
+
+    #[test]
+    fn errors() {
+        assert!(matches!(to_bytes(&1.5), Err(Error::Custom(_))));
+        assert!(matches!(to_bytes(&None::<i64>), Err(Error::Custom(_))));
+        assert_eq!(to_bytes(&u64::MAX).unwrap(), b"i18446744073709551615e");
+        let huge = crate::from_bytes(b"i999999999999999999999999999999999999999e").unwrap();
+        assert!(matches!(to_bytes(&huge), Err(Error::Custom(_))));
+        let mut map = HashMap::new();
+        map.insert(1, 2);
+        assert!(matches!(to_bytes(&map), Err(Error::Custom(_))));
+    }
+}
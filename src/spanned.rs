@@ -0,0 +1,191 @@
+use crate::io::Read;
+use crate::{build, Error, Key, Parser, Tree, Value};
+use alloc::{collections::BTreeMap, string::String, vec::Vec};
+use core::ops::Range;
+
+/// A decoded value along with the range of input bytes it was decoded from,
+/// as produced by `decode_spanned`.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Spanned {
+    /// Offset of the first byte of the value.
+    pub start: usize,
+    /// Offset just past the last byte of the value.
+    pub end: usize,
+    pub value: SpannedValue,
+}
+
+/// Like `Value`, but every child carries its own span.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum SpannedValue {
+    ByteString(Vec<u8>),
+    Integer(i64),
+    BigInteger(String),
+    /// See `Value::RawInteger`.
+    RawInteger(String),
+    List(Vec<Spanned>),
+    Dictionary(BTreeMap<Vec<u8>, Spanned>),
+}
+
+impl Spanned {
+    /// The input range this value was decoded from, for slicing the original
+    /// buffer.
+    pub fn span(&self) -> Range<usize> {
+        self.start..self.end
+    }
+
+    /// Looks up `key` if this is a `Dictionary`.
+    pub fn get(&self, key: impl AsRef<[u8]>) -> Option<&Spanned> {
+        match &self.value {
+            SpannedValue::Dictionary(map) => map.get(key.as_ref()),
+            _ => None,
+        }
+    }
+
+    /// Strips the spans, returning a plain `Value`.
+    pub fn to_value(&self) -> Value {
+        match &self.value {
+            SpannedValue::ByteString(bytes) => Value::ByteString(bytes.clone()),
+            SpannedValue::Integer(int) => Value::Integer(*int),
+            SpannedValue::BigInteger(digits) => Value::BigInteger(digits.clone()),
+            SpannedValue::RawInteger(digits) => Value::RawInteger(digits.clone()),
+            SpannedValue::List(list) => Value::List(list.iter().map(Spanned::to_value).collect()),
+            SpannedValue::Dictionary(map) => Value::Dictionary(
+                map.iter()
+                    .map(|(key, value)| (Key::from(key.clone()), value.to_value()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// Like `decode`, but records for every value the byte range of the input it
+/// came from. Slicing the input with these recovers the original bytes of
+/// any subtree, even where re-encoding it wouldn't because the input wasn't
+/// canonical.
+///
+/// ```
+/// use bencode_decode::{decode_spanned, Parser};
+///
+/// let input = b"d4:infod1:bi1e1:ai2eee";
+/// let value = decode_spanned(&mut Parser::new(&input[..])).unwrap();
+/// let info = value.get("info").unwrap();
+/// assert_eq!(&input[info.span()], b"d1:bi1e1:ai2ee");
+/// ```
+pub fn decode_spanned<R: Read>(parser: &mut Parser<R>) -> Result<Spanned, Error> {
+    build(parser, Spans)
+}
+
+/// The `Tree` of `Spanned` values.
+struct Spans;
+
+impl Tree for Spans {
+    type Node = Spanned;
+
+    fn scalar(&mut self, value: Value, span: Range<usize>) -> Spanned {
+        let value = match value {
+            Value::ByteString(bytes) => SpannedValue::ByteString(bytes),
+            Value::Integer(int) => SpannedValue::Integer(int),
+            Value::BigInteger(digits) => SpannedValue::BigInteger(digits),
+            Value::RawInteger(digits) => SpannedValue::RawInteger(digits),
+            _ => unreachable!("the parser only emits scalars"),
+        };
+        spanned(value, span)
+    }
+
+    fn list(&mut self, items: Vec<Spanned>, span: Range<usize>) -> Spanned {
+        spanned(SpannedValue::List(items), span)
+    }
+
+    fn dict(&mut self, entries: Vec<(usize, Vec<u8>, Spanned)>, span: Range<usize>) -> Spanned {
+        let map = entries
+            .into_iter()
+            .map(|(_, key, value)| (key, value))
+            .collect();
+        spanned(SpannedValue::Dictionary(map), span)
+    }
+}
+
+fn spanned(value: SpannedValue, span: Range<usize>) -> Spanned {
+    Spanned {
+        start: span.start,
+        end: span.end,
+        value,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::decode;
+    use std::fs;
+
+    #[test]
+    fn spans() {
+        let input = b"li1e3:abcd1:a0:ee";
+        let value = decode_spanned(&mut Parser::new(&input[..])).unwrap();
+        assert_eq!(value.span(), 0..input.len());
+        match &value.value {
+            SpannedValue::List(list) => {
+                let spans: Vec<_> = list.iter().map(Spanned::span).collect();
+                assert_eq!(spans, [1..4, 4..9, 9..16]);
+                assert_eq!(list[2].get("a").unwrap().span(), 13..15);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn torrent() {
+        let raw = fs::read("./test/ubuntu-18.04.4-live-server-amd64.iso.torrent").unwrap();
+        let spanned = decode_spanned(&mut Parser::new(raw.as_slice())).unwrap();
+        let owned = decode(&mut Parser::new(raw.as_slice())).unwrap();
+        assert_eq!(spanned.to_value(), owned);
+        let info = spanned.get("info").unwrap();
+        assert_eq!(raw[info.span()], owned["info"].to_bytes()[..]);
+    }
+
+    #[test]
+    fn malformed() {
+        let decode = |input: &str| decode_spanned(&mut Parser::new(input.as_bytes()));
+        assert!(matches!(
+            decode("l"),
+            Err(Error::UnexpectedEof { offset: 1 })
+        ));
+        assert!(matches!(
+            decode("e"),
+            Err(Error::UnexpectedEnd { offset: 0 })
+        ));
+        assert!(matches!(
+            decode("di1ei2ee"),
+            Err(Error::NonStringKey { offset: 1 })
+        ));
+        assert!(matches!(
+            decode("d1:ae"),
+            Err(Error::OddDictLength { offset: 4 })
+        ));
+        let mut parser = Parser::with_max_depth("lllee".as_bytes(), 2);
+        assert!(matches!(
+            decode_spanned(&mut parser),
+            Err(Error::DepthLimitExceeded { offset: 2 })
+        ));
+    }
+
+    #[test]
+    fn strict() {
+        let decode = |input: &str, strict: bool| {
+            let mut parser = Parser::new(input.as_bytes());
+            parser.set_strict(strict);
+            decode_spanned(&mut parser)
+        };
+        let value = decode("d1:bi1e1:ai2e1:bi3ee", false).unwrap();
+        assert_eq!(value.get("b").unwrap().span(), 16..19);
+        assert!(matches!(
+            decode("d1:bi1e1:ai2ee", true),
+            Err(Error::UnsortedKeys { offset: 7 })
+        ));
+        assert!(matches!(
+            decode("d1:ai1e1:ai2ee", true),
+            Err(Error::DuplicateKey { offset: 7 })
+        ));
+    }
+}
@@ -0,0 +1,381 @@
+use crate::{
+    canonical_integer, check_length, integer_byte, parse_integer, token, Error, Event, ParseResult,
+    Value, I64_LEN, PREALLOC_LIMIT,
+};
+use alloc::{string::String, vec, vec::Vec};
+use core::mem;
+
+/// Where the tokenizer is within the current token.
+enum State {
+    /// Between tokens.
+    Start,
+    /// Reading the length prefix of a byte string.
+    Length(u64),
+    /// Collecting the contents of a byte string, only for `push`.
+    Payload { data: Vec<u8>, remaining: usize },
+    /// Collecting the digits of an integer into `digits`.
+    Integer,
+}
+
+/// The grammar of bencode, as a state machine that is fed one byte at a
+/// time instead of reading from an `io::Read`. It suits input that arrives
+/// in pieces outside of the caller's control, like from an event loop, a
+/// ring buffer or a WASM host, and never blocks.
+///
+/// `Parser`, `decode_slice` and `AsyncParser` drive a `Tokenizer` as well,
+/// so they all accept the same input and fail with the same errors. Each
+/// reads the contents of byte strings by itself though, in bulk.
+///
+/// ```
+/// use bencode_decode::{ParseResult, Tokenizer, Value};
+///
+/// let mut tokenizer = Tokenizer::new();
+/// let mut tokens = vec![];
+/// for chunk in [&b"li4"[..], b"2e4:sp", b"ame"] {
+///     for &byte in chunk {
+///         tokens.extend(tokenizer.push(byte).unwrap());
+///     }
+/// }
+/// assert_eq!(tokenizer.finish().unwrap(), ParseResult::EOF);
+/// assert_eq!(
+///     tokens,
+///     [
+///         ParseResult::ListStart,
+///         ParseResult::ValueType(Value::Integer(42)),
+///         ParseResult::ValueType(Value::ByteString(b"spam".to_vec())),
+///         ParseResult::End,
+///     ]
+/// );
+/// ```
+pub struct Tokenizer {
+    state: State,
+    digits: Vec<u8>,
+    max_byte_string_len: usize,
+    big_integers: bool,
+    raw_integers: bool,
+    /// With a list, integers with leading zeros or a negative zero are
+    /// accepted in their canonical form, and their offsets recorded.
+    pub(crate) noncanonical: Option<Vec<usize>>,
+    pos: usize,
+    token_start: usize,
+}
+
+impl Default for Tokenizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Tokenizer {
+    pub fn new() -> Self {
+        Self {
+            state: State::Start,
+            digits: Vec::new(),
+            max_byte_string_len: usize::MAX,
+            big_integers: true,
+            raw_integers: false,
+            noncanonical: None,
+            pos: 0,
+            token_start: 0,
+        }
+    }
+
+    /// See `Parser::set_max_byte_string_len`.
+    pub fn set_max_byte_string_len(&mut self, max_len: usize) {
+        self.max_byte_string_len = max_len;
+    }
+
+    /// See `Parser::set_big_integers`.
+    pub fn set_big_integers(&mut self, allow: bool) {
+        self.big_integers = allow;
+    }
+
+    /// See `Parser::set_raw_integers`. Unlike there, this also applies to
+    /// strict parsers, as a tokenizer doesn't know whether it is in one.
+    pub fn set_raw_integers(&mut self, allow: bool) {
+        self.raw_integers = allow;
+    }
+
+    /// The number of bytes pushed so far.
+    pub fn position(&self) -> u64 {
+        self.pos as u64
+    }
+
+    /// Feeds the next byte of input, returning the token it completes, if
+    /// any. Errors carry offsets into the input as with `Parser`, and leave
+    /// the tokenizer at the start of a new token.
+    pub fn push(&mut self, byte: u8) -> Result<Option<ParseResult>, Error> {
+        let offset = self.pos;
+        self.pos += 1;
+        if let State::Payload { data, remaining } = &mut self.state {
+            data.push(byte);
+            *remaining -= 1;
+            if *remaining > 0 {
+                return Ok(None);
+            }
+            let data = mem::take(data);
+            self.state = State::Start;
+            return Ok(Some(ParseResult::ValueType(Value::ByteString(data))));
+        }
+        Ok(match self.event(byte, offset)? {
+            Some(Event::ByteString(0)) => Some(ParseResult::ValueType(Value::ByteString(vec![]))),
+            Some(Event::ByteString(size)) => {
+                self.state = State::Payload {
+                    data: Vec::with_capacity(size.min(PREALLOC_LIMIT)),
+                    remaining: size,
+                };
+                None
+            }
+            event => event.map(token),
+        })
+    }
+
+    /// Signals the end of the input, returning `ParseResult::EOF` if it
+    /// ended between tokens, and an error if it ended inside one.
+    pub fn finish(&mut self) -> Result<ParseResult, Error> {
+        if let State::Payload { data, remaining } = &self.state {
+            let error = Error::TruncatedByteString {
+                declared: data.len() + remaining,
+                available: data.len(),
+                offset: self.pos,
+            };
+            self.discard();
+            return Err(error);
+        }
+        self.end(self.pos).map(token)
+    }
+
+    /// Feeds `byte`, found at `offset` in the input, returning the event it
+    /// completes, if any. A byte string is done once its `:` is read, and its
+    /// contents are left to the caller. On errors, the token is dropped.
+    pub(crate) fn event(&mut self, byte: u8, offset: usize) -> Result<Option<Event>, Error> {
+        let result = self.step(byte, offset);
+        if result.is_err() {
+            self.discard();
+        }
+        result
+    }
+
+    /// Signals the end of the input at `offset`, returning `Event::Eof` if it
+    /// ended between tokens, and an error if it ended inside one.
+    pub(crate) fn end(&mut self, offset: usize) -> Result<Event, Error> {
+        match mem::replace(&mut self.state, State::Start) {
+            State::Start => Ok(Event::Eof),
+            State::Length(_) | State::Payload { .. } => Err(Error::UnexpectedEof { offset }),
+            State::Integer => Err(Error::UnterminatedInteger {
+                start: self.token_start,
+                offset,
+            }),
+        }
+    }
+
+    /// Drops the token read so far, if any, so the next byte starts a new
+    /// one. Drivers call this before each token, in case the last one was
+    /// cut short by an I/O error.
+    pub(crate) fn discard(&mut self) {
+        self.state = State::Start;
+    }
+
+    fn step(&mut self, byte: u8, offset: usize) -> Result<Option<Event>, Error> {
+        let start = self.token_start;
+        match &mut self.state {
+            State::Start => {
+                self.token_start = offset;
+                match byte {
+                    b'0'..=b'9' => self.state = State::Length(u64::from(byte - b'0')),
+                    b'i' => {
+                        self.digits.clear();
+                        self.state = State::Integer;
+                    }
+                    b'l' => return Ok(Some(Event::ListStart)),
+                    b'd' => return Ok(Some(Event::DictStart)),
+                    b'e' => return Ok(Some(Event::End)),
+                    byte => return Err(Error::InvalidToken { byte, offset }),
+                }
+            }
+            // The length is accumulated rather than collected, so an endless
+            // run of digits fails once it overflows.
+            State::Length(len) => match byte {
+                b'0'..=b'9' => {
+                    *len = len
+                        .checked_mul(10)
+                        .and_then(|len| len.checked_add(u64::from(byte - b'0')))
+                        .ok_or(Error::InvalidLength { offset: start })?;
+                }
+                b':' => {
+                    let size = check_length(*len, self.max_byte_string_len, start)?;
+                    self.state = State::Start;
+                    return Ok(Some(Event::ByteString(size)));
+                }
+                _ => return Err(Error::InvalidLength { offset: start }),
+            },
+            State::Payload { .. } => unreachable!("only `push` reads byte strings"),
+            State::Integer if byte == b'e' => {
+                self.state = State::Start;
+                return self.integer(start).map(Some);
+            }
+            State::Integer if !integer_byte(byte, self.digits.len()) => {
+                return Err(Error::InvalidInteger { offset: start })
+            }
+            State::Integer => {
+                if !self.big_integers && self.digits.len() == I64_LEN {
+                    self.check_overflow(start)?;
+                }
+                self.digits.push(byte);
+            }
+        }
+        Ok(None)
+    }
+
+    /// Called with as many digits as an `i64` can have before another one
+    /// is read, and without big integers: the integer starting at `start`
+    /// can't fit, unless it is a non-canonical one that is accepted anyway.
+    fn check_overflow(&self, start: usize) -> Result<(), Error> {
+        let lenient = self.raw_integers || self.noncanonical.is_some();
+        match parse_integer(&self.digits, start) {
+            Ok(_) => Err(Error::IntegerOverflow {
+                digits: String::from_utf8_lossy(&self.digits).into_owned(),
+                offset: start,
+            }),
+            Err(e) if !lenient => Err(e),
+            Err(_) => Ok(()),
+        }
+    }
+
+    /// Parses the integer starting at `start` from the digits read.
+    fn integer(&mut self, start: usize) -> Result<Event, Error> {
+        let digits = &self.digits;
+        let event = match (parse_integer(digits, start), &mut self.noncanonical) {
+            (Ok(int), _) => int.into(),
+            (Err(e), Some(offsets)) => match canonical_integer(digits) {
+                Some(canonical) => {
+                    offsets.push(start);
+                    parse_integer(&canonical, start)?.into()
+                }
+                None => return Err(e),
+            },
+            (Err(_), None) if self.raw_integers && canonical_integer(digits).is_some() => {
+                // Only ASCII digits and a `-` at this point.
+                Event::RawInteger(String::from_utf8_lossy(digits).into_owned())
+            }
+            (Err(e), None) => return Err(e),
+        };
+        match event {
+            Event::BigInteger(digits) if !self.big_integers => Err(Error::IntegerOverflow {
+                digits,
+                offset: start,
+            }),
+            event => Ok(event),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Parser;
+    use std::fs;
+
+    fn tokenize(input: &[u8]) -> Result<Vec<ParseResult>, Error> {
+        let mut tokenizer = Tokenizer::new();
+        let mut tokens = vec![];
+        for &byte in input {
+            tokens.extend(tokenizer.push(byte)?);
+        }
+        match tokenizer.finish()? {
+            ParseResult::EOF => Ok(tokens),
+            token => panic!("unexpected {:?}", token),
+        }
+    }
+
+    #[test]
+    fn matches_parser() {
+        let raw = fs::read("./test/ubuntu-18.04.4-live-server-amd64.iso.torrent").unwrap();
+        let tokens: Vec<_> = Parser::new(raw.as_slice())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(tokenize(&raw).unwrap(), tokens);
+
+        let input = b"li0ei-18446744073709551616e0:d1:a1:bee";
+        let tokens: Vec<_> = Parser::new(&input[..]).collect::<Result<_, _>>().unwrap();
+        assert_eq!(tokenize(input).unwrap(), tokens);
+    }
+
+    #[test]
+    fn malformed() {
+        assert!(matches!(
+            tokenize(b"li01ee"),
+            Err(Error::InvalidInteger { offset: 1 })
+        ));
+        assert!(matches!(
+            tokenize(b"l3x:abc"),
+            Err(Error::InvalidLength { offset: 1 })
+        ));
+        assert!(matches!(
+            tokenize(b"99999999999999999999999:"),
+            Err(Error::InvalidLength { offset: 0 })
+        ));
+        assert!(matches!(
+            tokenize(b"lx"),
+            Err(Error::InvalidToken {
+                byte: b'x',
+                offset: 1
+            })
+        ));
+        assert!(matches!(
+            tokenize(b"i12"),
+            Err(Error::UnterminatedInteger {
+                start: 0,
+                offset: 3
+            })
+        ));
+        assert!(matches!(
+            tokenize(b"5:ab"),
+            Err(Error::TruncatedByteString {
+                declared: 5,
+                available: 2,
+                offset: 4
+            })
+        ));
+
+        let mut tokenizer = Tokenizer::new();
+        tokenizer.set_max_byte_string_len(2);
+        tokenizer.push(b'3').unwrap();
+        assert!(matches!(
+            tokenizer.push(b':'),
+            Err(Error::LengthLimitExceeded { offset: 0 })
+        ));
+        // The next token starts afresh.
+        assert_eq!(tokenizer.push(b'e').unwrap(), Some(ParseResult::End));
+        assert_eq!(tokenizer.position(), 3);
+    }
+
+    #[test]
+    fn integers() {
+        let mut tokenizer = Tokenizer::new();
+        tokenizer.set_big_integers(false);
+        for &byte in b"i-9223372036854775808" {
+            tokenizer.push(byte).unwrap();
+        }
+        // A 21st character can't belong to an `i64` anymore.
+        assert!(matches!(
+            tokenizer.push(b'0'),
+            Err(Error::IntegerOverflow { digits, offset: 0 }) if digits == "-9223372036854775808"
+        ));
+
+        let mut tokenizer = Tokenizer::new();
+        tokenizer.set_raw_integers(true);
+        tokenizer.set_big_integers(false);
+        let tokens: Vec<_> = b"i-07ei0000000000000000000001e"
+            .iter()
+            .filter_map(|&byte| tokenizer.push(byte).unwrap())
+            .collect();
+        assert_eq!(
+            tokens,
+            [
+                ParseResult::ValueType(Value::RawInteger("-07".into())),
+                ParseResult::ValueType(Value::RawInteger("0000000000000000000001".into())),
+            ]
+        );
+    }
+}
@@ -0,0 +1,894 @@
+//! Helpers for BitTorrent metainfo (`.torrent`) files.
+//!
+//! A torrent is identified by the SHA-1 hash of its `info` dictionary as it
+//! appears in the file. Re-encoding a decoded `Value` only reproduces those
+//! bytes if the file was canonical, so the functions here capture them
+//! straight from the input instead.
+//!
+//! ```
+//! use bencode_decode::torrent::info_hash;
+//! use std::fs::File;
+//!
+//! let f = File::open("./test/ubuntu-18.04.4-live-server-amd64.iso.torrent").unwrap();
+//! let hash = info_hash(f).unwrap();
+//! assert_eq!(hash[..4], [0xe7, 0x31, 0x08, 0xcb]);
+//! ```
+//!
+//! BitTorrent v2 torrents (BEP 52), which have a `meta version` of 2, are
+//! identified by the SHA-256 hash of the same bytes instead, see
+//! `info_hash_v2`, and describe their files in a `file tree`, see `v2_files`.
+//! The `files` of a v1 multi-file torrent can be streamed one at a time with
+//! `seek_files` and `files`.
+//!
+//! For creating torrents rather than reading them, see `TorrentBuilder`.
+
+use crate::io::{self, Read};
+use crate::{Error, Event, Key, Parser, Value};
+use alloc::{collections::BTreeMap, string::String, vec, vec::Vec};
+use sha1::{Digest, Sha1};
+use sha2::Sha256;
+
+/// Returns the raw bytes of the `info` dictionary of the metainfo file in
+/// `reader`, exactly as they appear in the input. Reading stops right after
+/// it; nothing but the dictionary itself is kept in memory. Fails with
+/// `Error::MissingInfo` if the input is not a dictionary with an `info`
+/// dictionary.
+pub fn info_bytes<R: Read>(reader: R) -> Result<Vec<u8>, Error> {
+    let mut parser = Parser::new(Recorder {
+        inner: reader,
+        buf: None,
+    });
+    if parser.next_event()? != Event::DictStart {
+        return Err(Error::MissingInfo);
+    }
+    while let Event::ByteString(_) | Event::DictKey(_) = parser.next_event()? {
+        if parser.read_bytes()? != b"info" {
+            parser.skip_value()?;
+            continue;
+        }
+        parser.reader.buf = Some(vec![]);
+        parser.skip_value()?;
+        return match parser.reader.buf.take() {
+            Some(buf) if buf.first() == Some(&b'd') => Ok(buf),
+            _ => Err(Error::MissingInfo),
+        };
+    }
+    Err(Error::MissingInfo)
+}
+
+/// Computes the SHA-1 info-hash of the metainfo file in `reader` from the
+/// bytes returned by `info_bytes`.
+pub fn info_hash<R: Read>(reader: R) -> Result<[u8; 20], Error> {
+    Ok(Sha1::digest(info_bytes(reader)?).into())
+}
+
+/// Computes the SHA-256 info-hash of a v2 or hybrid metainfo file in
+/// `reader` from the bytes returned by `info_bytes`.
+pub fn info_hash_v2<R: Read>(reader: R) -> Result<[u8; 32], Error> {
+    Ok(Sha256::digest(info_bytes(reader)?).into())
+}
+
+/// The outline of a torrent read by `scan_header`. Fields that are missing
+/// or of the wrong type are `None`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TorrentSummary {
+    pub name: Option<Vec<u8>>,
+    pub piece_length: Option<i64>,
+    /// The `length` of a single-file torrent, or the sum of the lengths in
+    /// `files`.
+    pub length: Option<i64>,
+    /// The number of entries in `files`, `0` for a single-file torrent.
+    pub files: usize,
+}
+
+/// Reads just enough of the metainfo file in `reader` to summarize its
+/// `info` dictionary, for sizing up a torrent before decoding it. Byte
+/// strings other than the `name`, like the huge `pieces`, are skipped
+/// without being held in memory, and reading stops right after the `info`
+/// dictionary. Fails with `Error::MissingInfo` like `info_bytes`.
+///
+/// ```
+/// use bencode_decode::torrent::scan_header;
+/// use std::fs::File;
+///
+/// let f = File::open("./test/ubuntu-18.04.4-live-server-amd64.iso.torrent").unwrap();
+/// let summary = scan_header(f).unwrap();
+/// assert_eq!(summary.length, Some(912_261_120));
+/// assert_eq!(summary.files, 0);
+/// ```
+pub fn scan_header<R: Read>(reader: R) -> Result<TorrentSummary, Error> {
+    let mut parser = Parser::new(reader);
+    if parser.next_event()? != Event::DictStart {
+        return Err(Error::MissingInfo);
+    }
+    while let Some(key) = next_key(&mut parser)? {
+        if key != b"info" {
+            parser.skip_value()?;
+            continue;
+        }
+        if parser.next_event()? != Event::DictStart {
+            return Err(Error::MissingInfo);
+        }
+        let mut summary = TorrentSummary::default();
+        while let Some(key) = next_key(&mut parser)? {
+            match (key.as_slice(), parser.next_event()?) {
+                (b"name", Event::ByteString(_)) => summary.name = Some(parser.read_bytes()?),
+                (b"piece length", Event::Integer(len)) => summary.piece_length = Some(len),
+                (b"length", Event::Integer(len)) => summary.length = Some(len),
+                (b"files", Event::ListStart) => {
+                    let mut total = 0i64;
+                    loop {
+                        match parser.next_event()? {
+                            Event::End => break,
+                            Event::DictStart => {
+                                summary.files += 1;
+                                while let Some(key) = next_key(&mut parser)? {
+                                    match (key.as_slice(), parser.next_event()?) {
+                                        (b"length", Event::Integer(len)) => {
+                                            total = total.saturating_add(len)
+                                        }
+                                        (_, event) => skip_rest(&mut parser, event)?,
+                                    }
+                                }
+                            }
+                            event => skip_rest(&mut parser, event)?,
+                        }
+                    }
+                    summary.length = Some(total);
+                }
+                (_, event) => skip_rest(&mut parser, event)?,
+            }
+        }
+        return Ok(summary);
+    }
+    Err(Error::MissingInfo)
+}
+
+/// A file listed in the `files` of a v1 multi-file `info` dictionary, as
+/// yielded by `files`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileEntry {
+    /// The path components below the torrent's `name` directory.
+    pub path: Vec<Vec<u8>>,
+    pub length: i64,
+}
+
+/// Reads the metainfo file in `parser` up to the `files` list of its `info`
+/// dictionary, leaving the list itself to be read, e.g. by `files`. Fails
+/// with `Error::MissingInfo` like `info_bytes`, and with
+/// `Error::InvalidMetainfo` if there is no `files` list, as in a
+/// single-file torrent.
+pub fn seek_files<R: Read>(parser: &mut Parser<R>) -> Result<(), Error> {
+    if parser.next_event()? != Event::DictStart {
+        return Err(Error::MissingInfo);
+    }
+    while let Some(key) = next_key(parser)? {
+        if key != b"info" {
+            parser.skip_value()?;
+            continue;
+        }
+        if parser.next_event()? != Event::DictStart {
+            return Err(Error::MissingInfo);
+        }
+        while let Some(key) = next_key(parser)? {
+            if key == b"files" {
+                return Ok(());
+            }
+            parser.skip_value()?;
+        }
+        return Err(Error::InvalidMetainfo("missing files list"));
+    }
+    Err(Error::MissingInfo)
+}
+
+/// Streams the entries of the `files` list that `parser` is positioned at,
+/// one `FileEntry` at a time, so that torrents with huge numbers of files
+/// never need the whole list in memory. Keys other than `path` and `length`
+/// are skipped. The iterator ends after the list, or after the first error.
+///
+/// ```
+/// use bencode_decode::{torrent::{files, seek_files}, Parser};
+///
+/// let input = b"d4:infod5:filesld6:lengthi3e4:pathl1:a1:beed6:lengthi4e4:pathl1:ceee4:name3:diree";
+/// let mut parser = Parser::new(&input[..]);
+/// seek_files(&mut parser).unwrap();
+/// let lengths: Result<Vec<_>, _> = files(&mut parser).map(|file| file.map(|f| f.length)).collect();
+/// assert_eq!(lengths.unwrap(), [3, 4]);
+/// ```
+pub fn files<R: Read>(
+    parser: &mut Parser<R>,
+) -> impl Iterator<Item = Result<FileEntry, Error>> + '_ {
+    let mut started = false;
+    let mut done = false;
+    core::iter::from_fn(move || {
+        if done {
+            return None;
+        }
+        let result = if started {
+            next_file(parser)
+        } else {
+            started = true;
+            match parser.next_event() {
+                Ok(Event::ListStart) => next_file(parser),
+                Ok(_) => Err(Error::InvalidMetainfo("files must be a list")),
+                Err(e) => Err(e),
+            }
+        };
+        let file = result.transpose();
+        done = !matches!(file, Some(Ok(_)));
+        file
+    })
+}
+
+/// Reads the next entry of a `files` list, or `None` at its end.
+fn next_file<R: Read>(parser: &mut Parser<R>) -> Result<Option<FileEntry>, Error> {
+    match parser.next_event()? {
+        Event::DictStart => {}
+        Event::End => return Ok(None),
+        Event::Eof => {
+            return Err(Error::UnexpectedEof {
+                offset: parser.token_start,
+            })
+        }
+        _ => return Err(Error::InvalidMetainfo("file entries must be dictionaries")),
+    }
+    let (mut path, mut length) = (None, None);
+    while let Some(key) = next_key(parser)? {
+        match (key.as_slice(), parser.next_event()?) {
+            (b"length", Event::Integer(len)) => length = Some(len),
+            (b"path", Event::ListStart) => {
+                let mut components = vec![];
+                loop {
+                    match parser.next_event()? {
+                        Event::ByteString(_) => components.push(parser.read_bytes()?),
+                        Event::End => break,
+                        _ => {
+                            return Err(Error::InvalidMetainfo(
+                                "path components must be byte strings",
+                            ))
+                        }
+                    }
+                }
+                path = Some(components);
+            }
+            (_, event) => skip_rest(parser, event)?,
+        }
+    }
+    match (path, length) {
+        (Some(path), Some(length)) => Ok(Some(FileEntry { path, length })),
+        _ => Err(Error::InvalidMetainfo(
+            "file entries need a length and a path",
+        )),
+    }
+}
+
+/// Reads the next key of a dictionary, or `None` at its end.
+fn next_key<R: Read>(parser: &mut Parser<R>) -> Result<Option<Vec<u8>>, Error> {
+    match parser.next_event()? {
+        Event::ByteString(_) | Event::DictKey(_) => Ok(Some(parser.read_bytes()?)),
+        Event::End => Ok(None),
+        Event::Eof => Err(Error::UnexpectedEof {
+            offset: parser.token_start,
+        }),
+        _ => Err(Error::NonStringKey {
+            offset: parser.token_start,
+        }),
+    }
+}
+
+/// Skips the rest of the value that began with `event`.
+fn skip_rest<R: Read>(parser: &mut Parser<R>, event: Event) -> Result<(), Error> {
+    let mut depth = match event {
+        Event::ListStart | Event::DictStart => 1usize,
+        Event::End => {
+            return Err(Error::OddDictLength {
+                offset: parser.token_start,
+            })
+        }
+        Event::Eof => {
+            return Err(Error::UnexpectedEof {
+                offset: parser.token_start,
+            })
+        }
+        _ => return Ok(()),
+    };
+    while depth > 0 {
+        match parser.next_event()? {
+            Event::ListStart | Event::DictStart => depth += 1,
+            Event::End => depth -= 1,
+            Event::Eof => {
+                return Err(Error::UnexpectedEof {
+                    offset: parser.token_start,
+                })
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Checks the sizes in a metainfo file, or just its `info` dictionary, as
+/// `TorrentBuilder` does: the `piece length` has to be positive, and the
+/// `length` of the torrent or of each file in `files` must not be negative.
+/// Decoding accepts any `i64` for these, so this catches corrupt metadata
+/// before a bogus size is used. Missing fields are not an error here.
+///
+/// ```
+/// use bencode_decode::{from_bytes, torrent::check_sizes, Error};
+///
+/// let torrent = from_bytes(b"d4:infod6:lengthi-5e4:name1:xee").unwrap();
+/// assert!(matches!(
+///     check_sizes(&torrent),
+///     Err(Error::InvalidMetainfo("length must not be negative"))
+/// ));
+/// ```
+pub fn check_sizes(metainfo: &Value) -> Result<(), Error> {
+    let info = metainfo.get("info").unwrap_or(metainfo);
+    let negative =
+        |field: Option<&Value>| matches!(field.and_then(Value::as_integer), Some(len) if len < 0);
+    if matches!(info.get("piece length").and_then(Value::as_integer), Some(len) if len <= 0) {
+        return Err(Error::InvalidMetainfo("piece length must be positive"));
+    }
+    if negative(info.get("length")) {
+        return Err(Error::InvalidMetainfo("length must not be negative"));
+    }
+    let mut files = info.get("files").into_iter().flat_map(Value::iter);
+    if files.any(|file| negative(file.get("length"))) {
+        return Err(Error::InvalidMetainfo("file lengths must not be negative"));
+    }
+    Ok(())
+}
+
+/// A file listed in the `file tree` of a v2 `info` dictionary.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct V2File<'a> {
+    /// The path components below the torrent's `name` directory.
+    pub path: Vec<&'a [u8]>,
+    pub length: i64,
+    /// The root hash of the file's merkle tree, absent for empty files.
+    pub pieces_root: Option<&'a [u8]>,
+}
+
+/// Lists the files in the `file tree` of the v2 `info` dictionary `info`,
+/// sorted by path. `None` if there is no `file tree`, or it is malformed.
+///
+/// ```
+/// use bencode_decode::{from_bytes, torrent::v2_files};
+///
+/// let info = from_bytes(b"d9:file treed3:dird1:ad0:d6:lengthi0eeeee12:meta versioni2ee").unwrap();
+/// assert_eq!(info.meta_version(), Some(2));
+/// let files = v2_files(&info).unwrap();
+/// assert_eq!(files[0].path, [&b"dir"[..], b"a"]);
+/// ```
+pub fn v2_files(info: &Value) -> Option<Vec<V2File<'_>>> {
+    let mut files = vec![];
+    // Directories still to be listed, along with their paths.
+    let mut stack = vec![(vec![], info.get("file tree")?.as_dict()?)];
+    while let Some((path, dir)) = stack.pop() {
+        // Reverse order, so that popping the stack visits entries sorted.
+        for (name, entry) in dir.iter().rev() {
+            let entry = entry.as_dict()?;
+            let mut path = path.clone();
+            path.push(name.as_bytes());
+            match entry.get(&b""[..]) {
+                Some(file) if entry.len() == 1 => files.push(V2File {
+                    path,
+                    length: file.get("length")?.as_integer()?,
+                    pieces_root: file.get("pieces root").and_then(Value::as_bytes),
+                }),
+                Some(_) => return None,
+                None => stack.push((path, entry)),
+            }
+        }
+    }
+    Some(files)
+}
+
+/// Assembles the metainfo of a new torrent. `build` checks that the required
+/// fields are present and consistent, and returns a `Value` whose `to_bytes`
+/// is the canonical encoding of the file.
+///
+/// ```
+/// use bencode_decode::torrent::{info_hash, TorrentBuilder};
+///
+/// let torrent = TorrentBuilder::new()
+///     .name("spam.txt")
+///     .piece_length(16384)
+///     .pieces(vec![0; 20])
+///     .length(4)
+///     .announce("http://tracker.example/announce")
+///     .creation_date(1_600_000_000)
+///     .build()
+///     .unwrap();
+/// assert_eq!(torrent["info"]["name"], "spam.txt");
+/// assert!(info_hash(torrent.to_bytes().as_slice()).is_ok());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct TorrentBuilder {
+    name: Option<String>,
+    piece_length: Option<i64>,
+    pieces: Option<Vec<u8>>,
+    length: Option<i64>,
+    files: Vec<(Vec<String>, i64)>,
+    announce: Option<String>,
+    announce_list: Vec<Vec<String>>,
+    creation_date: Option<i64>,
+}
+
+impl TorrentBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The file name of a single-file torrent, or the directory name of a
+    /// multi-file one.
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    pub fn piece_length(mut self, piece_length: i64) -> Self {
+        self.piece_length = Some(piece_length);
+        self
+    }
+
+    /// The concatenated 20-byte SHA-1 hashes of all pieces.
+    pub fn pieces(mut self, pieces: Vec<u8>) -> Self {
+        self.pieces = Some(pieces);
+        self
+    }
+
+    /// Makes this a single-file torrent of `length` bytes.
+    pub fn length(mut self, length: i64) -> Self {
+        self.length = Some(length);
+        self
+    }
+
+    /// Adds a file of a multi-file torrent, at `path` below the `name`
+    /// directory.
+    pub fn file<S: Into<String>>(mut self, path: impl IntoIterator<Item = S>, length: i64) -> Self {
+        self.files
+            .push((path.into_iter().map(Into::into).collect(), length));
+        self
+    }
+
+    pub fn announce(mut self, url: impl Into<String>) -> Self {
+        self.announce = Some(url.into());
+        self
+    }
+
+    /// Adds a tier of tracker URLs to the `announce-list`.
+    pub fn announce_tier<S: Into<String>>(mut self, urls: impl IntoIterator<Item = S>) -> Self {
+        self.announce_list
+            .push(urls.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Seconds since the Unix epoch.
+    pub fn creation_date(mut self, timestamp: i64) -> Self {
+        self.creation_date = Some(timestamp);
+        self
+    }
+
+    /// Builds the `info` dictionary. Fails with `Error::InvalidMetainfo` if
+    /// the name, piece length or pieces are missing or malformed, or unless
+    /// exactly one of `length` and `file` was used.
+    pub fn info(&self) -> Result<Value, Error> {
+        let invalid = |reason| Err(Error::InvalidMetainfo(reason));
+        let mut info = BTreeMap::new();
+        match &self.name {
+            Some(name) if !name.is_empty() => info.insert(key("name"), name.as_str().into()),
+            _ => return invalid("missing name"),
+        };
+        match self.piece_length {
+            Some(len) if len > 0 => info.insert(key("piece length"), len.into()),
+            Some(_) => return invalid("piece length must be positive"),
+            None => return invalid("missing piece length"),
+        };
+        match &self.pieces {
+            Some(pieces) if pieces.len() % 20 == 0 => {
+                info.insert(key("pieces"), pieces.clone().into())
+            }
+            Some(_) => return invalid("pieces must be a multiple of 20 bytes"),
+            None => return invalid("missing pieces"),
+        };
+        match (self.length, self.files.is_empty()) {
+            (Some(len), true) if len >= 0 => info.insert(key("length"), len.into()),
+            (None, false) => {
+                let mut files = vec![];
+                for (path, len) in &self.files {
+                    if path.is_empty() || path.iter().any(String::is_empty) {
+                        return invalid("file paths must not be empty");
+                    }
+                    if *len < 0 {
+                        return invalid("file lengths must not be negative");
+                    }
+                    let mut file = BTreeMap::new();
+                    file.insert(key("length"), (*len).into());
+                    let path = path.iter().map(|part| part.as_str().into()).collect();
+                    file.insert(key("path"), Value::List(path));
+                    files.push(Value::Dictionary(file));
+                }
+                info.insert(key("files"), Value::List(files))
+            }
+            (Some(_), true) => return invalid("length must not be negative"),
+            (None, true) => return invalid("missing length or files"),
+            (Some(_), false) => return invalid("both length and files are set"),
+        };
+        Ok(Value::Dictionary(info))
+    }
+
+    /// Builds the complete metainfo dictionary. See `info`.
+    pub fn build(&self) -> Result<Value, Error> {
+        let mut torrent = BTreeMap::new();
+        torrent.insert(key("info"), self.info()?);
+        if let Some(announce) = &self.announce {
+            torrent.insert(key("announce"), announce.as_str().into());
+        }
+        if !self.announce_list.is_empty() {
+            let tiers = self
+                .announce_list
+                .iter()
+                .map(|tier| Value::List(tier.iter().map(|url| url.as_str().into()).collect()))
+                .collect();
+            torrent.insert(key("announce-list"), Value::List(tiers));
+        }
+        if let Some(date) = self.creation_date {
+            torrent.insert(key("creation date"), date.into());
+        }
+        Ok(Value::Dictionary(torrent))
+    }
+}
+
+fn key(name: &str) -> Key {
+    Key::from(name)
+}
+
+impl Value {
+    /// Computes the SHA-1 info-hash of a decoded metainfo file by re-encoding
+    /// its `info` dictionary. `None` unless this is a dictionary with an
+    /// `info` dictionary.
+    ///
+    /// This only matches the torrent's actual info-hash if the `info`
+    /// dictionary in the original file was canonical; keys out of order, for
+    /// example, are sorted by decoding. When the input is still at hand,
+    /// prefer `info_hash`, which hashes the original bytes.
+    ///
+    /// ```
+    /// use bencode_decode::{from_bytes, torrent::info_hash};
+    ///
+    /// let raw = std::fs::read("./test/ubuntu-18.04.4-live-server-amd64.iso.torrent").unwrap();
+    /// let torrent = from_bytes(&raw).unwrap();
+    /// assert_eq!(torrent.info_hash(), Some(info_hash(raw.as_slice()).unwrap()));
+    /// ```
+    pub fn info_hash(&self) -> Option<[u8; 20]> {
+        match self.get("info")? {
+            info @ Value::Dictionary(_) => Some(Sha1::digest(info.to_bytes()).into()),
+            _ => None,
+        }
+    }
+
+    /// Like `Value::info_hash`, but computes the SHA-256 info-hash of a v2
+    /// torrent. The same caveat about non-canonical input applies; prefer
+    /// `info_hash_v2` on the original bytes.
+    pub fn info_hash_v2(&self) -> Option<[u8; 32]> {
+        match self.get("info")? {
+            info @ Value::Dictionary(_) => Some(Sha256::digest(info.to_bytes()).into()),
+            _ => None,
+        }
+    }
+
+    /// The `meta version` of an `info` dictionary, or of the `info`
+    /// dictionary of a metainfo file: 2 for v2 and hybrid torrents. `None`
+    /// for v1 torrents, which don't have one.
+    pub fn meta_version(&self) -> Option<i64> {
+        let info = self.get("info").unwrap_or(self);
+        info.get("meta version")?.as_integer()
+    }
+}
+
+/// Copies everything read through it into `buf`, if set.
+struct Recorder<R> {
+    inner: R,
+    buf: Option<Vec<u8>>,
+}
+
+impl<R: Read> Read for Recorder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, io::Error> {
+        let read = self.inner.read(buf)?;
+        if let Some(recorded) = &mut self.buf {
+            recorded.extend_from_slice(&buf[..read]);
+        }
+        Ok(read)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn torrent() {
+        let raw = fs::read("./test/ubuntu-18.04.4-live-server-amd64.iso.torrent").unwrap();
+        let info = info_bytes(raw.as_slice()).unwrap();
+        assert_eq!(info, raw[227..raw.len() - 1]);
+
+        let hash: String = info_hash(raw.as_slice())
+            .unwrap()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        assert_eq!(hash, "e73108cbd628fee5cf203acdf668c5bf45d07810");
+    }
+
+    #[test]
+    fn scan_header() {
+        let raw = fs::read("./test/ubuntu-18.04.4-live-server-amd64.iso.torrent").unwrap();
+        let info = crate::from_bytes(&info_bytes(raw.as_slice()).unwrap()).unwrap();
+        assert_eq!(
+            super::scan_header(raw.as_slice()).unwrap(),
+            TorrentSummary {
+                name: Some(b"ubuntu-18.04.4-live-server-amd64.iso".to_vec()),
+                piece_length: info["piece length"].as_integer(),
+                length: Some(912_261_120),
+                files: 0,
+            }
+        );
+
+        let input = b"d8:announce1:x4:infod5:filesld6:lengthi3e4:pathl1:aeed4:pathl1:be6:lengthi4eei0ee4:name3:dir12:piece lengthi2e6:pieces2:xxee";
+        assert_eq!(
+            super::scan_header(&input[..]).unwrap(),
+            TorrentSummary {
+                name: Some(b"dir".to_vec()),
+                piece_length: Some(2),
+                length: Some(7),
+                files: 2,
+            }
+        );
+        assert_eq!(
+            super::scan_header(&b"d4:infod4:namei1e6:lengthl1:xeee"[..]).unwrap(),
+            TorrentSummary::default()
+        );
+
+        for input in ["le", "d4:infoi1ee", "d1:ai1ee"] {
+            assert!(matches!(
+                super::scan_header(input.as_bytes()),
+                Err(Error::MissingInfo)
+            ));
+        }
+        assert!(matches!(
+            super::scan_header(&b"d4:infod6:lengthi1e"[..]),
+            Err(Error::UnexpectedEof { offset: 19 })
+        ));
+    }
+
+    #[test]
+    fn files() {
+        let mut builder = TorrentBuilder::new()
+            .name("dir")
+            .piece_length(4)
+            .pieces(vec![1; 20]);
+        for i in 0..100 {
+            builder = builder.file(["sub".to_string(), i.to_string()], i);
+        }
+        let raw = builder.build().unwrap().to_bytes();
+        let mut parser = Parser::new(raw.as_slice());
+        seek_files(&mut parser).unwrap();
+        let files: Vec<_> = super::files(&mut parser).collect::<Result<_, _>>().unwrap();
+        assert_eq!(files.len(), 100);
+        assert_eq!(
+            files[42],
+            FileEntry {
+                path: vec![b"sub".to_vec(), b"42".to_vec()],
+                length: 42,
+            }
+        );
+        // The rest of the `info` dictionary is still there to be read.
+        assert_eq!(parser.next_event().unwrap(), Event::ByteString(4));
+
+        let input = b"d4:infod5:filesld4:pathl1:ae6:lengthi1e6:md5sum1:xei1eeee";
+        let mut parser = Parser::new(&input[..]);
+        seek_files(&mut parser).unwrap();
+        let mut iter = super::files(&mut parser);
+        assert_eq!(iter.next().unwrap().unwrap().path, [b"a"]);
+        assert!(matches!(
+            iter.next(),
+            Some(Err(Error::InvalidMetainfo(
+                "file entries must be dictionaries"
+            )))
+        ));
+        assert!(iter.next().is_none());
+
+        let raw = fs::read("./test/ubuntu-18.04.4-live-server-amd64.iso.torrent").unwrap();
+        assert!(matches!(
+            seek_files(&mut Parser::new(raw.as_slice())),
+            Err(Error::InvalidMetainfo("missing files list"))
+        ));
+        let mut parser = Parser::new(&b"d6:lengthi1ee"[..]);
+        assert!(matches!(
+            super::files(&mut parser).next(),
+            Some(Err(Error::InvalidMetainfo("files must be a list")))
+        ));
+    }
+
+    #[test]
+    fn builder() {
+        let torrent = TorrentBuilder::new()
+            .name("dir")
+            .piece_length(4)
+            .pieces(vec![1; 40])
+            .file(["a", "b.txt"], 3)
+            .file(["c"], 5)
+            .announce("udp://one")
+            .announce_tier(["udp://one", "udp://two"])
+            .announce_tier(["udp://three"])
+            .creation_date(7)
+            .build()
+            .unwrap();
+        let mut expected = b"d8:announce9:udp://one13:announce-listll9:udp://one9:udp://twoel11:udp://threeee13:creation datei7e4:infod5:filesld6:lengthi3e4:pathl1:a5:b.txteed6:lengthi5e4:pathl1:ceee4:name3:dir12:piece lengthi4e6:pieces40:".to_vec();
+        expected.extend_from_slice(&[1; 40]);
+        expected.extend_from_slice(b"ee");
+        assert_eq!(torrent.to_bytes(), expected);
+        assert_eq!(
+            info_bytes(expected.as_slice()).unwrap(),
+            torrent["info"].to_bytes()
+        );
+
+        // A decoded torrent can be rebuilt from its fields.
+        let raw = fs::read("./test/ubuntu-18.04.4-live-server-amd64.iso.torrent").unwrap();
+        let decoded = crate::from_bytes(&raw).unwrap();
+        let info = &decoded["info"];
+        let rebuilt = TorrentBuilder::new()
+            .name(info["name"].as_str().unwrap())
+            .piece_length(info["piece length"].as_integer().unwrap())
+            .pieces(info["pieces"].as_bytes().unwrap().to_vec())
+            .length(info["length"].as_integer().unwrap())
+            .info()
+            .unwrap();
+        assert_eq!(rebuilt.to_bytes(), info_bytes(raw.as_slice()).unwrap());
+    }
+
+    #[test]
+    fn check_sizes() {
+        let raw = fs::read("./test/ubuntu-18.04.4-live-server-amd64.iso.torrent").unwrap();
+        let torrent = crate::from_bytes(&raw).unwrap();
+        super::check_sizes(&torrent).unwrap();
+        super::check_sizes(&torrent["info"]).unwrap();
+
+        for (input, reason) in [
+            (
+                &b"d12:piece lengthi0ee"[..],
+                "piece length must be positive",
+            ),
+            (b"d4:infod6:lengthi-1eee", "length must not be negative"),
+            (
+                b"d5:filesld6:lengthi1eed6:lengthi-1eeee",
+                "file lengths must not be negative",
+            ),
+        ] {
+            let value = crate::from_bytes(input).unwrap();
+            assert!(
+                matches!(super::check_sizes(&value), Err(Error::InvalidMetainfo(r)) if r == reason),
+                "{}",
+                reason
+            );
+        }
+    }
+
+    #[test]
+    fn builder_validation() {
+        let valid = TorrentBuilder::new()
+            .name("a")
+            .piece_length(1)
+            .pieces(vec![0; 20]);
+        assert!(valid.clone().length(0).build().is_ok());
+        let cases = [
+            (TorrentBuilder::new(), "missing name"),
+            (valid.clone().name(""), "missing name"),
+            (
+                valid.clone().piece_length(0),
+                "piece length must be positive",
+            ),
+            (
+                valid.clone().pieces(vec![0; 19]),
+                "pieces must be a multiple of 20 bytes",
+            ),
+            (valid.clone(), "missing length or files"),
+            (valid.clone().length(-1), "length must not be negative"),
+            (
+                valid.clone().length(1).file(["b"], 1),
+                "both length and files are set",
+            ),
+            (
+                valid.clone().file(Vec::<String>::new(), 1),
+                "file paths must not be empty",
+            ),
+            (
+                valid.clone().file(["b"], -1),
+                "file lengths must not be negative",
+            ),
+        ];
+        for (builder, reason) in cases {
+            assert!(
+                matches!(builder.build(), Err(Error::InvalidMetainfo(r)) if r == reason),
+                "{}",
+                reason
+            );
+        }
+    }
+
+    #[test]
+    fn v2() {
+        let mut input =
+            b"d4:infod9:file treed1:bd0:d6:lengthi0eee3:dird1:ad0:d6:lengthi5e11:pieces root32:"
+                .to_vec();
+        input.extend_from_slice(&[7; 32]);
+        input.extend_from_slice(b"eeee12:meta versioni2e4:name1:x12:piece lengthi16384eee");
+        let torrent = crate::from_bytes(&input).unwrap();
+        assert_eq!(torrent.meta_version(), Some(2));
+        assert_eq!(torrent["info"].meta_version(), Some(2));
+
+        let hash = info_hash_v2(input.as_slice()).unwrap();
+        assert_eq!(torrent.info_hash_v2(), Some(hash));
+        assert_eq!(
+            hash[..],
+            Sha256::digest(info_bytes(input.as_slice()).unwrap())[..]
+        );
+
+        let files = v2_files(&torrent["info"]).unwrap();
+        assert_eq!(
+            files,
+            [
+                V2File {
+                    path: vec![b"b"],
+                    length: 0,
+                    pieces_root: None
+                },
+                V2File {
+                    path: vec![b"dir", b"a"],
+                    length: 5,
+                    pieces_root: Some(&[7; 32]),
+                },
+            ]
+        );
+
+        let v1 = crate::from_bytes(b"d4:infod4:name1:xee").unwrap();
+        assert_eq!(v1.meta_version(), None);
+        assert_eq!(v2_files(&v1["info"]), None);
+        let malformed = crate::from_bytes(b"d9:file treed1:ai1eee").unwrap();
+        assert_eq!(v2_files(&malformed), None);
+    }
+
+    #[test]
+    fn non_canonical() {
+        // Unsorted keys inside `info` are kept as they are.
+        let input = b"d4:infod1:bi1e1:ai2ee3:zzz0:e";
+        assert_eq!(info_bytes(&input[..]).unwrap(), b"d1:bi1e1:ai2ee");
+
+        // Re-encoding sorts them, which changes the hash.
+        let value = crate::from_bytes(input).unwrap();
+        assert_ne!(value.info_hash().unwrap(), info_hash(&input[..]).unwrap());
+        assert_eq!(
+            value.info_hash().unwrap(),
+            info_hash(&b"d4:infod1:ai2e1:bi1eee"[..]).unwrap()
+        );
+        assert_eq!(crate::from_bytes(b"d4:infoi1ee").unwrap().info_hash(), None);
+        assert_eq!(Value::Integer(1).info_hash(), None);
+    }
+
+    #[test]
+    fn missing_info() {
+        for input in ["de", "li1ee", "d4:infoi1ee", "d1:ai1ee", "di1ei2ee"] {
+            assert!(
+                matches!(info_bytes(input.as_bytes()), Err(Error::MissingInfo)),
+                "{}",
+                input
+            );
+        }
+        assert!(matches!(
+            info_bytes("d4:infod".as_bytes()),
+            Err(Error::UnexpectedEof { offset: 8 })
+        ));
+    }
+}
@@ -0,0 +1,1359 @@
+use crate::Error;
+use crate::Value::{self, *};
+use alloc::{
+    borrow::Cow,
+    collections::BTreeMap,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::borrow::Borrow;
+use core::convert::TryFrom;
+use core::fmt;
+use core::iter::FromIterator;
+use core::ops::{Deref, Index};
+
+/// A dictionary key. Keys are arbitrary bytes and order bytewise, like the
+/// `Vec<u8>` inside; they borrow as `[u8]`, so maps can be queried with
+/// `b"..."` or `key.as_bytes()` without allocating.
+///
+/// ```
+/// use bencode_decode::{from_bytes, Key};
+///
+/// let value = from_bytes(b"d4:name4:spame").unwrap();
+/// let dict = value.as_dict().unwrap();
+/// assert!(dict.contains_key(&b"name"[..]));
+/// assert_eq!(dict.keys().next(), Some(&Key::from("name")));
+/// ```
+#[derive(PartialEq, Ord, PartialOrd, Eq, Hash, Clone, Default)]
+pub struct Key(Vec<u8>);
+
+impl Key {
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.0
+    }
+}
+
+/// Shows UTF-8 keys as strings and anything else as bytes.
+impl fmt::Debug for Key {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match core::str::from_utf8(&self.0) {
+            Ok(str) => fmt::Debug::fmt(str, f),
+            Err(_) => fmt::Debug::fmt(&self.0, f),
+        }
+    }
+}
+
+impl Deref for Key {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl Borrow<[u8]> for Key {
+    fn borrow(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl AsRef<[u8]> for Key {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<&str> for Key {
+    fn from(str: &str) -> Self {
+        Key(str.as_bytes().to_vec())
+    }
+}
+
+impl From<String> for Key {
+    fn from(str: String) -> Self {
+        Key(str.into_bytes())
+    }
+}
+
+impl From<&[u8]> for Key {
+    fn from(bytes: &[u8]) -> Self {
+        Key(bytes.to_vec())
+    }
+}
+
+impl From<Vec<u8>> for Key {
+    fn from(bytes: Vec<u8>) -> Self {
+        Key(bytes)
+    }
+}
+
+impl From<Key> for Vec<u8> {
+    fn from(key: Key) -> Self {
+        key.0
+    }
+}
+
+impl PartialEq<[u8]> for Key {
+    fn eq(&self, other: &[u8]) -> bool {
+        self.0 == other
+    }
+}
+
+impl PartialEq<str> for Key {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other.as_bytes()
+    }
+}
+
+impl PartialEq<&str> for Key {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == other.as_bytes()
+    }
+}
+
+impl Value {
+    pub fn empty_dict() -> Self {
+        Dictionary(BTreeMap::new())
+    }
+
+    pub fn empty_list() -> Self {
+        List(Vec::new())
+    }
+
+    pub fn integer(int: i64) -> Self {
+        Integer(int)
+    }
+
+    /// Returns the integer if this is an `Integer`, or a `RawInteger` that
+    /// fits into an `i64`.
+    pub fn as_integer(&self) -> Option<i64> {
+        match self {
+            Integer(int) => Some(*int),
+            RawInteger(_) => self.integer_digits()?.parse().ok(),
+            _ => None,
+        }
+    }
+
+    /// Returns the integer if this is an integer of any kind that fits into
+    /// a `u64`, for sizes and counts that can't be negative. Negative
+    /// integers give `None`; `u64::try_from` reports them as an error.
+    ///
+    /// ```
+    /// use bencode_decode::from_bytes;
+    ///
+    /// let info = from_bytes(b"d6:lengthi-1e12:piece lengthi16384ee").unwrap();
+    /// assert_eq!(info["piece length"].as_u64(), Some(16384));
+    /// assert_eq!(info["length"].as_u64(), None);
+    /// ```
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            Integer(int) => u64::try_from(*int).ok(),
+            BigInteger(_) | RawInteger(_) => self.integer_digits()?.parse().ok(),
+            _ => None,
+        }
+    }
+
+    /// The decimal digits of an integer of any kind, in canonical form for a
+    /// `RawInteger`. `None` for anything else, or a `RawInteger` that isn't
+    /// a decimal number.
+    fn integer_digits(&self) -> Option<Cow<'_, str>> {
+        match self {
+            Integer(int) => Some(Cow::Owned(int.to_string())),
+            BigInteger(digits) => Some(Cow::Borrowed(digits)),
+            RawInteger(digits) => crate::canonical_integer(digits.as_bytes())
+                .map(|canonical| Cow::Owned(String::from_utf8_lossy(&canonical).into_owned())),
+            _ => None,
+        }
+    }
+
+    /// Returns the raw bytes if this is a `ByteString`.
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            ByteString(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+
+    /// Returns the string if this is a `ByteString` holding valid UTF-8.
+    pub fn as_str(&self) -> Option<&str> {
+        self.as_bytes()
+            .and_then(|bytes| core::str::from_utf8(bytes).ok())
+    }
+
+    /// Returns the items if this is a `List`.
+    pub fn as_list(&self) -> Option<&[Value]> {
+        match self {
+            List(list) => Some(list),
+            _ => None,
+        }
+    }
+
+    /// Returns the entries if this is a `Dictionary`.
+    pub fn as_dict(&self) -> Option<&BTreeMap<Key, Value>> {
+        match self {
+            Dictionary(map) => Some(map),
+            _ => None,
+        }
+    }
+
+    /// Like `as_integer`, but consumes the value.
+    pub fn into_integer(self) -> Option<i64> {
+        self.as_integer()
+    }
+
+    /// Moves the bytes out if this is a `ByteString`, without copying them.
+    ///
+    /// ```
+    /// use bencode_decode::from_bytes;
+    ///
+    /// let info = from_bytes(b"d6:pieces4:\x01\x02\x03\x04e").unwrap();
+    /// let mut entries = info.into_dict().unwrap();
+    /// let pieces = entries.remove(&b"pieces"[..]).and_then(|p| p.into_bytes());
+    /// assert_eq!(pieces.unwrap(), [1, 2, 3, 4]);
+    /// ```
+    pub fn into_bytes(self) -> Option<Vec<u8>> {
+        match self {
+            ByteString(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+
+    /// Moves the items out if this is a `List`.
+    pub fn into_list(self) -> Option<Vec<Value>> {
+        match self {
+            List(list) => Some(list),
+            _ => None,
+        }
+    }
+
+    /// Moves the entries out if this is a `Dictionary`.
+    pub fn into_dict(self) -> Option<BTreeMap<Key, Value>> {
+        match self {
+            Dictionary(map) => Some(map),
+            _ => None,
+        }
+    }
+
+    /// The number of bytes of a `ByteString`, items of a `List` or entries of
+    /// a `Dictionary`. `None` for integers, which have no length.
+    ///
+    /// ```
+    /// use bencode_decode::from_bytes;
+    ///
+    /// let value = from_bytes(b"d5:filesli1ei2eee").unwrap();
+    /// assert_eq!(value.len(), Some(1));
+    /// assert_eq!(value["files"].len(), Some(2));
+    /// assert_eq!(value["files"][0].len(), None);
+    /// ```
+    pub fn len(&self) -> Option<usize> {
+        match self {
+            ByteString(bytes) => Some(bytes.len()),
+            List(list) => Some(list.len()),
+            Dictionary(map) => Some(map.len()),
+            Integer(_) | BigInteger(_) | RawInteger(_) => None,
+        }
+    }
+
+    /// Whether this is an empty `ByteString`, `List` or `Dictionary`. Always
+    /// `false` for integers.
+    pub fn is_empty(&self) -> bool {
+        self.len() == Some(0)
+    }
+
+    /// Iterates over the items if this is a `List`, and over nothing
+    /// otherwise.
+    ///
+    /// ```
+    /// use bencode_decode::from_bytes;
+    ///
+    /// let value = from_bytes(b"d5:filesld6:lengthi3eed6:lengthi4eeee").unwrap();
+    /// let total: i64 = value["files"]
+    ///     .iter()
+    ///     .filter_map(|file| file.get("length")?.as_integer())
+    ///     .sum();
+    /// assert_eq!(total, 7);
+    /// ```
+    pub fn iter(&self) -> impl Iterator<Item = &Value> {
+        self.as_list().unwrap_or_default().iter()
+    }
+
+    /// Iterates over the entries in key order if this is a `Dictionary`, and
+    /// over nothing otherwise.
+    pub fn entries(&self) -> impl Iterator<Item = (&[u8], &Value)> {
+        self.as_dict()
+            .into_iter()
+            .flatten()
+            .map(|(key, value)| (key.as_bytes(), value))
+    }
+
+    /// Iterates over the keys in order if this is a `Dictionary`, and over
+    /// nothing otherwise.
+    ///
+    /// ```
+    /// use bencode_decode::from_bytes;
+    ///
+    /// let value = from_bytes(b"d8:announce3:url4:infodee").unwrap();
+    /// assert!(value.keys().eq([&b"announce"[..], b"info"]));
+    /// ```
+    pub fn keys(&self) -> impl Iterator<Item = &[u8]> {
+        self.entries().map(|(key, _)| key)
+    }
+
+    /// The keys of a `Dictionary` in order, decoded as lossy UTF-8 for
+    /// logging and error messages. Empty for anything else.
+    ///
+    /// ```
+    /// use bencode_decode::from_bytes;
+    ///
+    /// let value = from_bytes(b"d4:name4:spam3:\xffxxi1ee").unwrap();
+    /// assert_eq!(value.dict_keys_lossy(), ["name", "\u{fffd}xx"]);
+    /// ```
+    pub fn dict_keys_lossy(&self) -> Vec<Cow<'_, str>> {
+        self.keys().map(String::from_utf8_lossy).collect()
+    }
+
+    /// Looks up `key` if this is a `Dictionary`, without allocating.
+    ///
+    /// ```
+    /// use bencode_decode::{from_bytes, Value};
+    ///
+    /// let value = from_bytes(b"d4:infod4:name4:spamee").unwrap();
+    /// let name = value.get("info").and_then(|info| info.get(b"name"));
+    /// assert_eq!(name, Some(&Value::ByteString(b"spam".to_vec())));
+    /// ```
+    pub fn get(&self, key: impl AsRef<[u8]>) -> Option<&Value> {
+        match self {
+            Dictionary(map) => map.get(key.as_ref()),
+            _ => None,
+        }
+    }
+
+    /// Whether this is a `Dictionary` holding `key`.
+    ///
+    /// ```
+    /// use bencode_decode::from_bytes;
+    ///
+    /// let torrent = from_bytes(b"d8:announce3:url4:infodee").unwrap();
+    /// assert!(torrent.contains_key("info"));
+    /// assert!(!torrent.contains_key("announce-list"));
+    /// ```
+    pub fn contains_key(&self, key: impl AsRef<[u8]>) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Walks a path of segments, looking up each one as a key in
+    /// dictionaries and as a decimal index in lists. Returns `None` as soon
+    /// as a segment doesn't resolve. A path given as a string can be split
+    /// into segments first.
+    ///
+    /// ```
+    /// use bencode_decode::from_bytes;
+    ///
+    /// let value = from_bytes(b"d4:infod5:filesld6:lengthi3eeeee").unwrap();
+    /// assert_eq!(value.get_path(["info", "files", "0", "length"]), Some(&3.into()));
+    /// assert_eq!(value.get_path("info/files/0".split('/')), Some(&value["info"]["files"][0]));
+    /// assert_eq!(value.get_path(["info", "files", "1"]), None);
+    /// ```
+    pub fn get_path<I>(&self, path: I) -> Option<&Value>
+    where
+        I: IntoIterator,
+        I::Item: AsRef<[u8]>,
+    {
+        path.into_iter()
+            .try_fold(self, |value, segment| match value {
+                List(list) => list.get(parse_index(segment.as_ref())?),
+                _ => value.get(segment),
+            })
+    }
+
+    /// Like `get`, but returns a mutable reference.
+    pub fn get_mut(&mut self, key: impl AsRef<[u8]>) -> Option<&mut Value> {
+        match self {
+            Dictionary(map) => map.get_mut(key.as_ref()),
+            _ => None,
+        }
+    }
+
+    /// Inserts an entry if this is a `Dictionary`, returning the value
+    /// previously stored under `key`. Does nothing for other variants.
+    ///
+    /// ```
+    /// use bencode_decode::from_bytes;
+    ///
+    /// let mut torrent = from_bytes(b"d8:announce3:old4:infod4:name4:spam7:privatei1eee").unwrap();
+    /// assert_eq!(torrent.insert("announce", "new").unwrap(), "old");
+    /// torrent.get_mut("info").unwrap().remove("private");
+    /// assert_eq!(torrent.to_bytes(), b"d8:announce3:new4:infod4:name4:spamee");
+    /// ```
+    pub fn insert(&mut self, key: impl Into<Key>, value: impl Into<Value>) -> Option<Value> {
+        match self {
+            Dictionary(map) => map.insert(key.into(), value.into()),
+            _ => None,
+        }
+    }
+
+    /// Removes an entry if this is a `Dictionary`, returning its value.
+    /// Does nothing for other variants.
+    pub fn remove(&mut self, key: impl AsRef<[u8]>) -> Option<Value> {
+        match self {
+            Dictionary(map) => map.remove(key.as_ref()),
+            _ => None,
+        }
+    }
+
+    /// Sorts the items of a `List` in place, by the derived `Ord`: byte
+    /// strings before integers, big integers, raw integers, lists and
+    /// dictionaries. Integers held as different variants sort by variant,
+    /// not by value. Does nothing for other variants.
+    ///
+    /// ```
+    /// use bencode_decode::from_bytes;
+    ///
+    /// let mut nodes = from_bytes(b"l1:b1:ai2e1:ae").unwrap();
+    /// nodes.sort_list();
+    /// nodes.dedup_list();
+    /// assert_eq!(nodes.to_bytes(), b"l1:a1:bi2ee");
+    /// ```
+    pub fn sort_list(&mut self) {
+        if let List(list) = self {
+            list.sort();
+        }
+    }
+
+    /// Removes consecutive equal items from a `List` in place, so sorting
+    /// first removes all duplicates. Does nothing for other variants.
+    pub fn dedup_list(&mut self) {
+        if let List(list) = self {
+            list.dedup();
+        }
+    }
+
+    /// Deep-merges `other` into this value. If both are dictionaries, each
+    /// entry of `other` is merged into the entry under the same key, or
+    /// inserted if there is none. Otherwise, including for lists, `other`
+    /// replaces this value.
+    ///
+    /// ```
+    /// use bencode_decode::{from_bytes, Value};
+    ///
+    /// let mut torrent = from_bytes(b"d8:announce3:old4:infod4:name4:spamee").unwrap();
+    /// let patch = from_bytes(b"d8:announce3:new7:comment2:hie").unwrap();
+    /// torrent.merge(patch);
+    /// assert_eq!(torrent.to_bytes(), b"d8:announce3:new7:comment2:hi4:infod4:name4:spamee");
+    /// ```
+    pub fn merge(&mut self, other: Value) {
+        match (self, other) {
+            (Dictionary(map), Dictionary(other)) => {
+                for (key, value) in other {
+                    match map.get_mut(&key) {
+                        Some(existing) => existing.merge(value),
+                        None => {
+                            map.insert(key, value);
+                        }
+                    }
+                }
+            }
+            (this, other) => *this = other,
+        }
+    }
+
+    /// Whether both values mean the same, i.e. encode to the same bytes.
+    /// This is `==` except for integers, which compare by value whether
+    /// they are held as an `Integer`, a `BigInteger` or a `RawInteger`.
+    /// Dictionaries are sorted by key already, see `OrderedValue::semantic_eq`
+    /// for ones that aren't.
+    ///
+    /// ```
+    /// use bencode_decode::{bencode, Value};
+    ///
+    /// let big = bencode!({ "length": (Value::BigInteger("42".into())) });
+    /// assert!(big.semantic_eq(&bencode!({ "length": 42 })));
+    /// assert!(!big.semantic_eq(&bencode!({ "length": 43 })));
+    /// ```
+    pub fn semantic_eq(&self, other: &Value) -> bool {
+        match (self.integer_digits(), other.integer_digits()) {
+            (Some(digits), Some(other)) => return digits == other,
+            (None, None) => {}
+            _ => return false,
+        }
+        match (self, other) {
+            (List(list), List(other)) => {
+                list.len() == other.len() && list.iter().zip(other).all(|(a, b)| a.semantic_eq(b))
+            }
+            (Dictionary(map), Dictionary(other)) => {
+                map.len() == other.len()
+                    && map
+                        .iter()
+                        .zip(other)
+                        .all(|((k, a), (l, b))| k == l && a.semantic_eq(b))
+            }
+            (this, other) => this == other,
+        }
+    }
+
+    /// Calls `f` on this value and everything nested in it, depth-first and
+    /// in encoding order, along with how deeply each is nested. Dictionary
+    /// keys aren't values and are skipped, only the values they map to are
+    /// visited. Nodes are kept on an explicit stack rather than the call
+    /// stack, so this is safe on arbitrarily deep trees.
+    ///
+    /// ```
+    /// use bencode_decode::{from_bytes, Value};
+    ///
+    /// let value = from_bytes(b"d4:infod5:filesld6:lengthi3eeee4:name4:spame").unwrap();
+    /// let (mut bytes, mut depth) = (0, 0);
+    /// value.walk(&mut |value, d| {
+    ///     bytes += value.as_bytes().map_or(0, <[u8]>::len);
+    ///     depth = depth.max(d);
+    /// });
+    /// assert_eq!((bytes, depth), (4, 4));
+    /// ```
+    pub fn walk(&self, f: &mut dyn FnMut(&Value, usize)) {
+        let mut stack = vec![(self, 0)];
+        while let Some((value, depth)) = stack.pop() {
+            f(value, depth);
+            // Reverse order, so that popping the stack visits items in order.
+            match value {
+                List(list) => stack.extend(list.iter().rev().map(|item| (item, depth + 1))),
+                Dictionary(map) => stack.extend(map.values().rev().map(|item| (item, depth + 1))),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Parses a list index path segment, which has to be plain decimal digits.
+fn parse_index(segment: &[u8]) -> Option<usize> {
+    if segment.is_empty() || !segment.iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+    core::str::from_utf8(segment).ok()?.parse().ok()
+}
+
+/// Looks up a dictionary entry, panicking if this is not a `Dictionary` or
+/// the key is missing. Use `Value::get` for a non-panicking lookup.
+///
+/// ```
+/// use bencode_decode::from_bytes;
+///
+/// let value = from_bytes(b"d4:infod5:filesld6:lengthi3eeeee").unwrap();
+/// assert_eq!(value["info"]["files"][0]["length"].as_integer(), Some(3));
+/// ```
+impl Index<&str> for Value {
+    type Output = Value;
+
+    fn index(&self, key: &str) -> &Value {
+        match self {
+            Dictionary(map) => map
+                .get(key.as_bytes())
+                .unwrap_or_else(|| panic!("no key {:?} in dictionary", key)),
+            other => panic!(
+                "cannot look up key {:?} in {} value",
+                key,
+                other.type_name()
+            ),
+        }
+    }
+}
+
+/// Looks up a list item, panicking if this is not a `List` or the index is
+/// out of bounds.
+impl Index<usize> for Value {
+    type Output = Value;
+
+    fn index(&self, index: usize) -> &Value {
+        match self {
+            List(list) => list.get(index).unwrap_or_else(|| {
+                panic!(
+                    "index {} out of bounds for list of length {}",
+                    index,
+                    list.len()
+                )
+            }),
+            other => panic!(
+                "cannot look up index {} in {} value",
+                index,
+                other.type_name()
+            ),
+        }
+    }
+}
+
+/// An empty dictionary, the usual root of a bencoded document.
+impl Default for Value {
+    fn default() -> Self {
+        Value::empty_dict()
+    }
+}
+
+impl From<&str> for Value {
+    fn from(str: &str) -> Self {
+        ByteString(str.as_bytes().to_vec())
+    }
+}
+
+impl From<String> for Value {
+    fn from(str: String) -> Self {
+        ByteString(str.into_bytes())
+    }
+}
+
+impl From<&[u8]> for Value {
+    fn from(bytes: &[u8]) -> Self {
+        ByteString(bytes.to_vec())
+    }
+}
+
+impl From<Vec<u8>> for Value {
+    fn from(bytes: Vec<u8>) -> Self {
+        ByteString(bytes)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(int: i64) -> Self {
+        Integer(int)
+    }
+}
+
+impl From<Vec<Value>> for Value {
+    fn from(list: Vec<Value>) -> Self {
+        List(list)
+    }
+}
+
+impl From<BTreeMap<Key, Value>> for Value {
+    fn from(map: BTreeMap<Key, Value>) -> Self {
+        Dictionary(map)
+    }
+}
+
+/// Collects values into a `List`.
+///
+/// ```
+/// use bencode_decode::Value;
+///
+/// let list: Value = (1..=3).map(Value::from).collect();
+/// assert_eq!(list.to_bytes(), b"li1ei2ei3ee");
+/// ```
+impl FromIterator<Value> for Value {
+    fn from_iter<I: IntoIterator<Item = Value>>(iter: I) -> Self {
+        List(iter.into_iter().collect())
+    }
+}
+
+/// Collects entries into a `Dictionary`. Of entries with the same key, the
+/// last one wins.
+///
+/// ```
+/// use bencode_decode::Value;
+///
+/// let files = [("b", 2), ("a", 1)];
+/// let dict: Value = files.iter().map(|&(name, len)| (name, Value::from(len))).collect();
+/// assert_eq!(dict.to_bytes(), b"d1:ai1e1:bi2ee");
+/// ```
+impl<K: Into<Key>> FromIterator<(K, Value)> for Value {
+    fn from_iter<I: IntoIterator<Item = (K, Value)>>(iter: I) -> Self {
+        Dictionary(
+            iter.into_iter()
+                .map(|(key, value)| (key.into(), value))
+                .collect(),
+        )
+    }
+}
+
+impl Value {
+    /// A short, stable name for the variant, for diagnostics like
+    /// `Error::TypeMismatch`: `"bytes"`, `"integer"`, `"list"` or
+    /// `"dictionary"`, and `"big integer"` for a `BigInteger`.
+    ///
+    /// ```
+    /// use bencode_decode::from_bytes;
+    ///
+    /// let value = from_bytes(b"d5:filesle4:name4:spame").unwrap();
+    /// assert_eq!(value.type_name(), "dictionary");
+    /// assert_eq!(value["name"].type_name(), "bytes");
+    /// assert_eq!(value["files"].type_name(), "list");
+    /// ```
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            ByteString(_) => "bytes",
+            Integer(_) => "integer",
+            BigInteger(_) => "big integer",
+            RawInteger(_) => "integer",
+            List(_) => "list",
+            Dictionary(_) => "dictionary",
+        }
+    }
+
+    fn mismatch(&self, expected: &'static str) -> Error {
+        Error::TypeMismatch {
+            expected,
+            found: self.type_name(),
+        }
+    }
+}
+
+/// Extracts an `Integer`, or a `RawInteger` that fits. Fails with
+/// `Error::TypeMismatch` for anything else, including a `BigInteger`.
+///
+/// ```
+/// use bencode_decode::from_bytes;
+/// use std::convert::TryFrom;
+///
+/// let info = from_bytes(b"d6:lengthi42e4:name4:spame").unwrap();
+/// assert_eq!(i64::try_from(info["length"].clone()).unwrap(), 42);
+/// assert!(i64::try_from(info["name"].clone()).is_err());
+/// ```
+impl TryFrom<Value> for i64 {
+    type Error = Error;
+
+    fn try_from(value: Value) -> Result<Self, Error> {
+        match value.as_integer() {
+            Some(int) => Ok(int),
+            None => Err(value.mismatch("integer")),
+        }
+    }
+}
+
+/// Extracts a non-negative integer, see `Value::as_u64`. Negative integers
+/// fail with `Error::TypeMismatch`, as does anything else that doesn't fit.
+impl TryFrom<Value> for u64 {
+    type Error = Error;
+
+    fn try_from(value: Value) -> Result<Self, Error> {
+        match (value.as_u64(), &value) {
+            (Some(int), _) => Ok(int),
+            (None, Integer(_)) => Err(Error::TypeMismatch {
+                expected: "non-negative integer",
+                found: "negative integer",
+            }),
+            (None, BigInteger(digits)) | (None, RawInteger(digits)) if digits.starts_with('-') => {
+                Err(Error::TypeMismatch {
+                    expected: "non-negative integer",
+                    found: "negative integer",
+                })
+            }
+            (None, other) => Err(other.mismatch("non-negative integer")),
+        }
+    }
+}
+
+/// Extracts a `ByteString` holding valid UTF-8.
+impl TryFrom<Value> for String {
+    type Error = Error;
+
+    fn try_from(value: Value) -> Result<Self, Error> {
+        match value {
+            ByteString(bytes) => String::from_utf8(bytes).map_err(|_| Error::TypeMismatch {
+                expected: "UTF-8 byte string",
+                found: "non-UTF-8 byte string",
+            }),
+            other => Err(other.mismatch("UTF-8 byte string")),
+        }
+    }
+}
+
+impl TryFrom<Value> for Vec<u8> {
+    type Error = Error;
+
+    fn try_from(value: Value) -> Result<Self, Error> {
+        match value {
+            ByteString(bytes) => Ok(bytes),
+            other => Err(other.mismatch("bytes")),
+        }
+    }
+}
+
+impl TryFrom<Value> for Vec<Value> {
+    type Error = Error;
+
+    fn try_from(value: Value) -> Result<Self, Error> {
+        match value {
+            List(list) => Ok(list),
+            other => Err(other.mismatch("list")),
+        }
+    }
+}
+
+impl TryFrom<Value> for BTreeMap<Key, Value> {
+    type Error = Error;
+
+    fn try_from(value: Value) -> Result<Self, Error> {
+        match value {
+            Dictionary(map) => Ok(map),
+            other => Err(other.mismatch("dictionary")),
+        }
+    }
+}
+
+/// Compares a `ByteString` with the bytes of a string, for concise
+/// assertions like `assert_eq!(value["name"], "spam")`. Other variants are
+/// never equal to a string.
+impl PartialEq<str> for Value {
+    fn eq(&self, other: &str) -> bool {
+        self.as_bytes() == Some(other.as_bytes())
+    }
+}
+
+impl PartialEq<&str> for Value {
+    fn eq(&self, other: &&str) -> bool {
+        *self == **other
+    }
+}
+
+impl PartialEq<[u8]> for Value {
+    fn eq(&self, other: &[u8]) -> bool {
+        self.as_bytes() == Some(other)
+    }
+}
+
+impl PartialEq<&[u8]> for Value {
+    fn eq(&self, other: &&[u8]) -> bool {
+        *self == **other
+    }
+}
+
+impl PartialEq<i64> for Value {
+    fn eq(&self, other: &i64) -> bool {
+        self.as_integer() == Some(*other)
+    }
+}
+
+/// Generates values that survive an `encode`/`decode` round trip, for fuzzing
+/// and property tests. Nesting is limited to `ARBITRARY_MAX_DEPTH` levels.
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for Value {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        arbitrary_value(u, 0)
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+const ARBITRARY_MAX_DEPTH: usize = 8;
+
+#[cfg(feature = "arbitrary")]
+fn arbitrary_value(u: &mut arbitrary::Unstructured, depth: usize) -> arbitrary::Result<Value> {
+    let kinds = if depth < ARBITRARY_MAX_DEPTH { 4 } else { 2 };
+    Ok(match u.int_in_range(0..=kinds)? {
+        0 => ByteString(u.arbitrary()?),
+        1 => Integer(u.arbitrary()?),
+        // Only integers outside of the `i64` range decode as `BigInteger`.
+        2 => {
+            let magnitude = (1 << 63) + 1 + u128::from(u.arbitrary::<u64>()?);
+            if u.arbitrary()? {
+                BigInteger(format!("-{}", magnitude))
+            } else {
+                BigInteger(magnitude.to_string())
+            }
+        }
+        3 => {
+            let mut list = Vec::new();
+            while u.arbitrary()? {
+                list.push(arbitrary_value(u, depth + 1)?);
+            }
+            List(list)
+        }
+        _ => {
+            let mut map = BTreeMap::new();
+            while u.arbitrary()? {
+                map.insert(Key(u.arbitrary()?), arbitrary_value(u, depth + 1)?);
+            }
+            Dictionary(map)
+        }
+    })
+}
+
+/// Builds a `Value` from JSON-like syntax. Dictionary keys are string or
+/// byte string literals, separated from their values by either `:` or `=>`;
+/// everything else goes through `Value::from`, so any expression with a
+/// `From` impl can be used. Expressions made of more than one token, like
+/// negative numbers, have to be wrapped in parentheses.
+///
+/// ```
+/// use bencode_decode::{bencode, encode};
+///
+/// let (name, raw) = ("spam", b"raw".to_vec());
+/// let value = bencode!({
+///     "info": { "name": name, "length": 42 },
+///     "list": [1, (-2), raw, []],
+/// });
+/// assert_eq!(
+///     encode(&value),
+///     b"d4:infod6:lengthi42e4:name4:spame4:listli1ei-2e3:rawleee"
+/// );
+/// assert_eq!(bencode!({ "length" => 912261120i64 }), bencode!({ "length": 912261120 }));
+/// ```
+#[macro_export]
+macro_rules! bencode {
+    ([ $($item:tt),* $(,)? ]) => {
+        $crate::Value::List(
+            ::core::iter::IntoIterator::into_iter([$($crate::bencode!($item)),*]).collect(),
+        )
+    };
+    ({ $($key:literal : $value:tt),* $(,)? }) => {
+        $crate::Value::Dictionary(
+            ::core::iter::IntoIterator::into_iter([$((
+                $crate::Key::from(::core::convert::AsRef::<[u8]>::as_ref($key)),
+                $crate::bencode!($value),
+            )),*])
+            .collect(),
+        )
+    };
+    ({ $($key:literal => $value:tt),* $(,)? }) => {
+        $crate::bencode!({ $($key : $value),* })
+    };
+    ($other:expr) => {
+        $crate::Value::from($other)
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::from_bytes;
+
+    #[test]
+    fn accessors() {
+        let value = from_bytes(b"d3:inti-3e4:listli1ee3:str4:spam3:bin1:\xffe").unwrap();
+        let dict = value.as_dict().unwrap();
+        assert_eq!(dict[b"int".as_slice()].as_integer(), Some(-3));
+        assert_eq!(dict[b"str".as_slice()].as_str(), Some("spam"));
+        assert_eq!(dict[b"str".as_slice()].as_bytes(), Some(b"spam".as_slice()));
+        assert_eq!(dict[b"bin".as_slice()].as_str(), None);
+        assert_eq!(dict[b"bin".as_slice()].as_bytes(), Some(b"\xff".as_slice()));
+        assert_eq!(dict[b"list".as_slice()].as_list().unwrap().len(), 1);
+
+        assert_eq!(value.as_integer(), None);
+        assert_eq!(value.as_bytes(), None);
+        assert_eq!(value.as_list(), None);
+        assert!(dict[b"int".as_slice()].as_dict().is_none());
+
+        let mut dict = value.into_dict().unwrap();
+        let mut take = |key: &str| dict.remove(key.as_bytes()).unwrap();
+        assert_eq!(take("int").into_integer(), Some(-3));
+        assert_eq!(take("bin").into_bytes().unwrap(), [0xff]);
+        assert_eq!(take("list").into_list().unwrap(), [Integer(1)]);
+        let str = take("str");
+        assert_eq!(str.clone().into_integer(), None);
+        assert_eq!(str.clone().into_list(), None);
+        assert_eq!(str.into_dict(), None);
+    }
+
+    #[test]
+    fn key() {
+        let value = from_bytes(b"d1:\xffi1e4:spami2ee").unwrap();
+        let dict = value.as_dict().unwrap();
+        assert_eq!(dict["spam".as_bytes()], Integer(2));
+        assert_eq!(dict[&b"\xff"[..]], Integer(1));
+        let keys: Vec<_> = dict.keys().collect();
+        assert_eq!(format!("{:?}", keys), "[\"spam\", [255]]");
+        assert!(keys[0] < keys[1]);
+        assert_eq!(*keys[0], "spam");
+        assert_eq!(keys[0].len(), 4);
+        assert_eq!(Key::from("spam"), Key::from(b"spam".to_vec()));
+        assert_eq!(Vec::from(Key::from("spam")), b"spam");
+    }
+
+    #[test]
+    fn constructors() {
+        assert_eq!(Value::default(), from_bytes(b"de").unwrap());
+        assert_eq!(Value::empty_dict(), Dictionary(BTreeMap::new()));
+        assert_eq!(Value::empty_list(), from_bytes(b"le").unwrap());
+        assert_eq!(Value::integer(-7), from_bytes(b"i-7e").unwrap());
+    }
+
+    #[test]
+    fn len() {
+        let value = from_bytes(b"d1:ali1ei2ee1:b0:1:cdee").unwrap();
+        assert_eq!(value.len(), Some(3));
+        assert_eq!(value["a"].len(), Some(2));
+        assert_eq!(value["b"].len(), Some(0));
+        assert_eq!(Integer(0).len(), None);
+        assert_eq!(BigInteger("18446744073709551616".into()).len(), None);
+
+        assert!(!value.is_empty());
+        assert!(value["b"].is_empty());
+        assert!(value["c"].is_empty());
+        assert!(List(vec![]).is_empty());
+        assert!(!Integer(0).is_empty());
+    }
+
+    #[test]
+    fn get_path() {
+        let value = from_bytes(b"d1:ali1ed1:bi2eee1:0i3e0:i4ee").unwrap();
+        assert_eq!(value.get_path(["a", "1", "b"]), Some(&Integer(2)));
+        assert_eq!(
+            value.get_path(vec![b"a".to_vec(), b"0".to_vec()]),
+            Some(&Integer(1))
+        );
+        assert_eq!(value.get_path(["0"]), Some(&Integer(3)));
+        assert_eq!(value.get_path([""]), Some(&Integer(4)));
+        assert_eq!(value.get_path(Vec::<&str>::new()), Some(&value));
+
+        assert_eq!(value.get_path(["a", "2"]), None);
+        assert_eq!(value.get_path(["a", "+1"]), None);
+        assert_eq!(value.get_path(["a", ""]), None);
+        assert_eq!(value.get_path(["a", "99999999999999999999999"]), None);
+        assert_eq!(value.get_path(["a", "0", "x"]), None);
+        assert_eq!(value.get_path(["missing"]), None);
+    }
+
+    #[test]
+    fn iter() {
+        let value = from_bytes(b"d1:ali1ei2ee1:bi3ee").unwrap();
+        let entries: Vec<_> = value.entries().collect();
+        assert_eq!(
+            entries,
+            [
+                (&b"a"[..], &List(vec![Integer(1), Integer(2)])),
+                (&b"b"[..], &Integer(3))
+            ]
+        );
+        let items: Vec<_> = value["a"].iter().collect();
+        assert_eq!(items, [&Integer(1), &Integer(2)]);
+
+        assert_eq!(value.iter().count(), 0);
+        assert_eq!(value["a"].entries().count(), 0);
+        assert_eq!(Integer(1).iter().count(), 0);
+        assert_eq!(ByteString(vec![1]).entries().count(), 0);
+    }
+
+    #[test]
+    fn get() {
+        let mut value = from_bytes(b"d1:ai1e1:bd1:ci2eee").unwrap();
+        assert_eq!(value.get("a"), Some(&Integer(1)));
+        assert_eq!(value.get(b"a"), Some(&Integer(1)));
+        assert_eq!(value.get(vec![b'b']).unwrap().get("c"), Some(&Integer(2)));
+        assert_eq!(value.get("missing"), None);
+        assert_eq!(Integer(1).get("a"), None);
+
+        *value.get_mut("b").unwrap().get_mut("c").unwrap() = Integer(3);
+        assert_eq!(value.get("b").unwrap().get("c"), Some(&Integer(3)));
+        assert_eq!(List(vec![]).get_mut("a"), None);
+    }
+
+    #[test]
+    fn from() {
+        assert_eq!(Value::from("spam"), ByteString(b"spam".to_vec()));
+        assert_eq!(
+            Value::from("spam".to_string()),
+            ByteString(b"spam".to_vec())
+        );
+        assert_eq!(Value::from(&b"\xff"[..]), ByteString(vec![0xff]));
+        assert_eq!(Value::from(vec![0xff]), ByteString(vec![0xff]));
+        assert_eq!(Value::from(-3), Integer(-3));
+        assert_eq!(Value::from(vec![Value::from(1)]), List(vec![Integer(1)]));
+        assert_eq!(Value::from(BTreeMap::new()), Dictionary(BTreeMap::new()));
+    }
+
+    #[cfg(feature = "arbitrary")]
+    #[test]
+    fn arbitrary_round_trip() {
+        use crate::{decode_slice, encode};
+        use arbitrary::{Arbitrary, Unstructured};
+
+        // A fixed xorshift stream keeps this deterministic; `fuzz/` explores
+        // further.
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        let mut bytes = vec![0; 4096];
+        for _ in 0..256 {
+            for byte in bytes.iter_mut() {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                *byte = state as u8;
+            }
+            let value = Value::arbitrary(&mut Unstructured::new(&bytes)).unwrap();
+            let encoded = encode(&value);
+            assert_eq!(from_bytes(&encoded).unwrap(), value);
+            assert_eq!(decode_slice(&encoded).unwrap().to_value(), value);
+
+            let _ = from_bytes(&bytes);
+            let _ = decode_slice(&bytes);
+        }
+    }
+
+    #[test]
+    fn try_from() {
+        let value = from_bytes(b"d3:bin1:\xff3:inti-3e4:listli1ee3:str4:spame").unwrap();
+        let get = |key: &str| value[key].clone();
+        assert_eq!(i64::try_from(get("int")).unwrap(), -3);
+        assert_eq!(String::try_from(get("str")).unwrap(), "spam");
+        assert_eq!(Vec::<u8>::try_from(get("bin")).unwrap(), [0xff]);
+        assert_eq!(Vec::<Value>::try_from(get("list")).unwrap(), [Integer(1)]);
+        assert_eq!(BTreeMap::try_from(value.clone()).unwrap().len(), 4);
+
+        assert!(matches!(
+            i64::try_from(get("str")),
+            Err(Error::TypeMismatch {
+                expected: "integer",
+                found: "bytes"
+            })
+        ));
+        assert_eq!(u64::try_from(get("int")).ok(), None);
+        assert_eq!(Integer(3).as_u64(), Some(3));
+        assert_eq!(u64::try_from(Integer(3)).unwrap(), 3);
+        assert_eq!(
+            u64::try_from(BigInteger("18446744073709551615".into())).unwrap(),
+            u64::MAX
+        );
+        assert_eq!(BigInteger("18446744073709551616".into()).as_u64(), None);
+        assert_eq!(get("str").as_u64(), None);
+        let error = u64::try_from(get("int")).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "expected non-negative integer, found negative integer"
+        );
+        assert!(matches!(
+            u64::try_from(BigInteger("-18446744073709551616".into())),
+            Err(Error::TypeMismatch {
+                found: "negative integer",
+                ..
+            })
+        ));
+        assert!(matches!(
+            u64::try_from(BigInteger("18446744073709551616".into())),
+            Err(Error::TypeMismatch {
+                found: "big integer",
+                ..
+            })
+        ));
+        assert!(matches!(
+            i64::try_from(BigInteger("9223372036854775808".into())),
+            Err(Error::TypeMismatch {
+                found: "big integer",
+                ..
+            })
+        ));
+        assert!(matches!(
+            String::try_from(get("bin")),
+            Err(Error::TypeMismatch {
+                found: "non-UTF-8 byte string",
+                ..
+            })
+        ));
+        assert!(matches!(
+            Vec::<u8>::try_from(get("list")),
+            Err(Error::TypeMismatch { found: "list", .. })
+        ));
+        assert!(matches!(
+            Vec::<Value>::try_from(value.clone()),
+            Err(Error::TypeMismatch {
+                found: "dictionary",
+                ..
+            })
+        ));
+        let error = BTreeMap::try_from(get("int")).unwrap_err();
+        assert_eq!(error.to_string(), "expected dictionary, found integer");
+    }
+
+    #[test]
+    fn insert_remove() {
+        let mut value = from_bytes(b"d1:ai1e1:bi2ee").unwrap();
+        assert_eq!(value.insert("c", 3), None);
+        assert_eq!(value.insert(b"a".to_vec(), "x"), Some(Integer(1)));
+        assert_eq!(value.remove("b"), Some(Integer(2)));
+        assert_eq!(value.remove("b"), None);
+        *value.get_mut("c").unwrap() = List(vec![]);
+        assert_eq!(value.to_bytes(), b"d1:a1:x1:clee");
+
+        let mut list = List(vec![Integer(1)]);
+        assert_eq!(list.insert("a", 1), None);
+        assert_eq!(list.remove("a"), None);
+        assert!(list.get_mut("a").is_none());
+        assert_eq!(list, List(vec![Integer(1)]));
+    }
+
+    #[test]
+    fn semantic_eq() {
+        let value = from_bytes(b"d1:ali1ei-2ee1:bd1:ci3eee").unwrap();
+        assert!(value.semantic_eq(&value.clone()));
+        let mut big = value.clone();
+        *big.get_mut("a").unwrap() = List(vec![BigInteger("1".into()), BigInteger("-2".into())]);
+        assert_ne!(big, value);
+        assert!(big.semantic_eq(&value));
+        assert!(value.semantic_eq(&big));
+        assert!(!value.semantic_eq(&from_bytes(b"d1:ali1ei-2ee1:bd1:ci4eee").unwrap()));
+        assert!(!value.semantic_eq(&from_bytes(b"d1:ali1ei-2ee1:dd1:ci3eee").unwrap()));
+        assert!(!value.semantic_eq(&from_bytes(b"d1:ali1ee1:bd1:ci3eee").unwrap()));
+        assert!(!Integer(1).semantic_eq(&ByteString(b"1".to_vec())));
+    }
+
+    #[test]
+    fn keys() {
+        let value = from_bytes(b"d1:bi1e1:ad1:c0:ee").unwrap();
+        assert_eq!(value.keys().collect::<Vec<_>>(), [b"a", b"b"]);
+        assert!(value.contains_key("a"));
+        assert!(value.contains_key(b"b"));
+        assert!(!value.contains_key("c"));
+        assert!(value["a"].contains_key("c"));
+        for other in [
+            Integer(1),
+            ByteString(b"a".to_vec()),
+            List(vec![ByteString(b"a".to_vec())]),
+        ] {
+            assert_eq!(other.keys().count(), 0);
+            assert!(!other.contains_key("a"));
+        }
+    }
+
+    #[test]
+    fn from_iter() {
+        let list: Value = vec![Integer(1), "a".into()].into_iter().collect();
+        assert_eq!(list, List(vec![Integer(1), ByteString(b"a".to_vec())]));
+        assert_eq!(
+            core::iter::empty::<Value>().collect::<Value>(),
+            List(vec![])
+        );
+
+        let dict: Value = vec![(b"b".to_vec(), Integer(1)), (b"a".to_vec(), Integer(2))]
+            .into_iter()
+            .collect();
+        assert_eq!(dict.to_bytes(), b"d1:ai2e1:bi1ee");
+        let dict: Value = vec![("a", Integer(1)), ("a", Integer(2))]
+            .into_iter()
+            .collect();
+        assert_eq!(dict, from_bytes(b"d1:ai2ee").unwrap());
+        let dict: Value = dict
+            .entries()
+            .map(|(key, value)| (key, List(vec![value.clone()])))
+            .collect();
+        assert_eq!(dict.to_bytes(), b"d1:ali2eee");
+    }
+
+    #[test]
+    fn walk() {
+        let value = from_bytes(b"d1:ali1eli2eee1:bd1:c0:ee").unwrap();
+        let mut visited = vec![];
+        value.walk(&mut |value, depth| visited.push((value.to_bytes(), depth)));
+        let expected: Vec<(&[u8], usize)> = vec![
+            (b"d1:ali1eli2eee1:bd1:c0:ee", 0),
+            (b"li1eli2eee", 1),
+            (b"i1e", 2),
+            (b"li2ee", 2),
+            (b"i2e", 3),
+            (b"d1:c0:e", 1),
+            (b"0:", 2),
+        ];
+        assert_eq!(visited.len(), expected.len());
+        for ((bytes, depth), (expected, expected_depth)) in visited.iter().zip(expected) {
+            assert_eq!((bytes.as_slice(), *depth), (expected, expected_depth));
+        }
+
+        let mut deep = Integer(0);
+        for _ in 0..100_000 {
+            deep = List(vec![deep]);
+        }
+        let mut nodes = 0;
+        deep.walk(&mut |_, _| nodes += 1);
+        assert_eq!(nodes, 100_001);
+        // Dropping is recursive, so unwind the list by hand.
+        while let List(mut list) = deep {
+            deep = list.pop().unwrap();
+        }
+    }
+
+    #[test]
+    fn sort_list() {
+        let mut value = from_bytes(b"li2e1:ali1eei2ei1e1:ae").unwrap();
+        value.dedup_list();
+        assert_eq!(value.to_bytes(), b"li2e1:ali1eei2ei1e1:ae");
+        value.sort_list();
+        assert_eq!(value.to_bytes(), b"l1:a1:ai1ei2ei2eli1eee");
+        value.dedup_list();
+        assert_eq!(value.to_bytes(), b"l1:ai1ei2eli1eee");
+
+        let mut dict = from_bytes(b"d1:bi1e1:ai2ee").unwrap();
+        let before = dict.clone();
+        dict.sort_list();
+        dict.dedup_list();
+        assert_eq!(dict, before);
+    }
+
+    #[test]
+    fn merge() {
+        let mut value = from_bytes(b"d1:ad1:bi1e1:ci2ee1:lli1ee1:si0ee").unwrap();
+        value.merge(from_bytes(b"d1:ad1:ci3e1:di4ee1:lli2ee1:sd1:xi5eee").unwrap());
+        assert_eq!(
+            value.to_bytes(),
+            &b"d1:ad1:bi1e1:ci3e1:di4ee1:lli2ee1:sd1:xi5eee"[..]
+        );
+
+        value.merge(Integer(7));
+        assert_eq!(value, Integer(7));
+    }
+
+    #[test]
+    fn eq_native() {
+        let value = from_bytes(b"d3:bin1:\xff3:inti-3e3:str4:spame").unwrap();
+        assert_eq!(value["str"], "spam");
+        assert_eq!(value["str"], *"spam");
+        assert_eq!(value["str"], b"spam"[..]);
+        assert_eq!(value["str"], &b"spam"[..]);
+        assert_eq!(value["bin"], &b"\xff"[..]);
+        assert_eq!(value["int"], -3);
+
+        assert_ne!(value["str"], "eggs");
+        assert_ne!(value["int"], "-3");
+        assert_ne!(value["str"], 4);
+        assert_ne!(value, "");
+        assert_ne!(BigInteger("9223372036854775808".into()), i64::MIN);
+    }
+
+    #[test]
+    fn macro_() {
+        assert_eq!(crate::bencode!([]), List(vec![]));
+        assert_eq!(crate::bencode!({}), Dictionary(BTreeMap::new()));
+        assert_eq!(
+            crate::bencode!({ b"a": [1, { "b": "c" }], "d": (i64::MAX) }),
+            from_bytes(b"d1:ali1ed1:b1:cee1:di9223372036854775807ee").unwrap()
+        );
+    }
+
+    #[test]
+    fn hash() {
+        use std::collections::{HashMap, HashSet};
+        let list = from_bytes(b"li1e1:ai1ed1:ai1ee1:ad1:ai1eee").unwrap();
+        let unique: HashSet<_> = list.as_list().unwrap().iter().collect();
+        assert_eq!(unique.len(), 3);
+
+        let mut counts = HashMap::new();
+        for item in list.as_list().unwrap() {
+            *counts.entry(item.clone()).or_insert(0) += 1;
+        }
+        assert_eq!(counts[&ByteString(b"a".to_vec())], 2);
+        assert_eq!(counts[&Integer(1)], 2);
+    }
+
+    #[test]
+    fn index() {
+        let value = from_bytes(b"d1:ali1ed1:bi2eee1:c0:e").unwrap();
+        assert_eq!(value["a"][0], Integer(1));
+        assert_eq!(value["a"][1]["b"], Integer(2));
+        assert_eq!(value["c"], ByteString(vec![]));
+    }
+
+    #[test]
+    #[should_panic(expected = "no key \"missing\" in dictionary")]
+    fn index_missing_key() {
+        let _ = &from_bytes(b"de").unwrap()["missing"];
+    }
+
+    #[test]
+    #[should_panic(expected = "index 1 out of bounds for list of length 1")]
+    fn index_out_of_bounds() {
+        let _ = &from_bytes(b"li1ee").unwrap()[1];
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot look up index 0 in integer value")]
+    fn index_wrong_type() {
+        let _ = &Integer(1)[0];
+    }
+}
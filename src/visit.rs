@@ -0,0 +1,362 @@
+use crate::io::Read;
+use crate::{Error, Event, Items, Parser};
+use alloc::{vec, vec::Vec};
+use core::cmp::Ordering;
+
+/// Callbacks for `decode_visit`, which walks a document SAX-style without
+/// building a tree. Value callbacks get the dictionary key the value is
+/// stored under, or `None` inside lists and at the top level. Every method
+/// does nothing by default, and returning an error aborts decoding.
+pub trait Visitor {
+    fn on_integer(&mut self, _key: Option<&[u8]>, _int: i64) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// See `Value::BigInteger`.
+    fn on_big_integer(&mut self, _key: Option<&[u8]>, _digits: &str) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// See `Value::RawInteger`.
+    fn on_raw_integer(&mut self, _key: Option<&[u8]>, _digits: &str) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Called with the contents of each byte string that `wants_bytes`
+    /// asked for.
+    fn on_bytes(&mut self, _key: Option<&[u8]>, _bytes: &[u8]) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Decides whether the byte string of length `len` is read and passed to
+    /// `on_bytes`, or skipped without allocating anything. Defaults to
+    /// reading every string.
+    fn wants_bytes(&mut self, _key: Option<&[u8]>, _len: usize) -> bool {
+        true
+    }
+
+    fn on_list_start(&mut self, _key: Option<&[u8]>) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn on_list_end(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn on_dict_start(&mut self, _key: Option<&[u8]>) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn on_dict_end(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// An open container. Dictionaries hold the key of the value about to be
+/// read, if any, and the previous key for strict mode.
+enum Frame {
+    List,
+    Dict {
+        key: Option<Vec<u8>>,
+        last: Option<Vec<u8>>,
+    },
+}
+
+/// Walks the next value from `parser`, reporting its parts to `visitor` as
+/// they are read. Only dictionary keys are allocated by the driver itself,
+/// so a few fields can be picked out of a huge document cheaply. Checks the
+/// same limits and, in strict mode, the same key order as `decode`.
+///
+/// ```
+/// use bencode_decode::{decode_visit, Error, Parser, Visitor};
+///
+/// #[derive(Default)]
+/// struct TotalLength(i64);
+///
+/// impl Visitor for TotalLength {
+///     fn on_integer(&mut self, key: Option<&[u8]>, int: i64) -> Result<(), Error> {
+///         if key == Some(b"length") {
+///             self.0 += int;
+///         }
+///         Ok(())
+///     }
+///
+///     fn wants_bytes(&mut self, _: Option<&[u8]>, _: usize) -> bool {
+///         false
+///     }
+/// }
+///
+/// let input = b"d5:filesld6:lengthi3eed6:lengthi4eee6:pieces3:xyze";
+/// let mut total = TotalLength::default();
+/// decode_visit(&mut Parser::new(&input[..]), &mut total).unwrap();
+/// assert_eq!(total.0, 7);
+/// ```
+pub fn decode_visit<R: Read, V: Visitor + ?Sized>(
+    parser: &mut Parser<R>,
+    visitor: &mut V,
+) -> Result<(), Error> {
+    let mut stack: Vec<Frame> = vec![];
+    let mut items = Items::new(parser.max_items);
+    loop {
+        let event = parser.next_event()?;
+        let offset = parser.token_start;
+        if !matches!(event, Event::End | Event::Eof) {
+            items.count(offset)?;
+        }
+        let key = match stack.last_mut() {
+            Some(Frame::Dict {
+                key: key @ None,
+                last,
+            }) => {
+                match event {
+                    Event::ByteString(_) | Event::DictKey(_) => {}
+                    Event::End => {
+                        stack.pop();
+                        visitor.on_dict_end()?;
+                        if stack.is_empty() {
+                            return Ok(());
+                        }
+                        continue;
+                    }
+                    Event::Eof => return Err(Error::UnexpectedEof { offset }),
+                    _ => return Err(Error::NonStringKey { offset }),
+                }
+                let next = parser.read_bytes()?;
+                if parser.strict {
+                    match last.as_ref().map(|last| next.cmp(last)) {
+                        Some(Ordering::Less) => return Err(Error::UnsortedKeys { offset }),
+                        Some(Ordering::Equal) => return Err(Error::DuplicateKey { offset }),
+                        _ => {}
+                    }
+                }
+                *key = Some(next);
+                continue;
+            }
+            Some(Frame::Dict { key, .. }) => {
+                if event == Event::End {
+                    return Err(Error::OddDictLength { offset });
+                }
+                key.take()
+            }
+            Some(Frame::List) | None => None,
+        };
+        let opened = match event {
+            Event::ByteString(len) | Event::DictKey(len) => {
+                if visitor.wants_bytes(key.as_deref(), len) {
+                    visitor.on_bytes(key.as_deref(), &parser.read_bytes()?)?;
+                }
+                None
+            }
+            Event::Integer(int) => {
+                visitor.on_integer(key.as_deref(), int)?;
+                None
+            }
+            Event::BigInteger(digits) => {
+                visitor.on_big_integer(key.as_deref(), &digits)?;
+                None
+            }
+            Event::RawInteger(digits) => {
+                visitor.on_raw_integer(key.as_deref(), &digits)?;
+                None
+            }
+            Event::ListStart | Event::DictStart => {
+                if stack.len() >= parser.max_depth {
+                    return Err(Error::DepthLimitExceeded { offset });
+                }
+                if event == Event::ListStart {
+                    visitor.on_list_start(key.as_deref())?;
+                    Some(Frame::List)
+                } else {
+                    visitor.on_dict_start(key.as_deref())?;
+                    Some(Frame::Dict {
+                        key: None,
+                        last: None,
+                    })
+                }
+            }
+            Event::End => match stack.pop() {
+                Some(_) => {
+                    visitor.on_list_end()?;
+                    None
+                }
+                None => return Err(Error::UnexpectedEnd { offset }),
+            },
+            Event::Eof => return Err(Error::UnexpectedEof { offset }),
+        };
+        if let (Some(key), Some(Frame::Dict { last, .. })) = (key, stack.last_mut()) {
+            *last = Some(key);
+        }
+        match opened {
+            Some(frame) => stack.push(frame),
+            None if stack.is_empty() => return Ok(()),
+            None => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use alloc::{format, string::String};
+    #[cfg(feature = "std")]
+    use std::fs::File;
+
+    /// Records every callback as a line of text.
+    #[derive(Default)]
+    struct Log(Vec<String>);
+
+    impl Visitor for Log {
+        fn on_integer(&mut self, key: Option<&[u8]>, int: i64) -> Result<(), Error> {
+            self.0.push(format!(
+                "{:?} int {}",
+                key.map(String::from_utf8_lossy),
+                int
+            ));
+            Ok(())
+        }
+
+        fn on_big_integer(&mut self, key: Option<&[u8]>, digits: &str) -> Result<(), Error> {
+            self.0.push(format!(
+                "{:?} big {}",
+                key.map(String::from_utf8_lossy),
+                digits
+            ));
+            Ok(())
+        }
+
+        fn on_bytes(&mut self, key: Option<&[u8]>, bytes: &[u8]) -> Result<(), Error> {
+            self.0.push(format!(
+                "{:?} bytes {}",
+                key.map(String::from_utf8_lossy),
+                String::from_utf8_lossy(bytes)
+            ));
+            Ok(())
+        }
+
+        fn wants_bytes(&mut self, _: Option<&[u8]>, len: usize) -> bool {
+            len < 10
+        }
+
+        fn on_list_start(&mut self, key: Option<&[u8]>) -> Result<(), Error> {
+            self.0
+                .push(format!("{:?} list", key.map(String::from_utf8_lossy)));
+            Ok(())
+        }
+
+        fn on_list_end(&mut self) -> Result<(), Error> {
+            self.0.push("end list".into());
+            Ok(())
+        }
+
+        fn on_dict_start(&mut self, key: Option<&[u8]>) -> Result<(), Error> {
+            self.0
+                .push(format!("{:?} dict", key.map(String::from_utf8_lossy)));
+            Ok(())
+        }
+
+        fn on_dict_end(&mut self) -> Result<(), Error> {
+            self.0.push("end dict".into());
+            Ok(())
+        }
+    }
+
+    fn visit(input: &str) -> Result<Vec<String>, Error> {
+        let mut log = Log::default();
+        decode_visit(&mut Parser::new(input.as_bytes()), &mut log)?;
+        Ok(log.0)
+    }
+
+    #[test]
+    fn callbacks() {
+        assert_eq!(
+            visit("d1:ali1e10:skipped...e1:bd1:ci18446744073709551616eee").unwrap(),
+            [
+                "None dict",
+                "Some(\"a\") list",
+                "None int 1",
+                "end list",
+                "Some(\"b\") dict",
+                "Some(\"c\") big 18446744073709551616",
+                "end dict",
+                "end dict",
+            ]
+        );
+        assert_eq!(visit("3:abc").unwrap(), ["None bytes abc"]);
+        assert_eq!(visit("le").unwrap(), ["None list", "end list"]);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn torrent() {
+        #[derive(Default)]
+        struct Name(Option<String>);
+
+        impl Visitor for Name {
+            fn wants_bytes(&mut self, key: Option<&[u8]>, _: usize) -> bool {
+                key == Some(b"name")
+            }
+
+            fn on_bytes(&mut self, _: Option<&[u8]>, bytes: &[u8]) -> Result<(), Error> {
+                self.0 = Some(String::from_utf8_lossy(bytes).into_owned());
+                Ok(())
+            }
+        }
+
+        let f = File::open("./test/ubuntu-18.04.4-live-server-amd64.iso.torrent").unwrap();
+        let mut parser = Parser::new(f);
+        let mut name = Name::default();
+        decode_visit(&mut parser, &mut name).unwrap();
+        assert_eq!(name.0.unwrap(), "ubuntu-18.04.4-live-server-amd64.iso");
+        assert_eq!(parser.next_event().unwrap(), Event::Eof);
+    }
+
+    #[test]
+    fn malformed() {
+        assert!(matches!(
+            visit("di1ei2ee"),
+            Err(Error::NonStringKey { offset: 1 })
+        ));
+        assert!(matches!(
+            visit("d1:ae"),
+            Err(Error::OddDictLength { offset: 4 })
+        ));
+        assert!(matches!(
+            visit("d"),
+            Err(Error::UnexpectedEof { offset: 1 })
+        ));
+        assert!(matches!(
+            visit("e"),
+            Err(Error::UnexpectedEnd { offset: 0 })
+        ));
+
+        let mut parser = Parser::new("d1:bi1e1:ai2ee".as_bytes());
+        parser.set_strict(true);
+        assert!(matches!(
+            decode_visit(&mut parser, &mut Log::default()),
+            Err(Error::UnsortedKeys { offset: 7 })
+        ));
+        let mut parser = Parser::with_max_depth("llee".as_bytes(), 1);
+        assert!(matches!(
+            decode_visit(&mut parser, &mut Log::default()),
+            Err(Error::DepthLimitExceeded { offset: 1 })
+        ));
+    }
+
+    #[test]
+    fn abort() {
+        struct Stop;
+
+        impl Visitor for Stop {
+            fn on_integer(&mut self, _: Option<&[u8]>, _: i64) -> Result<(), Error> {
+                Err(Error::Custom("found it".into()))
+            }
+        }
+
+        let mut parser = Parser::new("li1ei2ee".as_bytes());
+        assert!(matches!(
+            decode_visit(&mut parser, &mut Stop),
+            Err(Error::Custom(msg)) if msg == "found it"
+        ));
+        assert_eq!(parser.next_event().unwrap(), Event::Integer(2));
+    }
+}